@@ -1,29 +1,64 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc, Mutex,
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
 };
 
+use chrono::{DateTime, Utc};
 use crossbeam::channel::{unbounded, Receiver, Sender};
 use egui::{
-    collapsing_header, epaint::Shadow, scroll_area::ScrollBarVisibility, vec2, Button,
-    CentralPanel, CursorIcon, FontDefinitions, Frame, Margin, Pos2, Rect, RichText, Rounding,
-    ScrollArea, TopBottomPanel, ViewportBuilder, ViewportClass, ViewportCommand, ViewportId,
-    WindowLevel,
+    collapsing_header, epaint::Shadow, scroll_area::ScrollBarVisibility, vec2, Align, Button,
+    CentralPanel, CursorIcon, DragValue, FontDefinitions, Frame, Key, KeyboardShortcut, Label,
+    Layout, Margin, Modifiers, Pos2, ProgressBar, Rect, Response, RichText, Rounding, ScrollArea,
+    TextEdit, TextStyle, TopBottomPanel, ViewportBuilder, ViewportClass, ViewportCommand,
+    ViewportId, WindowLevel,
 };
 use egui_modal::{Modal, ModalStyle};
 use egui_phosphor::regular;
 use lazy_static::lazy_static;
 
+#[cfg(target_os = "linux")]
+use crate::backend::dbus::DbusService;
 use crate::{
-    backend::bookmark_manager::BookmarkManager,
+    backend::{
+        archive::{archive_page, archived_page, ArchiveResult},
+        bookmark_manager::{read_export, BookmarkManager},
+        cache::cache_preview_image,
+        favicons::FaviconCache,
+        file_writer::flush_writes,
+        history::{self, History},
+        jobs::{Job, JobId, JobKind, JobManager, JobOutput, JobState},
+        link_checker::{is_short_link, LinkCheck, LinkChecker},
+        models::{LinkHealth, LinkModel, TopicModel, TrashEntry, TrashedItem},
+        page_meta::PageMeta,
+        query::Query,
+        search::SearchHit,
+        settings::AppSettings,
+        url_rules::UrlRules,
+    },
     ui::{
-        about::AboutViewport, components::custom_button, link_viewport::LinkViewport,
+        about::AboutViewport,
+        command_palette::{self, CommandPalette, PaletteCommand},
+        components::{
+            custom_button, drag_handle, focus_stroke, highlighted_text, link_preview_card,
+            selection_fill, site_icon,
+        },
+        link_viewport::LinkViewport,
+        reader_viewport::ReaderViewport,
+        toast::Toast,
         topic_viewport::TopicViewport,
     },
     utils::{
         backup_bookmarks, calc_btn_size_from_text,
-        enums::{AppMessage, AppPage, AppTheme, BookmarkItem, OpenLocationType},
-        open_file_location, open_urls,
+        enums::{
+            AppMessage, AppPage, AppTheme, BookmarkItem, LinkReportAction, LinkReportTab,
+            OpenLocationType, TreeFocus,
+        },
+        format_age, group_by_topic, links_to_json, open_file, open_file_location, open_urls,
+        write_export,
     },
 };
 
@@ -31,19 +66,29 @@ lazy_static! {
     static ref ABOUT_VIEWPORT: AboutViewport = AboutViewport::default();
     static ref ADD_TOPIC_VIEWPORT: Mutex<TopicViewport> = Mutex::new(TopicViewport::default());
     static ref ADD_LINK_VIEWPORT: Mutex<LinkViewport> = Mutex::new(LinkViewport::default());
+    static ref READER_VIEWPORT: Mutex<ReaderViewport> = Mutex::new(ReaderViewport::default());
     static ref MIN_SIZE: [f32; 2] = [320.0, 240.0];
 }
 
+const MAX_SEARCH_RESULTS: usize = 100;
+/// Topic the link report moves broken links to.
+const BROKEN_TOPIC: &str = "Broken";
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
 #[derive(Debug)]
 pub struct StashApp {
     is_debug: bool,
     is_first_run: bool,
+    settings: AppSettings,
     initial_viewport_center: Pos2,
     window_level: WindowLevel,
 
     is_about_open: Arc<AtomicBool>,
     is_add_topic_open: Arc<AtomicBool>,
     is_add_link_open: Arc<AtomicBool>,
+    is_reader_open: Arc<AtomicBool>,
 
     bookmark_manager: BookmarkManager,
     expanded_topics: Vec<bool>,
@@ -51,20 +96,66 @@ pub struct StashApp {
     app_page: AppPage,
     current_theme: AppTheme,
 
+    search_query: String,
+    searched_query: String,
+    search_results: Vec<SearchHit>,
+    search_error: Option<String>,
+
+    expanded_smart_topics: Vec<bool>,
+    smart_topic_hits: Option<Vec<Vec<SearchHit>>>,
+
+    duplicate_groups: Option<Vec<Vec<(String, LinkModel)>>>,
+
+    favicons: FaviconCache,
+
+    link_report_tab: LinkReportTab,
+
+    jobs: JobManager,
+    is_jobs_panel_open: bool,
+    /// What the running redirect lookups found so far, by job
+    resolved: HashMap<JobId, Vec<LinkCheck>>,
+    import_path: String,
+
+    url_rules_text: String,
+    url_cleanups: Option<Vec<(String, LinkModel, LinkModel)>>,
+
+    command_palette: CommandPalette,
+
+    tree_focus: Option<TreeFocus>,
+    scroll_to_focus: bool,
+    pending_removal: Option<AppMessage>,
+
+    selected_links: Vec<(String, LinkModel)>,
+    selection_anchor: Option<TreeFocus>,
+    bulk_tag: String,
+
+    history: History,
+    toast: Option<Toast>,
+
     tx: Sender<AppMessage>,
     rx: Receiver<AppMessage>,
+
+    #[cfg(target_os = "linux")]
+    dbus_service: Option<DbusService>,
 }
 
 impl StashApp {
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Self {
+    pub fn new(settings: AppSettings) -> Self {
         let (tx, rx) = unbounded::<AppMessage>();
 
         let is_debug = cfg!(debug_assertions);
-        let bookmark_manager = BookmarkManager::new(is_debug);
+        let mut bookmark_manager = BookmarkManager::new(is_debug);
+        // * Edits don't wait on the disk, pending saves are flushed on exit
+        bookmark_manager.set_background_saves(true);
+        bookmark_manager.purge_trash(settings.trash_retention_days);
         let expanded_topics = bookmark_manager
             .get_topics()
             .iter()
+            .map(|topic| settings.expanded_topics.contains(&topic.name))
+            .collect();
+        let expanded_smart_topics = bookmark_manager
+            .get_smart_topics()
+            .iter()
             .map(|_| false)
             .collect();
 
@@ -72,20 +163,65 @@ impl StashApp {
             is_debug,
             is_first_run: true,
             initial_viewport_center: Pos2::ZERO,
-            window_level: WindowLevel::Normal,
+            window_level: if settings.always_on_top {
+                WindowLevel::AlwaysOnTop
+            } else {
+                WindowLevel::Normal
+            },
 
             is_about_open: Arc::new(AtomicBool::new(false)),
             is_add_topic_open: Arc::new(AtomicBool::new(false)),
             is_add_link_open: Arc::new(AtomicBool::new(false)),
+            is_reader_open: Arc::new(AtomicBool::new(false)),
 
             bookmark_manager,
             expanded_topics,
             links_to_open: Vec::new(),
             app_page: AppPage::Main,
-            current_theme: AppTheme::Dark,
+            current_theme: settings.theme,
+
+            search_query: String::new(),
+            searched_query: String::new(),
+            search_results: Vec::new(),
+            search_error: None,
+
+            expanded_smart_topics,
+            smart_topic_hits: None,
+
+            duplicate_groups: None,
+
+            favicons: FaviconCache::load(),
+
+            link_report_tab: LinkReportTab::Broken,
+
+            jobs: JobManager::new(tx.clone(), settings.max_background_tasks),
+            is_jobs_panel_open: true,
+            resolved: HashMap::new(),
+            import_path: String::new(),
+
+            url_rules_text: settings.url_rules.to_text(),
+            url_cleanups: None,
+
+            command_palette: CommandPalette::default(),
+
+            tree_focus: None,
+            scroll_to_focus: false,
+            pending_removal: None,
+
+            selected_links: Vec::new(),
+            selection_anchor: None,
+            bulk_tag: String::new(),
+
+            history: History::default(),
+            toast: None,
+
+            settings,
 
             tx,
             rx,
+
+            #[cfg(target_os = "linux")]
+            dbus_service: None,
         }
     }
 
@@ -95,6 +231,19 @@ impl StashApp {
             .expect("Unable to lock AddTopicViewport");
         viewport.set_old_name("".to_owned());
         viewport.set_new_name("".to_owned());
+        viewport.set_query(None);
+        viewport.set_editing(false);
+
+        self.is_add_topic_open.store(true, Ordering::Relaxed);
+    }
+
+    fn open_add_smart_topic_viewport(&self, query: String) {
+        let mut viewport = ADD_TOPIC_VIEWPORT
+            .lock()
+            .expect("Unable to lock AddTopicViewport");
+        viewport.set_old_name("".to_owned());
+        viewport.set_new_name("".to_owned());
+        viewport.set_query(Some(query));
         viewport.set_editing(false);
 
         self.is_add_topic_open.store(true, Ordering::Relaxed);
@@ -106,6 +255,7 @@ impl StashApp {
             .expect("Unable to lock AddTopicViewport");
         viewport.set_old_name(topic_name.clone());
         viewport.set_new_name(topic_name);
+        viewport.set_query(None);
         viewport.set_editing(true);
 
         self.is_add_topic_open.store(true, Ordering::Relaxed);
@@ -116,189 +266,2628 @@ impl StashApp {
             .lock()
             .expect("Unable to lock AddLinkViewport");
         viewport.set_topic_name(topic_name);
-        viewport.set_old_title("".to_owned());
-        viewport.set_old_url("".to_owned());
+        viewport.set_old_link(LinkModel::default());
         viewport.set_new_title("".to_owned());
         viewport.set_new_url("".to_owned());
+        viewport.set_new_tags(vec![]);
+        viewport.set_new_notes("".to_owned());
+        viewport.set_preview(None);
+        viewport.set_existing_links(self.bookmark_manager.get_all_links());
         viewport.set_is_editing(false);
 
         self.is_add_link_open.store(true, Ordering::Relaxed);
     }
 
-    fn open_edit_link_viewport(&self, topic_name: String, title: String, url: String) {
+    fn open_edit_link_viewport(&self, topic_name: String, link: LinkModel) {
         let mut viewport = ADD_LINK_VIEWPORT
             .lock()
             .expect("Unable to lock AddLinkViewport");
         viewport.set_topic_name(topic_name);
-        viewport.set_old_title(title.clone());
-        viewport.set_old_url(url.clone());
-        viewport.set_new_title(title);
-        viewport.set_new_url(url);
+        viewport.set_new_title(link.title.clone());
+        viewport.set_new_url(link.url.clone());
+        viewport.set_new_tags(link.tags.clone());
+        viewport.set_new_notes(link.notes.clone());
+        viewport.set_preview(link.preview.as_deref().cloned());
+        viewport.set_existing_links(self.bookmark_manager.get_all_links());
+        viewport.set_old_link(link);
         viewport.set_is_editing(true);
 
         self.is_add_link_open.store(true, Ordering::Relaxed);
     }
-}
 
-impl eframe::App for StashApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // * First run
-        if self.is_first_run {
-            self.is_first_run = false;
+    fn open_reader_viewport(&self, ctx: &egui::Context, topic_name: String, link: LinkModel) {
+        READER_VIEWPORT
+            .lock()
+            .expect("Unable to lock ReaderViewport")
+            .set_link(ctx, topic_name, link);
 
-            let center = ctx
-                .input(|i| i.viewport().outer_rect)
-                .unwrap_or(Rect::NOTHING)
-                .center();
-            self.initial_viewport_center = Pos2::new(center.x - 170., center.y - 120.);
+        self.is_reader_open.store(true, Ordering::Relaxed);
+    }
 
-            egui_extras::install_image_loaders(ctx);
+    /// Every action reachable from the menus, as shown in the command palette.
+    fn palette_commands(&self) -> Vec<PaletteCommand> {
+        let mut commands = vec![PaletteCommand::new("Add topic", AppMessage::OpenAddTopic)];
+        if self.history.can_undo() {
+            commands.push(PaletteCommand::new("Undo", AppMessage::Undo));
+        }
+        if self.history.can_redo() {
+            commands.push(PaletteCommand::new("Redo", AppMessage::Redo));
+        }
 
-            let mut fonts = FontDefinitions::default();
-            egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
-            ctx.set_fonts(fonts);
+        for topic in self.bookmark_manager.get_topics() {
+            let links = self
+                .bookmark_manager
+                .get_links_for_topic(&BookmarkItem::Topic(topic.clone()));
+
+            commands.push(PaletteCommand::new(
+                format!("Add link to {}", topic.name),
+                AppMessage::OpenAddLink(topic.name.clone()),
+            ));
+            commands.push(PaletteCommand::new(
+                format!("Open all in {}", topic.name),
+                AppMessage::OpenLinks(links.iter().map(|l| l.url.clone()).collect()),
+            ));
+            commands.push(PaletteCommand::new(
+                format!("Archive all in {}", topic.name),
+                AppMessage::ArchiveLinks(
+                    links
+                        .iter()
+                        .map(|link| (topic.name.clone(), link.clone()))
+                        .collect(),
+                ),
+            ));
+            commands.push(PaletteCommand::new(
+                format!("Edit topic {}", topic.name),
+                AppMessage::OpenEditTopic(topic.name.clone()),
+            ));
+            commands.push(PaletteCommand::new(
+                format!("Remove topic {}", topic.name),
+                AppMessage::RemoveTopic(topic.clone()),
+            ));
+
+            for link in links {
+                commands.push(PaletteCommand::new(
+                    format!("Open {} ({})", link.title, topic.name),
+                    AppMessage::OpenLinks(vec![link.url.clone()]),
+                ));
+                commands.push(PaletteCommand::new(
+                    format!("Read {} ({})", link.title, topic.name),
+                    AppMessage::OpenReader(topic.name.clone(), link.clone()),
+                ));
+                commands.push(PaletteCommand::new(
+                    format!("Edit link {} ({})", link.title, topic.name),
+                    AppMessage::OpenEditLink(topic.name.clone(), link.clone()),
+                ));
+                commands.push(PaletteCommand::new(
+                    format!("Remove link {} ({})", link.title, topic.name),
+                    AppMessage::RemoveLink(topic.name.clone(), link),
+                ));
+            }
         }
 
-        let min_size = *MIN_SIZE;
-        let mut clicked_on_button = false;
+        for theme in AppTheme::values() {
+            commands.push(PaletteCommand::new(
+                format!("Switch theme to {}", theme.name()),
+                AppMessage::SetTheme(theme),
+            ));
+        }
+        commands.push(PaletteCommand::new(
+            "Toggle AlwaysOnTop",
+            AppMessage::ToggleAlwaysOnTop,
+        ));
+        commands.push(PaletteCommand::new(
+            "Backup bookmarks",
+            AppMessage::BackupBookmarks,
+        ));
+        commands.push(PaletteCommand::new(
+            "Go to settings",
+            AppMessage::SetPage(AppPage::Settings),
+        ));
+        commands.push(PaletteCommand::new(
+            "Refresh icons",
+            AppMessage::RefreshIcons,
+        ));
+        commands.push(PaletteCommand::new("Check links", AppMessage::CheckLinks));
+        commands.push(PaletteCommand::new(
+            "Expand short links",
+            AppMessage::ResolveLinks(self.unresolved_short_links()),
+        ));
+        commands.push(PaletteCommand::new(
+            "Go to link report",
+            AppMessage::SetPage(AppPage::LinkReport),
+        ));
+        commands.push(PaletteCommand::new(
+            "Find duplicates",
+            AppMessage::SetPage(AppPage::Duplicates),
+        ));
+        commands.push(PaletteCommand::new(
+            "Go to trash",
+            AppMessage::SetPage(AppPage::Trash),
+        ));
+        commands.push(PaletteCommand::new(
+            "Go to bookmarks",
+            AppMessage::SetPage(AppPage::Main),
+        ));
+        commands.push(PaletteCommand::new(
+            "Open next unread",
+            AppMessage::OpenNextUnread(None),
+        ));
+        commands.push(PaletteCommand::new(
+            if self.settings.show_unread_only {
+                "Show all links"
+            } else {
+                "Show unread links only"
+            },
+            AppMessage::ToggleUnreadOnly,
+        ));
+
+        commands
+    }
 
-        // * Open modal
-        let model_style = ModalStyle {
-            default_width: Some(min_size[0] - 20.),
-            ..Default::default()
+    /// Whether the tree shows `link`, read links are hidden when showing unread ones only.
+    fn is_link_shown(&self, link: &LinkModel) -> bool {
+        !self.settings.show_unread_only || !link.is_read()
+    }
+
+    /// Topic headers and the links of expanded topics, in display order.
+    fn visible_tree_rows(&self) -> Vec<TreeFocus> {
+        let mut rows = vec![];
+        for (idx, topic) in self.bookmark_manager.get_topics().into_iter().enumerate() {
+            rows.push(TreeFocus::Topic(idx));
+
+            if self.expanded_topics.get(idx).copied().unwrap_or(false) {
+                let links = self
+                    .bookmark_manager
+                    .get_links_for_topic(&BookmarkItem::Topic(topic));
+                rows.extend(
+                    links
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, link)| self.is_link_shown(link))
+                        .map(|(link_idx, _)| TreeFocus::Link(idx, link_idx)),
+                );
+            }
+        }
+
+        rows
+    }
+
+    fn handle_tree_keys(&mut self, ctx: &egui::Context, modal: &Modal, remove_modal: &Modal) {
+        if ctx.wants_keyboard_input()
+            || self.command_palette.is_open()
+            || modal.is_open()
+            || remove_modal.is_open()
+        {
+            return;
+        }
+
+        let rows = self.visible_tree_rows();
+        if rows.is_empty() {
+            self.tree_focus = None;
+            return;
+        }
+
+        let (up, down, left, right, enter, edit, add, remove) = ctx.input(|i| {
+            // * Only bare keys, so shortcuts like Ctrl+A don't act on the tree
+            let plain = i.modifiers.is_none();
+            (
+                i.key_pressed(Key::ArrowUp),
+                i.key_pressed(Key::ArrowDown),
+                i.key_pressed(Key::ArrowLeft),
+                i.key_pressed(Key::ArrowRight),
+                i.key_pressed(Key::Enter),
+                plain && i.key_pressed(Key::E),
+                plain && i.key_pressed(Key::A),
+                plain && i.key_pressed(Key::Delete),
+            )
+        });
+        if !(up || down || left || right || enter || edit || add || remove) {
+            return;
+        }
+
+        // * The first key press only places the cursor
+        let Some(focus) = self.tree_focus.filter(|focus| rows.contains(focus)) else {
+            self.tree_focus = Some(rows[0]);
+            self.scroll_to_focus = true;
+            return;
         };
-        let modal = Modal::new(ctx, "open_links_confirmation_modal")
-            .with_close_on_outside_click(true)
-            .with_style(&model_style);
-        modal.show(|ui| {
-            modal.title(ui, "Opening links");
-            modal.frame(ui, |ui| {
-                modal.body(
-                    ui,
-                    format!(
-                        "Are you sure you want to open {} {}?",
-                        self.links_to_open.len(),
-                        if self.links_to_open.len() == 1 {
-                            "link"
-                        } else {
-                            "links"
+        let pos = rows
+            .iter()
+            .position(|row| row == &focus)
+            .unwrap_or_default();
+
+        let topic = self.bookmark_manager.get_topics()[focus.topic_idx()].clone();
+        let links = self
+            .bookmark_manager
+            .get_links_for_topic(&BookmarkItem::Topic(topic.clone()));
+        let is_expanded = self
+            .expanded_topics
+            .get(focus.topic_idx())
+            .copied()
+            .unwrap_or(false);
+
+        let mut new_focus = focus;
+        if up {
+            new_focus = rows[pos.saturating_sub(1)];
+        } else if down {
+            new_focus = rows[(pos + 1).min(rows.len() - 1)];
+        } else if left {
+            match focus {
+                TreeFocus::Topic(idx) if is_expanded => {
+                    self.tx
+                        .send(AppMessage::SetExpanded(idx, false))
+                        .expect("Unable to send");
+                }
+                TreeFocus::Link(idx, _) => new_focus = TreeFocus::Topic(idx),
+                _ => {}
+            }
+        } else if right {
+            match focus {
+                TreeFocus::Topic(idx) if !is_expanded => {
+                    self.tx
+                        .send(AppMessage::SetExpanded(idx, true))
+                        .expect("Unable to send");
+                }
+                TreeFocus::Topic(_) => {
+                    if let Some(link @ TreeFocus::Link(..)) = rows.get(pos + 1) {
+                        new_focus = *link;
+                    }
+                }
+                _ => {}
+            }
+        } else if enter {
+            let urls = match focus {
+                TreeFocus::Topic(_) => links.iter().map(|l| l.url.clone()).collect(),
+                TreeFocus::Link(_, link_idx) => vec![links[link_idx].url.clone()],
+            };
+            self.tx
+                .send(AppMessage::OpenLinks(urls))
+                .expect("Unable to send");
+        } else if edit {
+            match focus {
+                TreeFocus::Topic(_) => self.open_edit_topic_viewport(topic.name),
+                TreeFocus::Link(_, link_idx) => {
+                    self.open_edit_link_viewport(topic.name, links[link_idx].clone())
+                }
+            }
+        } else if add {
+            self.open_add_link_viewport(topic.name);
+        } else if remove {
+            self.pending_removal = Some(match focus {
+                TreeFocus::Topic(_) => AppMessage::RemoveTopic(topic),
+                TreeFocus::Link(_, link_idx) => {
+                    AppMessage::RemoveLink(topic.name, links[link_idx].clone())
+                }
+            });
+            remove_modal.open();
+        }
+
+        if new_focus != focus {
+            self.tree_focus = Some(new_focus);
+            self.scroll_to_focus = true;
+        }
+    }
+
+    /// Writes `settings.json` when the theme, window level or expanded topics changed.
+    fn sync_settings(&mut self) {
+        let expanded_topics = self
+            .bookmark_manager
+            .get_topics()
+            .into_iter()
+            .zip(&self.expanded_topics)
+            .filter(|(_, is_expanded)| **is_expanded)
+            .map(|(topic, _)| topic.name)
+            .collect();
+        let settings = AppSettings {
+            theme: self.current_theme,
+            always_on_top: self.window_level == WindowLevel::AlwaysOnTop,
+            expanded_topics,
+            ..self.settings.clone()
+        };
+
+        if settings != self.settings {
+            settings.save(self.is_debug);
+            self.settings = settings;
+        }
+    }
+
+    /// "Move to…" and "Copy to…" submenus for `links`, given as `(topic name, link)` pairs.
+    fn show_transfer_menus(
+        &self,
+        ui: &mut egui::Ui,
+        links: &[(String, LinkModel)],
+        clicked_on_button: &mut bool,
+    ) {
+        // * A topic is only a target if some of the links are not already in it
+        let targets = self
+            .bookmark_manager
+            .get_topics()
+            .into_iter()
+            .filter(|t| links.iter().any(|(name, _)| name != &t.name))
+            .collect::<Vec<TopicModel>>();
+
+        ui.add_enabled_ui(!targets.is_empty(), |ui| {
+            ui.menu_button("Move to…", |ui| {
+                for topic in &targets {
+                    if ui.button(&topic.name).clicked() {
+                        for (from, links) in group_by_topic(links) {
+                            self.tx
+                                .send(AppMessage::MoveLinks(from, links, topic.name.clone()))
+                                .expect("Unable to send");
+                        }
+                        ui.close_menu();
+                        *clicked_on_button = true;
+                    }
+                }
+            });
+            ui.menu_button("Copy to…", |ui| {
+                for topic in &targets {
+                    if ui.button(&topic.name).clicked() {
+                        let links = links.iter().map(|(_, link)| link.clone()).collect();
+                        self.tx
+                            .send(AppMessage::CopyLinks(links, topic.name.clone()))
+                            .expect("Unable to send");
+                        ui.close_menu();
+                        *clicked_on_button = true;
+                    }
+                }
+            });
+        });
+    }
+
+    /// The selection when `link` is part of it, otherwise just `link`.
+    fn links_for_action(&self, topic_name: &str, link: &LinkModel) -> Vec<(String, LinkModel)> {
+        let entry = (topic_name.to_owned(), link.clone());
+        if self.selected_links.contains(&entry) {
+            self.selected_links.clone()
+        } else {
+            vec![entry]
+        }
+    }
+
+    /// Ctrl-click toggles a link in the selection, Shift-click selects the tree rows between
+    /// the last Ctrl-clicked row and this one.
+    fn handle_selection_click(
+        &mut self,
+        ui: &egui::Ui,
+        rect: Rect,
+        topic_name: &str,
+        link: &LinkModel,
+        row: Option<TreeFocus>,
+    ) {
+        let hov_pos = ui.input(|i| i.pointer.hover_pos().unwrap_or(Pos2::ZERO));
+        let modifiers = ui.input(|i| i.modifiers);
+        if !rect.contains(hov_pos)
+            || !ui.input(|i| i.pointer.primary_clicked())
+            || !(modifiers.command || modifiers.shift)
+        {
+            return;
+        }
+
+        let entry = (topic_name.to_owned(), link.clone());
+        match (modifiers.shift, self.selection_anchor, row) {
+            (true, Some(anchor), Some(row)) => {
+                let rows = self.visible_tree_rows();
+                let (Some(a), Some(b)) = (
+                    rows.iter().position(|r| r == &anchor),
+                    rows.iter().position(|r| r == &row),
+                ) else {
+                    return;
+                };
+
+                let topics = self.bookmark_manager.get_topics();
+                for row in &rows[a.min(b)..=a.max(b)] {
+                    if let TreeFocus::Link(topic_idx, link_idx) = *row {
+                        let topic = topics[topic_idx].clone();
+                        let link = self
+                            .bookmark_manager
+                            .get_links_for_topic(&BookmarkItem::Topic(topic.clone()))[link_idx]
+                            .clone();
+                        let entry = (topic.name, link);
+                        if !self.selected_links.contains(&entry) {
+                            self.selected_links.push(entry);
                         }
+                    }
+                }
+            }
+            _ => {
+                match self.selected_links.iter().position(|e| e == &entry) {
+                    Some(idx) => {
+                        self.selected_links.remove(idx);
+                    }
+                    None => self.selected_links.push(entry),
+                }
+                self.selection_anchor = row;
+            }
+        }
+    }
+
+    /// Selection count and bulk actions, shown in the top panel while links are selected.
+    fn show_selection_bar(
+        &mut self,
+        ui: &mut egui::Ui,
+        remove_modal: &Modal,
+        clicked_on_button: &mut bool,
+    ) {
+        let selected = self.selected_links.clone();
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label(RichText::new(format!("{} selected", selected.len())).strong());
+
+            if ui.button("Open").clicked() {
+                self.tx
+                    .send(AppMessage::OpenLinks(
+                        selected.iter().map(|(_, l)| l.url.clone()).collect(),
+                    ))
+                    .expect("Unable to send");
+                *clicked_on_button = true;
+            }
+
+            self.show_transfer_menus(ui, &selected, clicked_on_button);
+
+            ui.menu_button("Tag…", |ui| {
+                ui.add(
+                    TextEdit::singleline(&mut self.bulk_tag)
+                        .hint_text("Tag")
+                        .desired_width(100.),
+                );
+                let tag = self.bulk_tag.trim().to_owned();
+                if ui
+                    .add_enabled(!tag.is_empty(), Button::new("Add tag"))
+                    .clicked()
+                {
+                    self.tx
+                        .send(AppMessage::TagLinks(selected.clone(), tag))
+                        .expect("Unable to send");
+                    self.bulk_tag.clear();
+                    ui.close_menu();
+                    *clicked_on_button = true;
+                }
+            });
+
+            if ui.button("Mark read").clicked() {
+                self.tx
+                    .send(AppMessage::MarkRead(
+                        selected
+                            .iter()
+                            .map(|(name, l)| (name.clone(), l.url.clone()))
+                            .collect(),
+                        true,
+                    ))
+                    .expect("Unable to send");
+                *clicked_on_button = true;
+            }
+
+            if ui.button("Mark unread").clicked() {
+                self.tx
+                    .send(AppMessage::MarkRead(
+                        selected
+                            .iter()
+                            .map(|(name, l)| (name.clone(), l.url.clone()))
+                            .collect(),
+                        false,
+                    ))
+                    .expect("Unable to send");
+                *clicked_on_button = true;
+            }
+
+            if ui.button("Copy URLs").clicked() {
+                let urls = selected
+                    .iter()
+                    .map(|(_, l)| l.url.clone())
+                    .collect::<Vec<String>>();
+                ui.output_mut(|o| o.copied_text = urls.join("\n"));
+                *clicked_on_button = true;
+            }
+
+            if ui.button("Export").clicked() {
+                self.tx
+                    .send(AppMessage::ExportLinks(selected.clone()))
+                    .expect("Unable to send");
+                *clicked_on_button = true;
+            }
+
+            if ui.button("Delete").clicked() {
+                self.pending_removal = Some(AppMessage::RemoveLinks(selected.clone()));
+                remove_modal.open();
+                *clicked_on_button = true;
+            }
+
+            if ui
+                .button(regular::X)
+                .on_hover_text("Clear selection")
+                .clicked()
+            {
+                self.selected_links.clear();
+                *clicked_on_button = true;
+            }
+        });
+    }
+
+    /// Shows where a dragged row would land on `target` and reorders or moves it on release.
+    fn handle_tree_drop(&self, ui: &egui::Ui, target: TreeFocus, response: &Response) {
+        let Some(source) = response.dnd_hover_payload::<TreeFocus>().map(|p| *p) else {
+            return;
+        };
+        if source == target {
+            return;
+        }
+
+        let topics = self.bookmark_manager.get_topics();
+        let topic_links = |idx: usize| {
+            self.bookmark_manager
+                .get_links_for_topic(&BookmarkItem::Topic(topics[idx].clone()))
+        };
+
+        let msg = match (source, target) {
+            (TreeFocus::Topic(from), target) => {
+                let to = target.topic_idx();
+                if from == to {
+                    return;
+                }
+                AppMessage::ReorderTopic(from, to)
+            }
+            (TreeFocus::Link(from_topic, from), TreeFocus::Link(to_topic, to))
+                if from_topic == to_topic =>
+            {
+                AppMessage::ReorderLink(topics[to_topic].name.clone(), from, to)
+            }
+            (TreeFocus::Link(from_topic, _), TreeFocus::Topic(to_topic))
+                if from_topic == to_topic =>
+            {
+                return;
+            }
+            (TreeFocus::Link(from_topic, from), target) => {
+                // * Dropped on a topic the link is appended, on a row it takes its place
+                let to_topic = target.topic_idx();
+                let link = topic_links(from_topic)[from].clone();
+                let from_name = topics[from_topic].name.clone();
+                let to_name = topics[to_topic].name.clone();
+                match target {
+                    TreeFocus::Link(_, to) => AppMessage::MoveLinkTo(from_name, link, to_name, to),
+                    TreeFocus::Topic(_) => AppMessage::MoveLinks(from_name, vec![link], to_name),
+                }
+            }
+        };
+
+        // * Drop indicator: a line on the side the row lands on, or an outline for another topic
+        let stroke = ui.visuals().selection.stroke;
+        let rect = response.rect;
+        match (source, target) {
+            (TreeFocus::Link(from_topic, _), target) if from_topic != target.topic_idx() => {
+                ui.painter().rect_stroke(rect, Rounding::same(9.), stroke);
+            }
+            (TreeFocus::Link(_, from), TreeFocus::Link(_, to)) => {
+                let y = if to < from { rect.top() } else { rect.bottom() };
+                ui.painter().hline(rect.x_range(), y, stroke);
+            }
+            _ => {
+                let y = if target.topic_idx() < source.topic_idx() {
+                    rect.top()
+                } else {
+                    rect.bottom()
+                };
+                ui.painter().hline(rect.x_range(), y, stroke);
+            }
+        }
+
+        if response.dnd_release_payload::<TreeFocus>().is_some() {
+            self.tx.send(msg).expect("Unable to send");
+        }
+    }
+
+    /// Applies a message without recording it, used for new, undone and redone mutations.
+    fn apply_message(&mut self, ctx: &egui::Context, modal: &Modal, msg: AppMessage) {
+        match msg {
+            // * Topics
+            AppMessage::AddTopic(topic) => {
+                self.bookmark_manager.add_topic(BookmarkItem::Topic(topic));
+                self.expanded_topics.push(false);
+            }
+            AppMessage::EditTopic(old_topic, new_topic) => {
+                self.bookmark_manager.edit_topic(
+                    BookmarkItem::Topic(old_topic.clone()),
+                    BookmarkItem::Topic(new_topic),
+                );
+            }
+            AppMessage::RemoveTopic(topic) => {
+                let idx = self
+                    .bookmark_manager
+                    .get_topics()
+                    .iter()
+                    .position(|t| t == &topic);
+                self.bookmark_manager
+                    .remove_topic(BookmarkItem::Topic(topic.clone()));
+
+                if let Some(idx) = idx {
+                    self.expanded_topics.remove(idx);
+                }
+            }
+            AppMessage::RestoreTopic(idx, topic, links) => {
+                let idx = idx.min(self.expanded_topics.len());
+                self.bookmark_manager.insert_topic(
+                    idx,
+                    BookmarkItem::Topic(topic),
+                    links.into_iter().map(BookmarkItem::Link).collect(),
+                );
+                self.expanded_topics.insert(idx, false);
+            }
+
+            AppMessage::ReorderTopic(old_index, new_index) => {
+                self.bookmark_manager.reorder_topics(old_index, new_index);
+
+                let is_expanded = self.expanded_topics.remove(old_index);
+                self.expanded_topics.insert(new_index, is_expanded);
+                self.tree_focus = Some(TreeFocus::Topic(new_index));
+            }
+
+            // * Links
+            AppMessage::AddLink(name, link) => {
+                let topic = match self
+                    .bookmark_manager
+                    .get_topics()
+                    .into_iter()
+                    .find(|t| t.name == name)
+                {
+                    Some(topic) => topic,
+                    None => {
+                        let topic = TopicModel::new(name);
+                        self.bookmark_manager
+                            .add_topic(BookmarkItem::Topic(topic.clone()));
+                        self.expanded_topics.push(false);
+                        topic
+                    }
+                };
+
+                // * Short links are looked up right away, to show where they go
+                if is_short_link(&link.url) && link.resolved_url.is_none() {
+                    self.resolve_links(ctx, vec![(topic.name.clone(), link.url.clone())]);
+                }
+
+                let skipped = self
+                    .bookmark_manager
+                    .add_link(BookmarkItem::Topic(topic.clone()), BookmarkItem::Link(link));
+                self.warn_skipped_links(&topic.name, skipped.len());
+            }
+            AppMessage::EditLink(name, old_link, link) => {
+                // * Messages from D-Bus and the history may name a topic that is gone by now
+                let Some(topic) = self
+                    .bookmark_manager
+                    .get_topics()
+                    .into_iter()
+                    .find(|t| t.name == name)
+                else {
+                    eprintln!("Topic not found: {}", name);
+                    return;
+                };
+
+                self.bookmark_manager.edit_link(
+                    BookmarkItem::Topic(topic),
+                    BookmarkItem::Link(old_link),
+                    BookmarkItem::Link(link),
+                );
+            }
+            AppMessage::EditLinks(edits) => {
+                for (name, old_link, link) in edits {
+                    self.bookmark_manager.edit_link(
+                        BookmarkItem::Topic(TopicModel::new(name)),
+                        BookmarkItem::Link(old_link),
+                        BookmarkItem::Link(link),
+                    );
+                }
+            }
+            AppMessage::RemoveLink(name, link) => {
+                let Some(topic) = self
+                    .bookmark_manager
+                    .get_topics()
+                    .into_iter()
+                    .find(|t| t.name == name)
+                else {
+                    eprintln!("Topic not found: {}", name);
+                    return;
+                };
+
+                self.bookmark_manager
+                    .remove_link(BookmarkItem::Topic(topic), BookmarkItem::Link(link));
+            }
+            AppMessage::ReorderLink(name, old_index, new_index) => {
+                let topic = TopicModel::new(name);
+                self.bookmark_manager.reorder_links(
+                    BookmarkItem::Topic(topic),
+                    old_index,
+                    new_index,
+                );
+            }
+            AppMessage::MoveLinks(from, links, to) => {
+                let skipped = self.bookmark_manager.move_links(
+                    BookmarkItem::Topic(TopicModel::new(from)),
+                    links.into_iter().map(BookmarkItem::Link).collect(),
+                    BookmarkItem::Topic(TopicModel::new(to.clone())),
+                );
+                self.warn_skipped_links(&to, skipped.len());
+            }
+            AppMessage::MoveLinkTo(from, link, to, index) => {
+                let skipped = self.bookmark_manager.move_link_to(
+                    BookmarkItem::Topic(TopicModel::new(from)),
+                    BookmarkItem::Link(link),
+                    BookmarkItem::Topic(TopicModel::new(to.clone())),
+                    index,
+                );
+                self.warn_skipped_links(&to, skipped.len());
+            }
+            AppMessage::ImportTopics(topics) => {
+                let skipped = self.bookmark_manager.import_topics(topics);
+                let topics = self.bookmark_manager.get_topics().len();
+                self.expanded_topics.resize(topics, false);
+                if !skipped.is_empty() {
+                    self.toast = Some(Toast::new(
+                        format!(
+                            "Skipped {} {} already in their topic",
+                            skipped.len(),
+                            if skipped.len() == 1 { "link" } else { "links" }
+                        ),
+                        None,
+                    ));
+                }
+            }
+            AppMessage::CopyLinks(links, to) => {
+                let skipped = self.bookmark_manager.copy_links(
+                    links.into_iter().map(BookmarkItem::Link).collect(),
+                    BookmarkItem::Topic(TopicModel::new(to.clone())),
+                );
+                self.warn_skipped_links(&to, skipped.len());
+            }
+            AppMessage::RemoveLinks(links) => {
+                for (name, link) in links {
+                    self.bookmark_manager.remove_link(
+                        BookmarkItem::Topic(TopicModel::new(name)),
+                        BookmarkItem::Link(link),
+                    );
+                }
+            }
+            AppMessage::TagLinks(links, tag) => {
+                for (name, link) in links {
+                    if link.has_tag(&tag) {
+                        continue;
+                    }
+
+                    let mut tagged = link.clone();
+                    tagged.tags.push(tag.clone());
+                    self.bookmark_manager.edit_link(
+                        BookmarkItem::Topic(TopicModel::new(name)),
+                        BookmarkItem::Link(link),
+                        BookmarkItem::Link(tagged),
+                    );
+                }
+            }
+            AppMessage::RestoreLinks(links) => {
+                self.bookmark_manager.insert_links(
+                    links
+                        .into_iter()
+                        .map(|(name, idx, link)| {
+                            (
+                                BookmarkItem::Topic(TopicModel::new(name)),
+                                idx,
+                                BookmarkItem::Link(link),
+                            )
+                        })
+                        .collect(),
+                );
+            }
+            AppMessage::MergeDuplicates(groups) => {
+                self.bookmark_manager.merge_duplicates(&groups);
+            }
+            AppMessage::MarkOpened(urls) => {
+                self.bookmark_manager
+                    .mark_opened(&urls, self.settings.mark_read_on_open);
+            }
+            AppMessage::MarkRead(links, read) => {
+                self.bookmark_manager.mark_read(&links, read);
+            }
+
+            // * Smart topics
+            AppMessage::AddSmartTopic(smart_topic) => {
+                let is_new = !self
+                    .bookmark_manager
+                    .get_smart_topics()
+                    .iter()
+                    .any(|t| t.name == smart_topic.name);
+                self.bookmark_manager.add_smart_topic(smart_topic);
+                if is_new {
+                    self.expanded_smart_topics.push(true);
+                }
+                self.search_query.clear();
+            }
+            AppMessage::RemoveSmartTopic(smart_topic) => {
+                let idx = self
+                    .bookmark_manager
+                    .get_smart_topics()
+                    .iter()
+                    .position(|t| t == &smart_topic);
+                self.bookmark_manager.remove_smart_topic(&smart_topic);
+                if let Some(idx) = idx {
+                    self.expanded_smart_topics.remove(idx);
+                }
+            }
+
+            AppMessage::RestoreSmartTopic(idx, smart_topic) => {
+                let is_new = !self
+                    .bookmark_manager
+                    .get_smart_topics()
+                    .iter()
+                    .any(|t| t.name == smart_topic.name);
+                self.bookmark_manager.insert_smart_topic(idx, smart_topic);
+                if is_new {
+                    let idx = idx.min(self.expanded_smart_topics.len());
+                    self.expanded_smart_topics.insert(idx, true);
+                }
+            }
+
+            // * Trash
+            AppMessage::RestoreFromTrash(entry) => {
+                if let Some(idx) = self.bookmark_manager.restore_from_trash(&entry) {
+                    self.expanded_topics.insert(idx, false);
+                }
+            }
+            AppMessage::ReturnToTrash(entry, created_topic) => {
+                if let Some(idx) = self.bookmark_manager.return_to_trash(&entry, created_topic) {
+                    self.expanded_topics.remove(idx);
+                }
+            }
+            AppMessage::DeleteFromTrash(entry) => {
+                self.bookmark_manager.delete_from_trash(&entry);
+            }
+            AppMessage::EmptyTrash => {
+                self.bookmark_manager.empty_trash();
+            }
+
+            // * History
+            AppMessage::Undo => self.undo(ctx, modal),
+            AppMessage::Redo => self.redo(ctx, modal),
+
+            // * UI
+            AppMessage::SetExpanded(index, is_expanded) => {
+                if let Some(expanded) = self.expanded_topics.get_mut(index) {
+                    *expanded = is_expanded;
+                }
+            }
+            AppMessage::OpenAddTopic => {
+                self.open_add_topic_viewport();
+            }
+            AppMessage::OpenEditTopic(name) => {
+                self.open_edit_topic_viewport(name);
+            }
+            AppMessage::OpenAddLink(name) => {
+                self.open_add_link_viewport(name);
+            }
+            AppMessage::OpenEditLink(name, link) => {
+                self.open_edit_link_viewport(name, link);
+            }
+            AppMessage::OpenReader(name, link) => {
+                self.open_reader_viewport(ctx, name, link);
+            }
+            AppMessage::OpenNextUnread(topic) => {
+                match self.bookmark_manager.next_unread(topic.as_deref()) {
+                    Some((_, link)) => {
+                        self.links_to_open.push(link.url);
+                        modal.open();
+                    }
+                    None => self.toast = Some(Toast::new("No unread links".to_owned(), None)),
+                }
+            }
+            AppMessage::ToggleUnreadOnly => {
+                self.settings.show_unread_only = !self.settings.show_unread_only;
+                self.settings.save(self.is_debug);
+            }
+            AppMessage::OpenLinks(urls) => {
+                if !urls.is_empty() {
+                    self.links_to_open.extend(urls);
+                    modal.open();
+                }
+            }
+            AppMessage::SetTheme(theme) => {
+                theme.set_theme(ctx);
+                self.current_theme = theme;
+            }
+            AppMessage::SetPage(page) => {
+                self.app_page = page;
+            }
+
+            // * Misc
+            AppMessage::ToggleAlwaysOnTop => {
+                self.window_level = match self.window_level {
+                    WindowLevel::Normal => {
+                        ctx.send_viewport_cmd(ViewportCommand::Title(
+                            "Stash: AlwaysOnTop".to_owned(),
+                        ));
+                        WindowLevel::AlwaysOnTop
+                    }
+                    WindowLevel::AlwaysOnTop => {
+                        ctx.send_viewport_cmd(ViewportCommand::Title("Stash".to_owned()));
+                        WindowLevel::Normal
+                    }
+                    _ => WindowLevel::Normal,
+                };
+
+                ctx.send_viewport_cmd(ViewportCommand::WindowLevel(self.window_level));
+            }
+            AppMessage::SetVisible(is_visible) => {
+                ctx.send_viewport_cmd(ViewportCommand::Visible(is_visible));
+                if is_visible {
+                    ctx.send_viewport_cmd(ViewportCommand::Focus);
+                }
+            }
+            AppMessage::BackupBookmarks => {
+                // * The backup copies the file, which must have the edits held back this frame
+                self.bookmark_manager.release_saves();
+                backup_bookmarks();
+                self.bookmark_manager.hold_saves();
+            }
+            AppMessage::RefreshIcons => {
+                self.favicons.refresh();
+            }
+            AppMessage::CheckLinks => {
+                if self.jobs.running(JobKind::LinkCheck).is_none() {
+                    let links = web_links(self.bookmark_manager.get_all_links());
+                    let checker = Arc::new(LinkChecker::default());
+                    let pacer = checker.clone();
+                    self.jobs.spawn_paced(
+                        ctx,
+                        JobKind::LinkCheck,
+                        format!("Checking {} links", links.len()),
+                        links,
+                        move |(_, url)| pacer.wait_for_host(url),
+                        move |(topic, url)| JobOutput::Checked(checker.check(topic, url)),
+                    );
+                }
+                self.app_page = AppPage::LinkReport;
+            }
+            AppMessage::ArchiveLinks(links) => {
+                let links = web_links(links);
+                if self.jobs.running(JobKind::Archive).is_some() {
+                    self.toast = Some(Toast::new(
+                        "Already archiving, try again once it is done".to_owned(),
+                        None,
+                    ));
+                } else if links.is_empty() {
+                    self.toast = Some(Toast::new("No pages to archive".to_owned(), None));
+                } else {
+                    self.jobs.spawn(
+                        ctx,
+                        JobKind::Archive,
+                        format!("Archiving {} pages", links.len()),
+                        links,
+                        |(topic, url)| {
+                            let error = archive_page(&url).err();
+                            if let Some(e) = &error {
+                                eprintln!("Failed to archive {}: {}", url, e);
+                            }
+                            JobOutput::Archived(ArchiveResult { topic, url, error })
+                        },
+                    );
+                }
+            }
+            AppMessage::ResolveLinks(links) => {
+                let links = web_links(links);
+                if links.is_empty() {
+                    self.toast = Some(Toast::new("No links to resolve".to_owned(), None));
+                } else {
+                    self.resolve_links(ctx, links);
+                }
+            }
+            AppMessage::FetchMetadata(links) => {
+                let links = web_links(
+                    links
+                        .into_iter()
+                        .filter(|(_, link)| link.has_placeholder_title() || link.preview.is_none())
+                        .collect(),
+                );
+                if links.is_empty() {
+                    self.toast = Some(Toast::new(
+                        "Every link has a title and preview".to_owned(),
+                        None,
+                    ));
+                } else {
+                    self.jobs.spawn(
+                        ctx,
+                        JobKind::Metadata,
+                        format!("Fetching {} pages", links.len()),
+                        links,
+                        |(topic, url)| {
+                            let meta = PageMeta::fetch(&url);
+                            // * The card shows the image once it is on disk
+                            let image = meta.as_ref().ok().and_then(|meta| meta.preview()?.image);
+                            if let Some(image) = image {
+                                if let Err(e) = cache_preview_image(&image) {
+                                    eprintln!("Failed to cache preview image {}: {}", image, e);
+                                }
+                            }
+                            JobOutput::Fetched { topic, url, meta }
+                        },
+                    );
+                }
+            }
+            AppMessage::ImportBookmarks(path) => {
+                let label = format!(
+                    "Importing {}",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+                self.jobs
+                    .spawn(ctx, JobKind::Import, label, vec![path], |path| {
+                        JobOutput::Imported(read_export(&path))
+                    });
+            }
+            AppMessage::ExportBookmarks => {
+                let data = self.bookmark_manager.to_json();
+                self.jobs.spawn(
+                    ctx,
+                    JobKind::Export,
+                    "Exporting bookmarks".to_owned(),
+                    vec![data],
+                    |data| JobOutput::Exported(write_export("bookmarks", &data)),
+                );
+            }
+            AppMessage::ExportLinks(links) => {
+                let data = links_to_json(&links);
+                self.jobs.spawn(
+                    ctx,
+                    JobKind::Export,
+                    format!("Exporting {} links", links.len()),
+                    vec![data],
+                    |data| JobOutput::Exported(write_export("selection", &data)),
+                );
+            }
+            AppMessage::JobOutput(id, output) => {
+                self.apply_job_output(id, output);
+            }
+            AppMessage::CancelJob(id) => {
+                if let Some(job) = self.jobs.cancel(id) {
+                    self.toast = Some(Toast::new(
+                        format!(
+                            "{} cancelled after {} of {}",
+                            job.kind.name(),
+                            job.done,
+                            job.total
+                        ),
+                        None,
+                    ));
+                }
+                self.resolved.remove(&id);
+            }
+
+            // * D-Bus
+            #[cfg(target_os = "linux")]
+            AppMessage::Dbus(request) => request.answer(&self.bookmark_manager),
+        }
+    }
+
+    /// Tells that `skipped` links were left out, as `topic` already had their URLs.
+    fn warn_skipped_links(&mut self, topic: &str, skipped: usize) {
+        let text = match skipped {
+            0 => return,
+            1 => format!("Skipped a link already in \"{}\"", topic),
+            _ => format!("Skipped {} links already in \"{}\"", skipped, topic),
+        };
+        self.toast = Some(Toast::new(text, None));
+    }
+
+    fn undo(&mut self, ctx: &egui::Context, modal: &Modal) {
+        let Some(entry) = self.history.pop_undo() else {
+            return;
+        };
+
+        history::undo(
+            self,
+            |app| &mut app.bookmark_manager,
+            &entry,
+            |app, msg| app.apply_message(ctx, modal, msg),
+        );
+        self.toast = Some(Toast::new(format!("Undone: {}", entry.label), None));
+        self.history.push_redo(entry);
+    }
+
+    fn redo(&mut self, ctx: &egui::Context, modal: &Modal) {
+        let Some(entry) = self.history.pop_redo() else {
+            return;
+        };
+
+        if let Some(entry) = history::redo(
+            self,
+            |app| &mut app.bookmark_manager,
+            &entry,
+            |app, msg| app.apply_message(ctx, modal, msg),
+        ) {
+            self.history.push_undo(entry);
+        }
+        self.toast = Some(Toast::new(format!("Redone: {}", entry.label), None));
+    }
+
+    fn show_trash_page(&mut self, ctx: &egui::Context, clicked_on_button: &mut bool) {
+        let trash = self.bookmark_manager.get_trash();
+
+        // * Top panel
+        TopBottomPanel::top("top_panel_trash")
+            .resizable(false)
+            .show_separator_line(false)
+            .default_height(35.0)
+            .show(ctx, |ui| {
+                ui.add_space(5.);
+                ui.horizontal(|ui| {
+                    let back = ui.label(RichText::new(regular::ARROW_LEFT.to_string()).size(20.));
+                    if back.hovered() {
+                        ui.output_mut(|o| o.cursor_icon = CursorIcon::PointingHand);
+                    }
+                    if back.clicked() {
+                        self.app_page = AppPage::Main;
+                        *clicked_on_button = true;
+                    }
+
+                    let available_width = ui.available_width();
+                    let label = "Empty Trash";
+
+                    ui.add_space(available_width - calc_btn_size_from_text(label));
+
+                    ui.add_enabled_ui(!trash.is_empty(), |ui| {
+                        custom_button(ui, label, None, || {
+                            self.tx
+                                .send(AppMessage::EmptyTrash)
+                                .expect("Unable to send");
+                            *clicked_on_button = true;
+                        });
+                    });
+                });
+            });
+
+        // * Trash page
+        CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Trash");
+                ui.label(
+                    RichText::new(format!(
+                        "Items are deleted permanently after {} days",
+                        self.settings.trash_retention_days
+                    ))
+                    .small(),
+                );
+            });
+
+            ui.add_space(5.);
+
+            ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .scroll_bar_visibility(ScrollBarVisibility::VisibleWhenNeeded)
+                .show(ui, |ui| {
+                    if trash.is_empty() {
+                        ui.vertical_centered(|ui| {
+                            ui.label("Trash is empty!");
+                        });
+                        return;
+                    }
+
+                    // * Newest first
+                    let now = Utc::now();
+                    for (idx, entry) in trash.iter().rev().enumerate() {
+                        self.show_trash_row(ui, entry, now, clicked_on_button);
+
+                        if idx < trash.len() - 1 {
+                            ui.add_space(5.);
+                        }
+                    }
+                });
+        });
+    }
+
+    fn show_trash_row(
+        &self,
+        ui: &mut egui::Ui,
+        entry: &TrashEntry,
+        now: DateTime<Utc>,
+        clicked_on_button: &mut bool,
+    ) {
+        let (title, origin) = match &entry.item {
+            TrashedItem::Topic { topic, links, .. } => (
+                format!("{} {}", regular::FOLDER, topic.name),
+                format!(
+                    "{} {}",
+                    links.len(),
+                    if links.len() == 1 { "link" } else { "links" }
+                ),
+            ),
+            TrashedItem::Link { topic, link, .. } => (
+                format!("{} {}", regular::LINK, link.title),
+                format!("from {}", topic),
+            ),
+        };
+
+        Frame::group(ui.style())
+            .inner_margin(Margin::same(9.))
+            .rounding(Rounding::same(9.))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        ui.label(title);
+                        ui.label(
+                            RichText::new(format!(
+                                "{} · deleted {}",
+                                origin,
+                                format_age(entry.deleted_at, now)
+                            ))
+                            .small(),
+                        );
+                    });
+
+                    let available_width = ui.available_width();
+                    let restore = "Restore";
+                    let delete = "Delete";
+
+                    ui.add_space(
+                        available_width
+                            - calc_btn_size_from_text(restore)
+                            - calc_btn_size_from_text(delete)
+                            - 10.,
+                    );
+
+                    custom_button(ui, restore, None, || {
+                        self.tx
+                            .send(AppMessage::RestoreFromTrash(entry.clone()))
+                            .expect("Unable to send");
+                        *clicked_on_button = true;
+                    });
+                    custom_button(ui, delete, None, || {
+                        self.tx
+                            .send(AppMessage::DeleteFromTrash(entry.clone()))
+                            .expect("Unable to send");
+                        *clicked_on_button = true;
+                    });
+                });
+            });
+    }
+
+    fn show_duplicates_page(&mut self, ctx: &egui::Context, clicked_on_button: &mut bool) {
+        let groups = self
+            .duplicate_groups
+            .get_or_insert_with(|| self.bookmark_manager.find_duplicates())
+            .clone();
+
+        // * Top panel
+        TopBottomPanel::top("top_panel_duplicates")
+            .resizable(false)
+            .show_separator_line(false)
+            .default_height(35.0)
+            .show(ctx, |ui| {
+                ui.add_space(5.);
+                ui.horizontal(|ui| {
+                    let back = ui.label(RichText::new(regular::ARROW_LEFT.to_string()).size(20.));
+                    if back.hovered() {
+                        ui.output_mut(|o| o.cursor_icon = CursorIcon::PointingHand);
+                    }
+                    if back.clicked() {
+                        self.app_page = AppPage::Main;
+                        *clicked_on_button = true;
+                    }
+
+                    let available_width = ui.available_width();
+                    let label = "Merge all";
+
+                    ui.add_space(available_width - calc_btn_size_from_text(label));
+
+                    ui.add_enabled_ui(!groups.is_empty(), |ui| {
+                        custom_button(ui, label, None, || {
+                            self.tx
+                                .send(AppMessage::MergeDuplicates(groups.clone()))
+                                .expect("Unable to send");
+                            *clicked_on_button = true;
+                        });
+                    });
+                });
+            });
+
+        // * Duplicates page
+        CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Duplicates");
+                ui.label(
+                    RichText::new(
+                        "Merging keeps the first link with the best title, notes and all tags",
+                    )
+                    .small(),
+                );
+            });
+
+            ui.add_space(5.);
+
+            ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .scroll_bar_visibility(ScrollBarVisibility::VisibleWhenNeeded)
+                .show(ui, |ui| {
+                    if groups.is_empty() {
+                        ui.vertical_centered(|ui| {
+                            ui.label("No duplicates found!");
+                        });
+                        return;
+                    }
+
+                    for (idx, group) in groups.iter().enumerate() {
+                        Frame::group(ui.style())
+                            .inner_margin(Margin::same(9.))
+                            .rounding(Rounding::same(9.))
+                            .show(ui, |ui| {
+                                ui.set_width(ui.available_width());
+                                ui.horizontal(|ui| {
+                                    ui.label(
+                                        RichText::new(group[0].1.url.clone()).strong().small(),
+                                    );
+
+                                    let available_width = ui.available_width();
+                                    let label = "Merge";
+
+                                    ui.add_space(available_width - calc_btn_size_from_text(label));
+
+                                    custom_button(ui, label, None, || {
+                                        self.tx
+                                            .send(AppMessage::MergeDuplicates(vec![group.clone()]))
+                                            .expect("Unable to send");
+                                        *clicked_on_button = true;
+                                    });
+                                });
+
+                                for (name, link) in group {
+                                    ui.label(format!(
+                                        "{} {} · {}",
+                                        regular::LINK,
+                                        link.title,
+                                        name
+                                    ));
+                                }
+                            });
+
+                        if idx < groups.len() - 1 {
+                            ui.add_space(5.);
+                        }
+                    }
+                });
+        });
+    }
+
+    fn show_clean_links_page(&mut self, ctx: &egui::Context, clicked_on_button: &mut bool) {
+        let url_rules = &self.settings.url_rules;
+        let cleanups = self
+            .url_cleanups
+            .get_or_insert_with(|| {
+                self.bookmark_manager
+                    .get_all_links()
+                    .into_iter()
+                    .filter_map(|(name, link)| {
+                        let url = url_rules.canonicalize(&link.url);
+                        (url != link.url).then(|| {
+                            let cleaned = LinkModel {
+                                url,
+                                ..link.clone()
+                            };
+                            (name, link, cleaned)
+                        })
+                    })
+                    .collect()
+            })
+            .clone();
+
+        // * Top panel
+        TopBottomPanel::top("top_panel_clean_links")
+            .resizable(false)
+            .show_separator_line(false)
+            .default_height(35.0)
+            .show(ctx, |ui| {
+                ui.add_space(5.);
+                ui.horizontal(|ui| {
+                    let back = ui.label(RichText::new(regular::ARROW_LEFT.to_string()).size(20.));
+                    if back.hovered() {
+                        ui.output_mut(|o| o.cursor_icon = CursorIcon::PointingHand);
+                    }
+                    if back.clicked() {
+                        self.app_page = AppPage::Settings;
+                        *clicked_on_button = true;
+                    }
+
+                    let available_width = ui.available_width();
+                    let label = "Clean all";
+
+                    ui.add_space(available_width - calc_btn_size_from_text(label));
+
+                    ui.add_enabled_ui(!cleanups.is_empty(), |ui| {
+                        custom_button(ui, label, None, || {
+                            self.tx
+                                .send(AppMessage::EditLinks(cleanups.clone()))
+                                .expect("Unable to send");
+                            *clicked_on_button = true;
+                        });
+                    });
+                });
+            });
+
+        // * Clean links page
+        CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Clean links");
+                ui.label(
+                    RichText::new(format!(
+                        "{} {} with tracking parameters",
+                        cleanups.len(),
+                        if cleanups.len() == 1 { "link" } else { "links" }
+                    ))
+                    .small(),
+                );
+            });
+
+            ui.add_space(5.);
+
+            ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .scroll_bar_visibility(ScrollBarVisibility::VisibleWhenNeeded)
+                .show(ui, |ui| {
+                    if cleanups.is_empty() {
+                        ui.vertical_centered(|ui| {
+                            ui.label("All links are clean!");
+                        });
+                        return;
+                    }
+
+                    for (idx, (name, link, cleaned)) in cleanups.iter().enumerate() {
+                        Frame::group(ui.style())
+                            .inner_margin(Margin::same(9.))
+                            .rounding(Rounding::same(9.))
+                            .show(ui, |ui| {
+                                ui.set_width(ui.available_width());
+                                ui.label(format!("{} {} · {}", regular::LINK, link.title, name));
+                                ui.label(
+                                    RichText::new(link.url.clone())
+                                        .small()
+                                        .strikethrough()
+                                        .weak(),
+                                );
+                                ui.label(RichText::new(cleaned.url.clone()).small());
+                            });
+
+                        if idx < cleanups.len() - 1 {
+                            ui.add_space(5.);
+                        }
+                    }
+                });
+        });
+    }
+
+    fn show_link_report_page(&mut self, ctx: &egui::Context, clicked_on_button: &mut bool) {
+        let links = self.bookmark_manager.get_all_links();
+        let unchecked = links
+            .iter()
+            .filter(|(_, link)| link.health.is_none())
+            .count();
+        let tabs = [
+            LinkReportTab::Broken,
+            LinkReportTab::Redirected,
+            LinkReportTab::Ok,
+        ];
+        let count = |tab: LinkReportTab| {
+            links
+                .iter()
+                .filter(|(_, link)| link.health.as_deref().map(LinkReportTab::of) == Some(tab))
+                .count()
+        };
+        let counts = tabs.map(count);
+        let rows = links
+            .iter()
+            .filter(|(_, link)| {
+                link.health.as_deref().map(LinkReportTab::of) == Some(self.link_report_tab)
+            })
+            .cloned()
+            .collect::<Vec<(String, LinkModel)>>();
+        let check = self
+            .jobs
+            .running(JobKind::LinkCheck)
+            .map(|job| (job.id, job.done, job.total));
+
+        // * Top panel
+        TopBottomPanel::top("top_panel_link_report")
+            .resizable(false)
+            .show_separator_line(false)
+            .default_height(35.0)
+            .show(ctx, |ui| {
+                ui.add_space(5.);
+                ui.horizontal(|ui| {
+                    let back = ui.label(RichText::new(regular::ARROW_LEFT.to_string()).size(20.));
+                    if back.hovered() {
+                        ui.output_mut(|o| o.cursor_icon = CursorIcon::PointingHand);
+                    }
+                    if back.clicked() {
+                        self.app_page = AppPage::Main;
+                        *clicked_on_button = true;
+                    }
+
+                    let available_width = ui.available_width();
+                    let (label, msg) = match check {
+                        Some((id, _, _)) => ("Cancel", AppMessage::CancelJob(id)),
+                        None => ("Check links", AppMessage::CheckLinks),
+                    };
+
+                    ui.add_space(available_width - calc_btn_size_from_text(label));
+
+                    custom_button(ui, label, None, || {
+                        self.tx.send(msg).expect("Unable to send");
+                        *clicked_on_button = true;
+                    });
+                });
+            });
+
+        // * Link report page
+        CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Link report");
+                let subtitle = match check {
+                    Some((_, done, total)) => format!("Checking {} of {} links", done, total),
+                    None if unchecked > 0 => format!(
+                        "{} {} not checked yet",
+                        unchecked,
+                        if unchecked == 1 { "link" } else { "links" }
+                    ),
+                    None => "Every link has been checked".to_owned(),
+                };
+                ui.label(RichText::new(subtitle).small());
+            });
+
+            if let Some((_, done, total)) = check {
+                ui.add(ProgressBar::new(done as f32 / total.max(1) as f32).desired_height(6.));
+            }
+
+            ui.add_space(5.);
+
+            ui.horizontal(|ui| {
+                for (tab, count) in tabs.iter().zip(counts) {
+                    ui.selectable_value(
+                        &mut self.link_report_tab,
+                        *tab,
+                        format!("{} ({})", tab.name(), count),
+                    );
+                }
+
+                let actions = self.link_report_tab.actions();
+                let width = actions
+                    .iter()
+                    .map(|action| calc_btn_size_from_text(action.label(true)) + 5.)
+                    .sum::<f32>();
+                ui.add_space(ui.available_width() - width);
+
+                ui.add_enabled_ui(!rows.is_empty(), |ui| {
+                    for action in actions {
+                        custom_button(ui, action.label(true), None, || {
+                            self.send_link_report_action(*action, &rows);
+                            *clicked_on_button = true;
+                        });
+                    }
+                });
+            });
+
+            ui.add_space(5.);
+
+            ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .scroll_bar_visibility(ScrollBarVisibility::VisibleWhenNeeded)
+                .show(ui, |ui| {
+                    if rows.is_empty() {
+                        ui.vertical_centered(|ui| {
+                            ui.label(match self.link_report_tab {
+                                LinkReportTab::Broken => "No broken links!",
+                                LinkReportTab::Redirected => "No redirected links!",
+                                LinkReportTab::Ok => "No links checked yet!",
+                            });
+                        });
+                        return;
+                    }
+
+                    let now = Utc::now();
+                    for (idx, row) in rows.iter().enumerate() {
+                        self.show_link_report_row(ui, row, now, clicked_on_button);
+
+                        if idx < rows.len() - 1 {
+                            ui.add_space(5.);
+                        }
+                    }
+                });
+        });
+    }
+
+    fn show_link_report_row(
+        &self,
+        ui: &mut egui::Ui,
+        row: &(String, LinkModel),
+        now: DateTime<Utc>,
+        clicked_on_button: &mut bool,
+    ) {
+        let (name, link) = row;
+        let Some(health) = &link.health else {
+            return;
+        };
+
+        let mut status = match (&health.error, health.status) {
+            (Some(error), _) => format!("Unreachable: {}", error),
+            (None, Some(status)) => format!("HTTP {}", status),
+            (None, None) => "No answer".to_owned(),
+        };
+        if health.failures > 1 {
+            status.push_str(&format!(" · failed {} times in a row", health.failures));
+        }
+        status.push_str(&format!(
+            " · checked {}",
+            format_age(health.checked_at, now)
+        ));
+
+        Frame::group(ui.style())
+            .inner_margin(Margin::same(9.))
+            .rounding(Rounding::same(9.))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        ui.label(format!("{} {} · {}", regular::LINK, link.title, name));
+                        ui.label(RichText::new(link.url.clone()).small().weak());
+                        if let Some(redirect) = &health.redirect {
+                            ui.label(
+                                RichText::new(format!("{} {}", regular::ARROW_RIGHT, redirect))
+                                    .small(),
+                            );
+                        }
+                        ui.label(RichText::new(status).small());
+                    });
+
+                    let actions = LinkReportTab::of(health).actions();
+                    let width = actions
+                        .iter()
+                        .map(|action| calc_btn_size_from_text(action.label(false)) + 5.)
+                        .sum::<f32>();
+                    ui.add_space(ui.available_width() - width);
+
+                    for action in actions {
+                        custom_button(ui, action.label(false), None, || {
+                            self.send_link_report_action(*action, std::slice::from_ref(row));
+                            *clicked_on_button = true;
+                        });
+                    }
+                });
+            });
+    }
+
+    /// Updates redirected links to where they lead, or moves broken ones to the
+    /// "Broken" topic or the trash.
+    fn send_link_report_action(&self, action: LinkReportAction, rows: &[(String, LinkModel)]) {
+        let msgs = match action {
+            LinkReportAction::Update => {
+                let edits = rows
+                    .iter()
+                    .filter_map(|(name, link)| {
+                        let health = link.health.clone()?;
+                        let url = self
+                            .settings
+                            .url_rules
+                            .canonicalize(health.redirect.as_ref()?);
+                        let updated = LinkModel {
+                            url,
+                            health: Some(Box::new(LinkHealth {
+                                redirect: None,
+                                ..*health
+                            })),
+                            resolved_url: None,
+                            ..link.clone()
+                        };
+                        Some((name.clone(), link.clone(), updated))
+                    })
+                    .collect();
+                vec![AppMessage::EditLinks(edits)]
+            }
+            LinkReportAction::MoveToBroken => {
+                let mut msgs = vec![];
+                let broken = TopicModel::new(BROKEN_TOPIC.to_owned());
+                if !self.bookmark_manager.get_topics().contains(&broken) {
+                    msgs.push(AppMessage::AddTopic(broken));
+                }
+                for (name, links) in group_by_topic(rows) {
+                    if name != BROKEN_TOPIC {
+                        msgs.push(AppMessage::MoveLinks(name, links, BROKEN_TOPIC.to_owned()));
+                    }
+                }
+                msgs
+            }
+            LinkReportAction::Delete => vec![AppMessage::RemoveLinks(rows.to_vec())],
+        };
+
+        for msg in msgs {
+            self.tx.send(msg).expect("Unable to send");
+        }
+    }
+
+    /// Saved short links not resolved yet, as `(topic name, link)`.
+    fn unresolved_short_links(&self) -> Vec<(String, LinkModel)> {
+        self.bookmark_manager
+            .get_all_links()
+            .into_iter()
+            .filter(|(_, link)| is_short_link(&link.url) && link.resolved_url.is_none())
+            .collect()
+    }
+
+    /// Edits replacing the links given as `(topic name, url)` with where they resolved to.
+    fn expanded_link_edits(
+        &self,
+        links: &[(String, String)],
+    ) -> Vec<(String, LinkModel, LinkModel)> {
+        links
+            .iter()
+            .filter_map(|(name, url)| {
+                let link = self
+                    .bookmark_manager
+                    .get_links_for_topic(&BookmarkItem::Topic(TopicModel::new(name.clone())))
+                    .into_iter()
+                    .find(|link| &link.url == url)?;
+                let expanded = LinkModel {
+                    url: self
+                        .settings
+                        .url_rules
+                        .canonicalize(link.resolved_url.as_ref()?),
+                    resolved_url: None,
+                    health: link.health.clone().map(|health| {
+                        Box::new(LinkHealth {
+                            redirect: None,
+                            ..*health
+                        })
+                    }),
+                    ..link.clone()
+                };
+                Some((name.clone(), link, expanded))
+            })
+            .collect()
+    }
+
+    /// Looks up where `links`, given as `(topic name, url)`, redirect to.
+    fn resolve_links(&mut self, ctx: &egui::Context, links: Vec<(String, String)>) {
+        let label = match links.as_slice() {
+            [(_, url)] => format!("Resolving {}", url),
+            links => format!("Resolving {} links", links.len()),
+        };
+        let checker = Arc::new(LinkChecker::default());
+        let pacer = checker.clone();
+        let id = self.jobs.spawn_paced(
+            ctx,
+            JobKind::Resolve,
+            label,
+            links,
+            move |(_, url)| pacer.wait_for_host(url),
+            move |(topic, url)| JobOutput::Resolved(checker.check(topic, url)),
+        );
+        self.resolved.insert(id, vec![]);
+    }
+
+    /// Records what an item of job `id` found.
+    fn apply_job_output(&mut self, id: JobId, output: JobOutput) {
+        let finished = self.jobs.record(id, &output).cloned();
+
+        match output {
+            JobOutput::Imported(Ok(topics)) => {
+                // * Merged as a message of its own, so it can be undone
+                self.tx
+                    .send(AppMessage::ImportTopics(topics))
+                    .expect("Unable to send");
+            }
+            JobOutput::Imported(Err(e)) => {
+                self.toast = Some(Toast::new(
+                    format!("Couldn't import bookmarks: {}", e),
+                    None,
+                ));
+            }
+            JobOutput::Exported(Ok(path)) => {
+                open_file_location(OpenLocationType::Custom(path));
+            }
+            JobOutput::Exported(Err(e)) => {
+                self.toast = Some(Toast::new(format!("Couldn't export: {}", e), None));
+            }
+            JobOutput::Fetched {
+                topic,
+                url,
+                meta: Ok(meta),
+            } => {
+                self.bookmark_manager.record_meta(&topic, &url, &meta);
+            }
+            JobOutput::Fetched {
+                url, meta: Err(e), ..
+            } => {
+                eprintln!("Failed to fetch {}: {}", url, e);
+            }
+            JobOutput::Checked(check) => {
+                self.bookmark_manager
+                    .record_checks(std::slice::from_ref(&check));
+            }
+            JobOutput::Resolved(check) => {
+                self.bookmark_manager
+                    .record_resolved(std::slice::from_ref(&check));
+                if let Some(checks) = self.resolved.get_mut(&id) {
+                    checks.push(check);
+                }
+            }
+            JobOutput::Archived(result) => {
+                if result.error.is_none() {
+                    self.bookmark_manager
+                        .mark_archived(&[(result.topic, result.url)]);
+                }
+            }
+        }
+
+        if let Some(job) = finished {
+            self.finish_job(&job);
+        }
+    }
+
+    /// Tells how the job that just finished went.
+    fn finish_job(&mut self, job: &Job) {
+        let (succeeded, total, failed) = (job.done - job.failed, job.total, job.failed);
+        let toast = match job.kind {
+            // * Errors were shown as they came in and exports show the file
+            JobKind::Import | JobKind::Export if failed > 0 => return,
+            JobKind::Export => return,
+            JobKind::Import => Toast::new("Imported bookmarks".to_owned(), None),
+            JobKind::Metadata => Toast::new(
+                match failed {
+                    0 => format!(
+                        "Fetched {} {}",
+                        total,
+                        if total == 1 { "page" } else { "pages" }
+                    ),
+                    failed => format!(
+                        "Fetched {} of {} pages, {} failed",
+                        succeeded, total, failed
+                    ),
+                },
+                None,
+            ),
+            JobKind::LinkCheck => Toast::new(
+                format!(
+                    "Checked {} {}",
+                    total,
+                    if total == 1 { "link" } else { "links" }
+                ),
+                Some((
+                    "Report".to_owned(),
+                    AppMessage::SetPage(AppPage::LinkReport),
+                )),
+            ),
+            JobKind::Archive => Toast::new(
+                match failed {
+                    0 => format!(
+                        "Archived {} {}",
+                        total,
+                        if total == 1 { "page" } else { "pages" }
+                    ),
+                    failed => format!(
+                        "Archived {} of {} pages, {} failed",
+                        succeeded, total, failed
+                    ),
+                },
+                None,
+            ),
+            JobKind::Resolve => {
+                let checks = self.resolved.remove(&job.id).unwrap_or_default();
+                let redirected = checks
+                    .iter()
+                    .filter(|check| check.error.is_none() && check.redirect.is_some())
+                    .map(|check| (check.topic.clone(), check.url.clone()))
+                    .collect::<Vec<(String, String)>>();
+                let edits = self.expanded_link_edits(&redirected);
+                let action = (!edits.is_empty()).then(|| {
+                    let label = if edits.len() == 1 {
+                        "Replace"
+                    } else {
+                        "Replace all"
+                    };
+                    (label.to_owned(), AppMessage::EditLinks(edits))
+                });
+
+                let text = match checks.as_slice() {
+                    [check] => match (&check.error, &check.redirect) {
+                        (Some(e), _) => format!("Couldn't resolve {}: {}", check.url, e),
+                        (None, Some(redirect)) => format!("{} goes to {}", check.url, redirect),
+                        (None, None) => format!("{} doesn't redirect", check.url),
+                    },
+                    checks => format!(
+                        "Resolved {} links, {} go somewhere else",
+                        checks.len(),
+                        redirected.len()
+                    ),
+                };
+                Toast::new(text, action)
+            }
+        };
+        self.toast = Some(toast);
+    }
+
+    /// Lists the background jobs with their progress, collapsed to a summary line.
+    fn show_jobs_panel(&mut self, ctx: &egui::Context, clicked_on_button: &mut bool) {
+        let jobs = self.jobs.jobs().to_vec();
+        if jobs.is_empty() {
+            return;
+        }
+
+        TopBottomPanel::bottom("bottom_panel_jobs")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add_space(3.);
+                ui.horizontal(|ui| {
+                    let running = jobs.iter().filter(|job| job.is_running()).count();
+                    let caret = if self.is_jobs_panel_open {
+                        regular::CARET_DOWN
+                    } else {
+                        regular::CARET_RIGHT
+                    };
+                    let summary = match running {
+                        0 => "all done".to_owned(),
+                        running => format!("{} running", running),
+                    };
+                    let header = ui
+                        .selectable_label(false, format!("{} Background jobs, {}", caret, summary));
+                    if header.clicked() {
+                        self.is_jobs_panel_open = !self.is_jobs_panel_open;
+                        *clicked_on_button = true;
+                    }
+
+                    if running < jobs.len() {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.small_button("Clear finished").clicked() {
+                                self.jobs.clear_finished();
+                                *clicked_on_button = true;
+                            }
+                        });
+                    }
+                });
+
+                if self.is_jobs_panel_open {
+                    for job in &jobs {
+                        ui.horizontal(|ui| {
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                match job.state {
+                                    JobState::Running => {
+                                        if ui
+                                            .small_button(regular::X)
+                                            .on_hover_text("Cancel")
+                                            .clicked()
+                                        {
+                                            self.tx
+                                                .send(AppMessage::CancelJob(job.id))
+                                                .expect("Unable to send");
+                                            *clicked_on_button = true;
+                                        }
+                                    }
+                                    JobState::Done if job.failed > 0 => {
+                                        ui.label(
+                                            RichText::new(format!("{} failed", job.failed)).small(),
+                                        );
+                                    }
+                                    JobState::Done => {
+                                        ui.label(RichText::new("Done").small());
+                                    }
+                                    JobState::Cancelled => {
+                                        ui.label(RichText::new("Cancelled").small());
+                                    }
+                                }
+                                ui.add(
+                                    ProgressBar::new(job.progress())
+                                        .desired_width(120.)
+                                        .desired_height(6.),
+                                );
+                                ui.label(
+                                    RichText::new(format!("{}/{}", job.done, job.total)).small(),
+                                );
+
+                                ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+                                    ui.add(Label::new(&job.label).truncate(true));
+                                });
+                            });
+                        });
+                    }
+                }
+                ui.add_space(3.);
+            });
+    }
+
+    /// Drops selected links that are gone and refreshes the rest.
+    fn prune_selection(&mut self) {
+        // * Links updated in the background stay selected, as they are now
+        self.selected_links = std::mem::take(&mut self.selected_links)
+            .into_iter()
+            .filter_map(|(name, link)| {
+                let link = self.bookmark_manager.find_link(&name, &link.url)?;
+                Some((name, link))
+            })
+            .collect();
+    }
+
+    fn update_search_results(&mut self) {
+        let query = self.search_query.trim();
+        if query == self.searched_query {
+            return;
+        }
+
+        if query.is_empty() {
+            self.search_results = Vec::new();
+            self.search_error = None;
+        } else {
+            let parsed = Query::parse(query);
+            self.search_error = parsed.as_ref().err().map(|e| e.to_string());
+            self.search_results = self
+                .bookmark_manager
+                .search(&parsed.unwrap_or_else(|_| Query::parse_lenient(query)));
+        }
+        self.searched_query = query.to_owned();
+    }
+
+    fn update_smart_topic_hits(&mut self) {
+        if self.smart_topic_hits.is_some() {
+            return;
+        }
+
+        let hits = self
+            .bookmark_manager
+            .get_smart_topics()
+            .iter()
+            .map(|smart_topic| {
+                self.bookmark_manager
+                    .search(&Query::parse_lenient(&smart_topic.query))
+            })
+            .collect();
+        self.smart_topic_hits = Some(hits);
+    }
+
+    fn show_search_results(
+        &mut self,
+        ui: &mut egui::Ui,
+        modal: &Modal,
+        clicked_on_button: &mut bool,
+    ) {
+        ui.horizontal(|ui| {
+            if let Some(error) = &self.search_error {
+                ui.label(
+                    RichText::new(error)
+                        .small()
+                        .color(ui.visuals().error_fg_color),
+                );
+            } else {
+                ui.label(RichText::new(format!("{} results", self.search_results.len())).small());
+            }
+
+            let available_width = ui.available_width();
+            let label = "Save as smart topic";
+
+            ui.add_space(available_width - calc_btn_size_from_text(label));
+
+            custom_button(ui, label, None, || {
+                self.open_add_smart_topic_viewport(self.searched_query.clone());
+                *clicked_on_button = true;
+            });
+        });
+
+        ui.add_space(5.);
+
+        ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .scroll_bar_visibility(ScrollBarVisibility::VisibleWhenNeeded)
+            .show(ui, |ui| {
+                if self.search_results.is_empty() {
+                    ui.vertical_centered(|ui| {
+                        ui.label("No results!");
+                    });
+                    return;
+                }
+
+                if self.search_results.len() > MAX_SEARCH_RESULTS {
+                    ui.label(
+                        RichText::new(format!(
+                            "Showing the best {} of {} results",
+                            MAX_SEARCH_RESULTS,
+                            self.search_results.len()
+                        ))
+                        .small(),
+                    );
+                    ui.add_space(5.);
+                }
+
+                let hits = self
+                    .search_results
+                    .iter()
+                    .take(MAX_SEARCH_RESULTS)
+                    .cloned()
+                    .collect::<Vec<SearchHit>>();
+                for (idx, hit) in hits.iter().enumerate() {
+                    self.show_hit_row(
+                        ui,
+                        format!("search_hit_{}", idx),
+                        hit,
+                        modal,
+                        clicked_on_button,
+                    );
+
+                    if idx < hits.len() - 1 {
+                        ui.add_space(5.);
+                    }
+                }
+            });
+    }
+
+    /// A link row that also shows its topic and URL, with the matched chars highlighted.
+    fn show_hit_row(
+        &mut self,
+        ui: &mut egui::Ui,
+        id_str: String,
+        hit: &SearchHit,
+        modal: &Modal,
+        clicked_on_button: &mut bool,
+    ) {
+        let is_selected = self
+            .selected_links
+            .contains(&(hit.topic.name.clone(), hit.link.clone()));
+        let hit_frame = Frame::group(ui.style())
+            .shadow(Shadow::default())
+            .rounding(Rounding::same(9.))
+            .inner_margin(Margin::same(9.))
+            .fill(selection_fill(ui, is_selected))
+            .show(ui, |ui| {
+                ui.push_id(id_str, |ui| {
+                    let hit_ui = ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.label(highlighted_text(
+                                ui,
+                                &hit.link.title,
+                                &hit.title_indices,
+                                TextStyle::Body.resolve(ui.style()),
+                            ));
+                            ui.horizontal(|ui| {
+                                ui.spacing_mut().item_spacing.x = 0.;
+                                let small = TextStyle::Small.resolve(ui.style());
+                                ui.label(highlighted_text(
+                                    ui,
+                                    &hit.topic.name,
+                                    &hit.topic_indices,
+                                    small.clone(),
+                                ));
+                                ui.label(RichText::new(" · ").small());
+                                ui.label(highlighted_text(
+                                    ui,
+                                    &hit.link.url,
+                                    &hit.url_indices,
+                                    small,
+                                ));
+                            });
+                            if let Some(snippet) = &hit.snippet {
+                                let mut job = highlighted_text(
+                                    ui,
+                                    &snippet.text,
+                                    &snippet.indices,
+                                    TextStyle::Small.resolve(ui.style()),
+                                );
+                                // * Wrapped, leaving room for the open button
+                                job.wrap.max_width =
+                                    ui.available_width() - calc_btn_size_from_text("Open") - 10.;
+                                ui.label(job);
+                            }
+                        });
+
+                        let available_width = ui.available_width();
+                        let label = "Open";
+
+                        ui.add_space(available_width - calc_btn_size_from_text(label));
+
+                        custom_button(ui, label, None, || {
+                            self.links_to_open.push(hit.link.url.clone());
+                            *clicked_on_button = true;
+                            modal.open();
+                        });
+                    });
+                    hit_ui.response.context_menu(|ui| {
+                        if ui.button("Edit link").clicked() {
+                            self.open_edit_link_viewport(hit.topic.name.clone(), hit.link.clone());
+                            ui.close_menu();
+                            *clicked_on_button = true;
+                        }
+                        self.show_transfer_menus(
+                            ui,
+                            &self.links_for_action(&hit.topic.name, &hit.link),
+                            clicked_on_button,
+                        );
+                        if ui.button("Remove link").clicked() {
+                            self.tx
+                                .send(AppMessage::RemoveLink(
+                                    hit.topic.name.clone(),
+                                    hit.link.clone(),
+                                ))
+                                .expect("Unable to send");
+                            ui.close_menu();
+                            *clicked_on_button = true;
+                        }
+                    });
+                });
+            });
+        self.handle_selection_click(
+            ui,
+            hit_frame.response.rect,
+            &hit.topic.name,
+            &hit.link,
+            None,
+        );
+    }
+
+    fn show_smart_topics(
+        &mut self,
+        ui: &mut egui::Ui,
+        modal: &Modal,
+        clicked_on_button: &mut bool,
+    ) {
+        self.update_smart_topic_hits();
+        let smart_topic_hits = self.smart_topic_hits.clone().unwrap_or_default();
+
+        let smart_topics = self.bookmark_manager.get_smart_topics();
+        for (idx, (smart_topic, hits)) in smart_topics.iter().zip(smart_topic_hits).enumerate() {
+            let is_expanded = self
+                .expanded_smart_topics
+                .get(idx)
+                .copied()
+                .unwrap_or(false);
+
+            ui.push_id(format!("smart_topic_{}", idx), |ui| {
+                // ? Smart topic header
+                let header_res = ui.horizontal(|ui| {
+                    ui.allocate_ui(vec2(50., 50.), |ui| {
+                        ui.horizontal_centered(|ui| {
+                            ui.label(
+                                RichText::new(if !is_expanded {
+                                    regular::CARET_RIGHT
+                                } else {
+                                    regular::CARET_DOUBLE_DOWN
+                                })
+                                .size(12.),
+                            );
+                        });
+                    });
+
+                    Frame::group(ui.style())
+                        .inner_margin(Margin::same(9.))
+                        .rounding(Rounding::same(9.))
+                        .show(ui, |ui| {
+                            ui.label(
+                                RichText::new(format!(
+                                    "{} {} ({})",
+                                    regular::FUNNEL,
+                                    smart_topic.name,
+                                    hits.len()
+                                ))
+                                .size(20.),
+                            )
+                            .on_hover_text(&smart_topic.query);
+
+                            let available_width = ui.available_width();
+                            let label = "Open All";
+
+                            ui.add_space(available_width - calc_btn_size_from_text(label));
+
+                            custom_button(ui, label, None, || {
+                                self.links_to_open
+                                    .extend(hits.iter().map(|hit| hit.link.url.clone()));
+                                *clicked_on_button = true;
+                                modal.open();
+                            });
+                        });
+                });
+
+                let header_response = header_res.response.clone();
+
+                header_response.context_menu(|ui| {
+                    if ui.button("Show in search").clicked() {
+                        self.search_query = smart_topic.query.clone();
+                        ui.close_menu();
+                        *clicked_on_button = true;
+                    }
+                    if ui.button("Remove smart topic").clicked() {
+                        self.tx
+                            .send(AppMessage::RemoveSmartTopic(smart_topic.clone()))
+                            .expect("Unable to send");
+                        ui.close_menu();
+                        *clicked_on_button = true;
+                    }
+                });
+
+                let hov_pos = ui.input(|i| i.pointer.hover_pos().unwrap_or(Pos2::ZERO));
+                if header_response.rect.contains(hov_pos)
+                    && ui.input(|i| i.pointer.primary_clicked())
+                    && !*clicked_on_button
+                {
+                    if let Some(is_expanded) = self.expanded_smart_topics.get_mut(idx) {
+                        *is_expanded = !*is_expanded;
+                    }
+                }
+
+                if is_expanded {
+                    ui.add_space(5.);
+
+                    if hits.is_empty() {
+                        ui.vertical_centered(|ui| {
+                            ui.label("No links!");
+                        });
+                    }
+                    for (link_idx, hit) in hits.iter().enumerate() {
+                        self.show_hit_row(
+                            ui,
+                            format!("smart_topic_{}_{}", idx, link_idx),
+                            hit,
+                            modal,
+                            clicked_on_button,
+                        );
+
+                        if link_idx < hits.len() - 1 {
+                            ui.add_space(5.);
+                        }
+                    }
+                }
+            });
+
+            ui.add_space(5.);
+        }
+    }
+}
+
+impl eframe::App for StashApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // * First run
+        if self.is_first_run {
+            self.is_first_run = false;
+
+            let center = ctx
+                .input(|i| i.viewport().outer_rect)
+                .unwrap_or(Rect::NOTHING)
+                .center();
+            self.initial_viewport_center = Pos2::new(center.x - 170., center.y - 120.);
+
+            egui_extras::install_image_loaders(ctx);
+
+            self.current_theme.set_theme(ctx);
+            if self.window_level == WindowLevel::AlwaysOnTop {
+                ctx.send_viewport_cmd(ViewportCommand::Title("Stash: AlwaysOnTop".to_owned()));
+            }
+
+            let mut fonts = FontDefinitions::default();
+            egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
+            ctx.set_fonts(fonts);
+
+            #[cfg(target_os = "linux")]
+            {
+                self.dbus_service = Some(DbusService::start(ctx.clone(), self.tx.clone()));
+            }
+        }
+
+        let min_size = *MIN_SIZE;
+        let mut clicked_on_button = false;
+
+        // * Command palette
+        if ctx.input_mut(|i| i.consume_shortcut(&command_palette::TOGGLE_SHORTCUT)) {
+            if self.command_palette.is_open() {
+                self.command_palette.close();
+            } else {
+                self.command_palette.open(self.palette_commands());
+            }
+        }
+
+        // * Undo and redo, text fields keep their own
+        if !ctx.wants_keyboard_input() {
+            // * Redo first, since Ctrl+Z also matches Ctrl+Shift+Z
+            let (redo, undo) = ctx.input_mut(|i| {
+                (
+                    i.consume_shortcut(&REDO_SHORTCUT),
+                    i.consume_shortcut(&UNDO_SHORTCUT),
+                )
+            });
+            if redo {
+                self.tx.send(AppMessage::Redo).expect("Unable to send");
+            } else if undo {
+                self.tx.send(AppMessage::Undo).expect("Unable to send");
+            }
+        }
+
+        // * Clear selection
+        if !self.selected_links.is_empty()
+            && !self.command_palette.is_open()
+            && !ctx.wants_keyboard_input()
+            && ctx.input(|i| i.key_pressed(Key::Escape))
+        {
+            self.selected_links.clear();
+        }
+
+        // * Open modal
+        let model_style = ModalStyle {
+            default_width: Some(min_size[0] - 20.),
+            ..Default::default()
+        };
+        let modal = Modal::new(ctx, "open_links_confirmation_modal")
+            .with_close_on_outside_click(true)
+            .with_style(&model_style);
+        modal.show(|ui| {
+            modal.title(ui, "Opening links");
+            modal.frame(ui, |ui| {
+                modal.body(
+                    ui,
+                    format!(
+                        "Are you sure you want to open {} {}?",
+                        self.links_to_open.len(),
+                        if self.links_to_open.len() == 1 {
+                            "link"
+                        } else {
+                            "links"
+                        }
+                    ),
+                );
+            });
+            modal.buttons(ui, |ui| {
+                if modal.button(ui, "Close").clicked() {
+                    self.links_to_open.clear();
+                    clicked_on_button = true;
+                };
+
+                ui.add_space(5.);
+
+                if modal.button(ui, "Open").clicked() {
+                    open_urls(&self.links_to_open);
+                    self.tx
+                        .send(AppMessage::MarkOpened(self.links_to_open.clone()))
+                        .expect("Unable to send");
+                    self.links_to_open.clear();
+                    clicked_on_button = true;
+                };
+            });
+        });
+
+        // * Remove confirmation modal
+        let remove_modal = Modal::new(ctx, "remove_confirmation_modal")
+            .with_close_on_outside_click(true)
+            .with_style(&model_style);
+        remove_modal.show(|ui| {
+            remove_modal.title(ui, "Removing");
+            remove_modal.frame(ui, |ui| {
+                let target = match &self.pending_removal {
+                    Some(AppMessage::RemoveTopic(topic)) => {
+                        format!("the topic \"{}\" and all its links", topic.name)
+                    }
+                    Some(AppMessage::RemoveLink(_, link)) => format!("\"{}\"", link.title),
+                    Some(AppMessage::RemoveLinks(links)) => format!("{} links", links.len()),
+                    _ => "this".to_owned(),
+                };
+                remove_modal.body(
+                    ui,
+                    format!(
+                        "Are you sure you want to remove {}? It can be restored from the Trash.",
+                        target
                     ),
                 );
             });
-            modal.buttons(ui, |ui| {
-                if modal.button(ui, "Close").clicked() {
-                    self.links_to_open.clear();
+            remove_modal.buttons(ui, |ui| {
+                if remove_modal.button(ui, "Cancel").clicked() {
+                    self.pending_removal = None;
                     clicked_on_button = true;
                 };
 
                 ui.add_space(5.);
 
-                if modal.button(ui, "Open").clicked() {
-                    open_urls(&self.links_to_open);
-                    self.links_to_open.clear();
+                if remove_modal.caution_button(ui, "Remove").clicked() {
+                    if let Some(msg) = self.pending_removal.take() {
+                        self.tx.send(msg).expect("Unable to send");
+                    }
                     clicked_on_button = true;
                 };
             });
         });
 
-        // * Handle app messages
-        if let Ok(msg) = self.rx.try_recv() {
-            match msg {
-                // * Topics
-                AppMessage::AddTopic(topic) => {
-                    self.bookmark_manager.add_topic(BookmarkItem::Topic(topic));
-                    self.expanded_topics.push(false);
-                }
-                AppMessage::EditTopic(old_topic, new_topic) => {
-                    self.bookmark_manager.edit_topic(
-                        BookmarkItem::Topic(old_topic.clone()),
-                        BookmarkItem::Topic(new_topic),
-                    );
-                }
-                AppMessage::RemoveTopic(topic) => {
-                    self.bookmark_manager
-                        .remove_topic(BookmarkItem::Topic(topic.clone()));
-
-                    let idx = self
-                        .bookmark_manager
-                        .get_topics()
-                        .iter()
-                        .position(|t| t == &topic)
-                        .unwrap_or_default();
-                    self.expanded_topics.remove(idx);
-                }
-
-                // * Links
-                AppMessage::AddLink(name, link) => {
-                    let topic = self
-                        .bookmark_manager
-                        .get_topics()
-                        .iter()
-                        .find(|t| t.name == name)
-                        .expect("Topic not found")
-                        .clone();
-
-                    self.bookmark_manager
-                        .add_link(BookmarkItem::Topic(topic.clone()), BookmarkItem::Link(link));
-                }
-                AppMessage::EditLink(name, old_link, link) => {
-                    let topic = self
-                        .bookmark_manager
-                        .get_topics()
-                        .iter()
-                        .find(|t| t.name == name)
-                        .expect("Topic not found")
-                        .clone();
-
-                    self.bookmark_manager.edit_link(
-                        BookmarkItem::Topic(topic.clone()),
-                        BookmarkItem::Link(old_link),
-                        BookmarkItem::Link(link),
-                    );
-                }
-                AppMessage::RemoveLink(name, link) => {
-                    let topic = self
-                        .bookmark_manager
-                        .get_topics()
-                        .iter()
-                        .find(|t| t.name == name)
-                        .expect("Topic not found")
-                        .clone();
+        self.favicons.poll();
+
+        // * Handle the app messages sent since the last frame, saving once after all of them
+        let mut has_messages = false;
+        let mut has_changes = false;
+        #[cfg(target_os = "linux")]
+        let mut has_bookmark_mutation = false;
+        self.bookmark_manager.hold_saves();
+        while let Ok(msg) = self.rx.try_recv() {
+            // * Answered from the bookmarks as of the messages before it
+            #[cfg(target_os = "linux")]
+            if matches!(msg, AppMessage::Dbus(_)) {
+                self.apply_message(ctx, &modal, msg);
+                continue;
+            }
 
-                    self.bookmark_manager
-                        .remove_link(BookmarkItem::Topic(topic.clone()), BookmarkItem::Link(link));
-                }
+            has_messages = true;
+            has_changes |= msg.changes_bookmarks();
+            #[cfg(target_os = "linux")]
+            {
+                has_bookmark_mutation |= msg.is_bookmark_mutation();
+            }
 
-                // * UI
-                AppMessage::ToggleCollapsed(index) => {
-                    let is_expanded = self.expanded_topics.get(index).copied().unwrap_or(false);
-                    self.expanded_topics[index] = !is_expanded;
-                }
+            // * Links are saved without their tracking parameters
+            let url_rules = &self.settings.url_rules;
+            let msg = match msg {
+                AppMessage::AddLink(name, link) => AppMessage::AddLink(
+                    name,
+                    LinkModel {
+                        url: url_rules.canonicalize(&link.url),
+                        ..link
+                    },
+                ),
+                AppMessage::EditLink(name, old_link, link) => AppMessage::EditLink(
+                    name,
+                    old_link,
+                    LinkModel {
+                        url: url_rules.canonicalize(&link.url),
+                        ..link
+                    },
+                ),
+                AppMessage::ImportTopics(topics) => AppMessage::ImportTopics(
+                    topics
+                        .into_iter()
+                        .map(|(name, links)| {
+                            let links = links
+                                .into_iter()
+                                .map(|link| LinkModel {
+                                    url: url_rules.canonicalize(&link.url),
+                                    ..link
+                                })
+                                .collect();
+                            (name, links)
+                        })
+                        .collect(),
+                ),
+                msg => msg,
+            };
 
-                // * Misc
-                AppMessage::ToggleAlwaysOnTop => {
-                    self.window_level = match self.window_level {
-                        WindowLevel::Normal => {
-                            ctx.send_viewport_cmd(ViewportCommand::Title(
-                                "Stash: AlwaysOnTop".to_owned(),
+            match msg {
+                AppMessage::Undo | AppMessage::Redo => self.apply_message(ctx, &modal, msg),
+                // * Background results aren't undoable, the history finds links by URL anyway
+                AppMessage::JobOutput(id, output) => self.apply_job_output(id, output),
+                msg => {
+                    let is_removal = msg.is_removal();
+                    if let Some(entry) = history::apply(
+                        self,
+                        |app| &mut app.bookmark_manager,
+                        msg,
+                        |app, msg| app.apply_message(ctx, &modal, msg),
+                    ) {
+                        if is_removal {
+                            self.toast = Some(Toast::new(
+                                entry.label.clone(),
+                                Some(("Undo".to_owned(), AppMessage::Undo)),
                             ));
-                            WindowLevel::AlwaysOnTop
                         }
-                        WindowLevel::AlwaysOnTop => {
-                            ctx.send_viewport_cmd(ViewportCommand::Title("Stash".to_owned()));
-                            WindowLevel::Normal
-                        }
-                        _ => WindowLevel::Normal,
-                    };
-
-                    ctx.send_viewport_cmd(ViewportCommand::WindowLevel(self.window_level));
+                        self.history.push(entry);
+                    }
+                }
+            }
+        }
+        self.bookmark_manager.release_saves();
+
+        if has_changes {
+            // * Bookmarks changed under the current results and selection
+            self.searched_query.clear();
+            self.smart_topic_hits = None;
+            self.duplicate_groups = None;
+            self.url_cleanups = None;
+            self.prune_selection();
+        }
+        if has_messages {
+            #[cfg(target_os = "linux")]
+            if has_bookmark_mutation {
+                if let Some(dbus_service) = &self.dbus_service {
+                    dbus_service.notify_bookmarks_changed();
                 }
             }
 
             ctx.request_repaint();
         }
 
+        // * Shown under every page, so it must come before their central panels
+        self.show_jobs_panel(ctx, &mut clicked_on_button);
+
         match self.app_page {
             AppPage::Main => {
                 // * Top panel
@@ -331,6 +2920,37 @@ impl eframe::App for StashApp {
                                     ui.close_menu();
                                     clicked_on_button = true;
                                 }
+                                if ui.button("Open next unread").clicked() {
+                                    self.tx
+                                        .send(AppMessage::OpenNextUnread(None))
+                                        .expect("Unable to send");
+                                    ui.close_menu();
+                                    clicked_on_button = true;
+                                }
+                                if ui.button("Find duplicates").clicked() {
+                                    self.app_page = AppPage::Duplicates;
+                                    ui.close_menu();
+                                    clicked_on_button = true;
+                                }
+                                if ui.button("Expand short links").clicked() {
+                                    self.tx
+                                        .send(AppMessage::ResolveLinks(
+                                            self.unresolved_short_links(),
+                                        ))
+                                        .expect("Unable to send");
+                                    ui.close_menu();
+                                    clicked_on_button = true;
+                                }
+                                if ui.button("Link report").clicked() {
+                                    self.app_page = AppPage::LinkReport;
+                                    ui.close_menu();
+                                    clicked_on_button = true;
+                                }
+                                if ui.button("Trash").clicked() {
+                                    self.app_page = AppPage::Trash;
+                                    ui.close_menu();
+                                    clicked_on_button = true;
+                                }
                                 if ui.button("Settings").clicked() {
                                     self.app_page = AppPage::Settings;
                                     ui.close_menu();
@@ -351,23 +2971,63 @@ impl eframe::App for StashApp {
                                 ));
                             }
 
-                            let available_width = ui.available_width();
                             let label = "Add Topic";
+                            let unread_label = format!("{} Unread", regular::BOOKMARK_SIMPLE);
+                            let search_width = ui.available_width()
+                                - calc_btn_size_from_text(label)
+                                - calc_btn_size_from_text(&unread_label)
+                                - 15.;
+
+                            ui.add(
+                                TextEdit::singleline(&mut self.search_query)
+                                    .hint_text(format!("{} Search", regular::MAGNIFYING_GLASS))
+                                    .desired_width(search_width),
+                            );
+
+                            let unread_only = self.settings.show_unread_only;
+                            if ui
+                                .selectable_label(unread_only, unread_label)
+                                .on_hover_text("Show unread links only")
+                                .clicked()
+                            {
+                                self.tx
+                                    .send(AppMessage::ToggleUnreadOnly)
+                                    .expect("Unable to send");
+                                clicked_on_button = true;
+                            }
 
+                            let available_width = ui.available_width();
                             ui.add_space(available_width - calc_btn_size_from_text(label));
 
                             custom_button(ui, label, None, || {
                                 self.open_add_topic_viewport();
                             });
                         });
+
+                        if !self.selected_links.is_empty() {
+                            ui.add_space(5.);
+                            self.show_selection_bar(ui, &remove_modal, &mut clicked_on_button);
+                        }
                     });
 
+                self.update_search_results();
+                if self.searched_query.is_empty() {
+                    self.handle_tree_keys(ctx, &modal, &remove_modal);
+                }
+
                 // * Main UI
                 CentralPanel::default().show(ctx, |ui| {
+                    if !self.search_results.is_empty() || !self.searched_query.is_empty() {
+                        self.show_search_results(ui, &modal, &mut clicked_on_button);
+                        return;
+                    }
+
                     ScrollArea::vertical()
                         .auto_shrink([false; 2])
                         .scroll_bar_visibility(ScrollBarVisibility::VisibleWhenNeeded)
                         .show(ui, |ui| {
+                            self.show_smart_topics(ui, &modal, &mut clicked_on_button);
+
                             let topics_list = self.bookmark_manager.get_topics();
                             for (idx, topic) in topics_list.into_iter().enumerate() {
                                 let id_str =
@@ -375,6 +3035,7 @@ impl eframe::App for StashApp {
                                 let id = ui.make_persistent_id(id_str.clone());
                                 let is_expanded =
                                     self.expanded_topics.get(idx).copied().unwrap_or(false);
+                                let topic_idx = idx;
 
                                 ui.push_id(id_str.clone(), |ui| {
                                     let mut state =
@@ -390,6 +3051,8 @@ impl eframe::App for StashApp {
                                     let header_res = ui.horizontal(|ui| {
                                         ui.allocate_ui(vec2(50., 50.), |ui| {
                                             ui.horizontal_centered(|ui| {
+                                                drag_handle(ui, TreeFocus::Topic(idx));
+
                                                 let resp = ui.label(
                                                     RichText::new(if !is_expanded {
                                                         regular::CARET_RIGHT
@@ -409,6 +3072,10 @@ impl eframe::App for StashApp {
                                         Frame::group(ui.style())
                                             .inner_margin(Margin::same(9.))
                                             .rounding(Rounding::same(9.))
+                                            .stroke(focus_stroke(
+                                                ui,
+                                                self.tree_focus == Some(TreeFocus::Topic(idx)),
+                                            ))
                                             .show(ui, |ui| {
                                                 let topic_label = ui.label(
                                                     RichText::new(topic.name.clone()).size(20.),
@@ -419,6 +3086,22 @@ impl eframe::App for StashApp {
                                                     });
                                                 }
 
+                                                let unread = self
+                                                    .bookmark_manager
+                                                    .get_links_for_topic(&BookmarkItem::Topic(
+                                                        topic.clone(),
+                                                    ))
+                                                    .iter()
+                                                    .filter(|link| !link.is_read())
+                                                    .count();
+                                                if unread > 0 {
+                                                    ui.label(
+                                                        RichText::new(format!("{} unread", unread))
+                                                            .small()
+                                                            .weak(),
+                                                    );
+                                                }
+
                                                 let available_width = ui.available_width();
                                                 let label = "Open All";
 
@@ -442,6 +3125,11 @@ impl eframe::App for StashApp {
                                     });
 
                                     let header_response = header_res.response.clone();
+                                    self.handle_tree_drop(
+                                        ui,
+                                        TreeFocus::Topic(idx),
+                                        &header_response,
+                                    );
 
                                     header_response.context_menu(|ui| {
                                         if ui.button("Add Link").clicked() {
@@ -449,6 +3137,45 @@ impl eframe::App for StashApp {
                                             ui.close_menu();
                                             clicked_on_button = true;
                                         }
+                                        if ui.button("Open next unread").clicked() {
+                                            self.tx
+                                                .send(AppMessage::OpenNextUnread(Some(
+                                                    topic.name.clone(),
+                                                )))
+                                                .expect("Unable to send");
+                                            ui.close_menu();
+                                            clicked_on_button = true;
+                                        }
+                                        if ui.button("Archive all links").clicked() {
+                                            let links = self
+                                                .bookmark_manager
+                                                .get_links_for_topic(&BookmarkItem::Topic(
+                                                    topic.clone(),
+                                                ))
+                                                .into_iter()
+                                                .map(|link| (topic.name.clone(), link))
+                                                .collect();
+                                            self.tx
+                                                .send(AppMessage::ArchiveLinks(links))
+                                                .expect("Unable to send");
+                                            ui.close_menu();
+                                            clicked_on_button = true;
+                                        }
+                                        if ui.button("Fetch titles and previews").clicked() {
+                                            let links = self
+                                                .bookmark_manager
+                                                .get_links_for_topic(&BookmarkItem::Topic(
+                                                    topic.clone(),
+                                                ))
+                                                .into_iter()
+                                                .map(|link| (topic.name.clone(), link))
+                                                .collect();
+                                            self.tx
+                                                .send(AppMessage::FetchMetadata(links))
+                                                .expect("Unable to send");
+                                            ui.close_menu();
+                                            clicked_on_button = true;
+                                        }
                                         if ui.button("Edit topic name").clicked() {
                                             self.open_edit_topic_viewport(topic.name.clone());
                                             ui.close_menu();
@@ -469,10 +3196,17 @@ impl eframe::App for StashApp {
                                         && ui.input(|i| i.pointer.primary_clicked())
                                         && !clicked_on_button
                                     {
+                                        self.tree_focus = Some(TreeFocus::Topic(idx));
                                         self.tx
-                                            .send(AppMessage::ToggleCollapsed(idx))
+                                            .send(AppMessage::SetExpanded(idx, !is_expanded))
                                             .expect("Unable to send");
                                     }
+                                    if self.tree_focus == Some(TreeFocus::Topic(idx))
+                                        && self.scroll_to_focus
+                                    {
+                                        header_response.scroll_to_me(None);
+                                        self.scroll_to_focus = false;
+                                    }
 
                                     state.show_body_unindented(ui, |ui| {
                                         ui.add_space(5.);
@@ -481,16 +3215,32 @@ impl eframe::App for StashApp {
                                         let links = self.bookmark_manager.get_links_for_topic(
                                             &BookmarkItem::Topic(topic.clone()),
                                         );
+                                        let shown = links
+                                            .iter()
+                                            .enumerate()
+                                            .filter(|(_, link)| self.is_link_shown(link))
+                                            .collect::<Vec<(usize, &LinkModel)>>();
                                         if links.is_empty() {
                                             ui.vertical_centered(|ui| {
                                                 ui.label("No links!");
                                             });
+                                        } else if shown.is_empty() {
+                                            ui.vertical_centered(|ui| {
+                                                ui.label("No unread links!");
+                                            });
                                         } else {
-                                            for (idx, link) in links.iter().enumerate() {
-                                                Frame::group(ui.style())
+                                            for (idx, link) in shown {
+                                                let is_focused = self.tree_focus
+                                                    == Some(TreeFocus::Link(topic_idx, idx));
+                                                let is_selected = self
+                                                    .selected_links
+                                                    .contains(&(topic.name.clone(), link.clone()));
+                                                let link_frame = Frame::group(ui.style())
                                                     .shadow(Shadow::default())
                                                     .rounding(Rounding::same(9.))
                                                     .inner_margin(Margin::same(9.))
+                                                    .stroke(focus_stroke(ui, is_focused))
+                                                    .fill(selection_fill(ui, is_selected))
                                                     .show(ui, |ui| {
                                                         let id_str = format!(
                                                             "{}_{}_{}",
@@ -500,7 +3250,61 @@ impl eframe::App for StashApp {
                                                         );
                                                         ui.push_id(id_str, |ui| {
                                                             let link_ui = ui.horizontal(|ui| {
-                                                                ui.label(link.title.clone());
+                                                                drag_handle(
+                                                                    ui,
+                                                                    TreeFocus::Link(topic_idx, idx),
+                                                                );
+                                                                site_icon(
+                                                                    ui,
+                                                                    &self
+                                                                        .favicons
+                                                                        .icon(ctx, &link.url),
+                                                                );
+                                                                let title =
+                                                                    RichText::new(&link.title);
+                                                                let title_label =
+                                                                    ui.label(if link.is_read() {
+                                                                        title.weak()
+                                                                    } else {
+                                                                        title
+                                                                    });
+                                                                title_label.on_hover_text(
+                                                                    match &link.resolved_url {
+                                                                        Some(resolved) => format!(
+                                                                            "{}\n{} {}",
+                                                                            link.url,
+                                                                            regular::ARROW_RIGHT,
+                                                                            resolved
+                                                                        ),
+                                                                        None => link.url.clone(),
+                                                                    },
+                                                                );
+                                                                if let Some(resolved) =
+                                                                    &link.resolved_url
+                                                                {
+                                                                    if ui
+                                                                        .small_button(
+                                                                            regular::ARROWS_OUT_SIMPLE,
+                                                                        )
+                                                                        .on_hover_text(format!(
+                                                                            "Replace with {}",
+                                                                            resolved
+                                                                        ))
+                                                                        .clicked()
+                                                                    {
+                                                                        self.tx
+                                                                            .send(
+                                                                                AppMessage::EditLinks(
+                                                                                    self.expanded_link_edits(&[(
+                                                                                        topic.name.clone(),
+                                                                                        link.url.clone(),
+                                                                                    )]),
+                                                                                ),
+                                                                            )
+                                                                            .expect("Unable to send");
+                                                                        clicked_on_button = true;
+                                                                    }
+                                                                }
 
                                                                 let available_width =
                                                                     ui.available_width();
@@ -529,12 +3333,121 @@ impl eframe::App for StashApp {
                                                                 {
                                                                     self.open_edit_link_viewport(
                                                                         topic.name.clone(),
-                                                                        link.title.clone(),
-                                                                        link.url.clone(),
+                                                                        link.clone(),
+                                                                    );
+                                                                    ui.close_menu();
+                                                                    clicked_on_button = true;
+                                                                }
+                                                                if ui.button("Read in Stash").clicked()
+                                                                {
+                                                                    self.open_reader_viewport(
+                                                                        ctx,
+                                                                        topic.name.clone(),
+                                                                        link.clone(),
                                                                     );
                                                                     ui.close_menu();
                                                                     clicked_on_button = true;
                                                                 }
+                                                                let label = if link.is_read() {
+                                                                    "Mark as unread"
+                                                                } else {
+                                                                    "Mark as read"
+                                                                };
+                                                                if ui.button(label).clicked() {
+                                                                    self.tx
+                                                                        .send(AppMessage::MarkRead(
+                                                                            self.links_for_action(
+                                                                                &topic.name,
+                                                                                link,
+                                                                            )
+                                                                            .into_iter()
+                                                                            .map(|(name, l)| {
+                                                                                (name, l.url)
+                                                                            })
+                                                                            .collect(),
+                                                                            !link.is_read(),
+                                                                        ))
+                                                                        .expect("Unable to send");
+                                                                    ui.close_menu();
+                                                                    clicked_on_button = true;
+                                                                }
+                                                                if link.resolved_url.is_some()
+                                                                    && ui
+                                                                        .button("Replace with expanded link")
+                                                                        .clicked()
+                                                                {
+                                                                    let links = self
+                                                                        .links_for_action(
+                                                                            &topic.name,
+                                                                            link,
+                                                                        )
+                                                                        .into_iter()
+                                                                        .map(|(name, l)| (name, l.url))
+                                                                        .collect::<Vec<(String, String)>>();
+                                                                    self.tx
+                                                                        .send(AppMessage::EditLinks(
+                                                                            self.expanded_link_edits(&links),
+                                                                        ))
+                                                                        .expect("Unable to send");
+                                                                    ui.close_menu();
+                                                                    clicked_on_button = true;
+                                                                }
+                                                                if ui.button("Resolve redirects").clicked() {
+                                                                    self.tx
+                                                                        .send(AppMessage::ResolveLinks(
+                                                                            self.links_for_action(
+                                                                                &topic.name,
+                                                                                link,
+                                                                            ),
+                                                                        ))
+                                                                        .expect("Unable to send");
+                                                                    ui.close_menu();
+                                                                    clicked_on_button = true;
+                                                                }
+                                                                let label =
+                                                                    match link.archived_at {
+                                                                        Some(_) => "Archive again",
+                                                                        None => "Archive page",
+                                                                    };
+                                                                if ui.button(label).clicked() {
+                                                                    self.tx
+                                                                        .send(
+                                                                            AppMessage::ArchiveLinks(
+                                                                                self.links_for_action(
+                                                                                    &topic.name,
+                                                                                    link,
+                                                                                ),
+                                                                            ),
+                                                                        )
+                                                                        .expect("Unable to send");
+                                                                    ui.close_menu();
+                                                                    clicked_on_button = true;
+                                                                }
+                                                                if let Some(path) = link
+                                                                    .archived_at
+                                                                    .and_then(|_| {
+                                                                        archived_page(&link.url)
+                                                                    })
+                                                                {
+                                                                    if ui
+                                                                        .button(
+                                                                            "Open archived copy",
+                                                                        )
+                                                                        .clicked()
+                                                                    {
+                                                                        open_file(&path);
+                                                                        ui.close_menu();
+                                                                        clicked_on_button = true;
+                                                                    }
+                                                                }
+                                                                self.show_transfer_menus(
+                                                                    ui,
+                                                                    &self.links_for_action(
+                                                                        &topic.name,
+                                                                        link,
+                                                                    ),
+                                                                    &mut clicked_on_button,
+                                                                );
                                                                 if ui
                                                                     .button("Remove link")
                                                                     .clicked()
@@ -551,9 +3464,43 @@ impl eframe::App for StashApp {
                                                                     clicked_on_button = true;
                                                                 }
                                                             });
+
+                                                            if let (true, Some(preview)) = (
+                                                                self.settings.show_link_previews,
+                                                                link.preview.as_deref(),
+                                                            ) {
+                                                                ui.add_space(5.);
+                                                                link_preview_card(ui, preview);
+                                                            }
                                                         });
                                                     });
 
+                                                let hov_pos = ui.input(|i| {
+                                                    i.pointer.hover_pos().unwrap_or(Pos2::ZERO)
+                                                });
+                                                if link_frame.response.rect.contains(hov_pos)
+                                                    && ui.input(|i| i.pointer.primary_clicked())
+                                                {
+                                                    self.tree_focus =
+                                                        Some(TreeFocus::Link(topic_idx, idx));
+                                                }
+                                                self.handle_selection_click(
+                                                    ui,
+                                                    link_frame.response.rect,
+                                                    &topic.name,
+                                                    link,
+                                                    Some(TreeFocus::Link(topic_idx, idx)),
+                                                );
+                                                self.handle_tree_drop(
+                                                    ui,
+                                                    TreeFocus::Link(topic_idx, idx),
+                                                    &link_frame.response,
+                                                );
+                                                if is_focused && self.scroll_to_focus {
+                                                    link_frame.response.scroll_to_me(None);
+                                                    self.scroll_to_focus = false;
+                                                }
+
                                                 if idx < links.len() - 1 {
                                                     ui.add_space(5.);
                                                 }
@@ -629,6 +3576,129 @@ impl eframe::App for StashApp {
 
                             ui.add_space(5.);
 
+                            ui.horizontal(|ui| {
+                                ui.label("Keep deleted items for");
+
+                                let available_width = ui.available_width();
+                                ui.add_space(available_width - 90.);
+
+                                let days = ui.add(
+                                    DragValue::new(&mut self.settings.trash_retention_days)
+                                        .clamp_range(1..=365)
+                                        .suffix(" days"),
+                                );
+                                if days.changed() {
+                                    self.settings.save(self.is_debug);
+                                }
+                            });
+
+                            ui.add_space(5.);
+
+                            ui.horizontal(|ui| {
+                                ui.label("Background tasks at once");
+
+                                let available_width = ui.available_width();
+                                ui.add_space(available_width - 90.);
+
+                                let tasks = ui.add(
+                                    DragValue::new(&mut self.settings.max_background_tasks)
+                                        .clamp_range(1..=32),
+                                );
+                                if tasks.changed() {
+                                    self.jobs.set_max_tasks(self.settings.max_background_tasks);
+                                    self.settings.save(self.is_debug);
+                                }
+                            });
+
+                            ui.add_space(5.);
+
+                            ui.horizontal(|ui| {
+                                ui.label("Show link previews");
+
+                                let available_width = ui.available_width();
+                                ui.add_space(available_width - 20.);
+
+                                if ui
+                                    .checkbox(&mut self.settings.show_link_previews, "")
+                                    .changed()
+                                {
+                                    self.settings.save(self.is_debug);
+                                }
+                            });
+
+                            ui.add_space(5.);
+
+                            ui.horizontal(|ui| {
+                                ui.label("Mark links as read when opened");
+
+                                let available_width = ui.available_width();
+                                ui.add_space(available_width - 20.);
+
+                                if ui
+                                    .checkbox(&mut self.settings.mark_read_on_open, "")
+                                    .changed()
+                                {
+                                    self.settings.save(self.is_debug);
+                                }
+                            });
+
+                            ui.add_space(5.);
+
+                            ui.horizontal(|ui| {
+                                ui.label("Site icons");
+
+                                let available_width = ui.available_width();
+                                let label = "Refresh";
+
+                                ui.add_space(available_width - calc_btn_size_from_text(label));
+
+                                custom_button(ui, label, None, || {
+                                    self.tx
+                                        .send(AppMessage::RefreshIcons)
+                                        .expect("Unable to send");
+                                    clicked_on_button = true;
+                                });
+                            });
+
+                            ui.add_space(5.);
+
+                            ui.collapsing(RichText::new("Tracking parameters"), |ui| {
+                                ui.label(
+                                    RichText::new(
+                                        "Removed from links when they are saved. One per line, \
+                                         `*` matches a prefix, `domain: a, b` only applies to a site.",
+                                    )
+                                    .small(),
+                                );
+                                let rules = ui.add(
+                                    TextEdit::multiline(&mut self.url_rules_text)
+                                        .code_editor()
+                                        .desired_rows(6)
+                                        .desired_width(f32::INFINITY),
+                                );
+                                if rules.changed() {
+                                    self.settings.url_rules = UrlRules::from_text(&self.url_rules_text);
+                                    self.settings.save(self.is_debug);
+                                    self.url_cleanups = None;
+                                }
+
+                                ui.horizontal(|ui| {
+                                    custom_button(ui, "Reset", None, || {
+                                        self.settings.url_rules = UrlRules::default();
+                                        self.url_rules_text = self.settings.url_rules.to_text();
+                                        self.settings.save(self.is_debug);
+                                        self.url_cleanups = None;
+                                        clicked_on_button = true;
+                                    });
+                                    custom_button(ui, "Clean existing links", None, || {
+                                        self.app_page = AppPage::CleanLinks;
+                                        clicked_on_button = true;
+                                    });
+                                });
+                            });
+
+                            ui.add_space(5.);
+
                             ui.horizontal(|ui| {
                                 ui.label("Export bookmarks");
 
@@ -638,8 +3708,10 @@ impl eframe::App for StashApp {
                                 ui.add_space(available_width - calc_btn_size_from_text(label));
 
                                 custom_button(ui, label, None, || {
-                                    // TODO: Export bookmarks
-                                    println!("Exporting bookmarks");
+                                    self.tx
+                                        .send(AppMessage::ExportBookmarks)
+                                        .expect("Unable to send");
+                                    clicked_on_button = true;
                                 });
                             });
 
@@ -648,21 +3720,62 @@ impl eframe::App for StashApp {
                             ui.horizontal(|ui| {
                                 ui.label("Import bookmarks");
 
-                                let available_width = ui.available_width();
                                 let label = "Import";
-
-                                ui.add_space(available_width - calc_btn_size_from_text(label));
+                                let width = ui.available_width() - calc_btn_size_from_text(label);
+                                ui.add(
+                                    TextEdit::singleline(&mut self.import_path)
+                                        .hint_text("Path to an exported file")
+                                        .desired_width(width - 9.),
+                                );
 
                                 custom_button(ui, label, None, || {
-                                    // TODO: Import bookmarks
-                                    println!("Importing bookmarks");
+                                    let path = self.import_path.trim();
+                                    if !path.is_empty() {
+                                        self.tx
+                                            .send(AppMessage::ImportBookmarks(PathBuf::from(path)))
+                                            .expect("Unable to send");
+                                        self.import_path.clear();
+                                    }
+                                    clicked_on_button = true;
                                 });
                             });
                         });
                 });
             }
+            AppPage::Trash => {
+                self.show_trash_page(ctx, &mut clicked_on_button);
+            }
+            AppPage::Duplicates => {
+                self.show_duplicates_page(ctx, &mut clicked_on_button);
+            }
+            AppPage::CleanLinks => {
+                self.show_clean_links_page(ctx, &mut clicked_on_button);
+            }
+            AppPage::LinkReport => {
+                self.show_link_report_page(ctx, &mut clicked_on_button);
+            }
+        }
+
+        // * Command palette
+        self.command_palette.ui(ctx, &self.tx);
+
+        // * Toast
+        if let Some(toast) = &self.toast {
+            if !toast.ui(ctx, &self.tx) {
+                self.toast = None;
+            }
         }
 
+        // * Settings, window geometry is only saved on close
+        if ctx.input(|i| i.viewport().close_requested()) {
+            let (inner_rect, outer_rect) =
+                ctx.input(|i| (i.viewport().inner_rect, i.viewport().outer_rect));
+            self.settings.window_size = inner_rect.map(|r| r.size().into());
+            self.settings.window_pos = outer_rect.map(|r| r.min.into());
+            self.settings.save(self.is_debug);
+        }
+        self.sync_settings();
+
         // * About viewport
         if self.is_about_open.load(Ordering::Relaxed) {
             let is_about_open = self.is_about_open.clone();
@@ -735,7 +3848,7 @@ impl eframe::App for StashApp {
             let add_link_pos2 = self.initial_viewport_center;
             let min_size = *MIN_SIZE;
             let width = min_size[0];
-            let height = min_size[1] + 50.;
+            let height = min_size[1] + 200.;
             let min_size = [width, height];
 
             // * Show add link viewport
@@ -764,5 +3877,51 @@ impl eframe::App for StashApp {
                 },
             );
         }
+
+        // * Reader viewport
+        if self.is_reader_open.load(Ordering::Relaxed) {
+            let is_reader_open = self.is_reader_open.clone();
+            let tx = self.tx.clone();
+
+            let reader_pos2 = self.initial_viewport_center;
+            let min_size = *MIN_SIZE;
+            let size = [min_size[0] + 400., min_size[1] + 480.];
+
+            // * Show reader viewport
+            ctx.show_viewport_deferred(
+                ViewportId::from_hash_of("reader_viewport"),
+                ViewportBuilder::default()
+                    .with_title("Reader")
+                    .with_position(reader_pos2)
+                    .with_inner_size(size)
+                    .with_min_inner_size(min_size),
+                move |ctx, class| {
+                    assert!(
+                        class == ViewportClass::Deferred,
+                        "This egui backend doesn't support multiple viewports"
+                    );
+
+                    // * Reader UI
+                    READER_VIEWPORT
+                        .lock()
+                        .expect("Unable to lock ReaderViewport")
+                        .ui(ctx, &is_reader_open, &tx);
+                },
+            );
+        }
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.jobs.cancel_all();
+        flush_writes();
+    }
+}
+
+/// The web links of `links` as `(topic name, url)`, the only ones jobs can fetch.
+fn web_links(links: Vec<(String, LinkModel)>) -> Vec<(String, String)> {
+    links
+        .into_iter()
+        .filter(|(_, link)| link.url.starts_with("http://") || link.url.starts_with("https://"))
+        .map(|(name, link)| (name, link.url))
+        .collect()
 }
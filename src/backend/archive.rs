@@ -0,0 +1,337 @@
+use std::{fs, io::Read, path::PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use url::Url;
+
+use super::{
+    cache::fnv1a,
+    page_meta::{attribute, attributes, find_ignore_case, FETCH_TIMEOUT},
+    readability::extract_blocks,
+};
+use crate::utils::stash_dir;
+
+const MAX_PAGE_BYTES: u64 = 10 * 1024 * 1024;
+/// Stylesheets, images and fonts bigger than this are left linked instead of inlined.
+const MAX_RESOURCE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Result of archiving one link, to be recorded on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveResult {
+    pub topic: String,
+    pub url: String,
+    pub error: Option<String>,
+}
+
+/// Stable id of the link at `url`, naming its archive directory.
+pub fn link_id(url: &str) -> String {
+    format!("{:016x}", fnv1a(url.as_bytes()))
+}
+
+/// `archive/<link-id>` in the stash directory.
+pub fn archive_dir(url: &str) -> PathBuf {
+    stash_dir().join("archive").join(link_id(url))
+}
+
+/// The archived copy of `url`, if it was archived.
+pub fn archived_page(url: &str) -> Option<PathBuf> {
+    let path = archive_dir(url).join("index.html");
+    path.exists().then_some(path)
+}
+
+/// Saves the page at `url` as one self-contained HTML file, with its stylesheets and images
+/// inlined and its scripts removed, and its readable text next to it. Replaces an earlier
+/// copy. Blocking.
+pub fn archive_page(url: &str) -> Result<PathBuf, String> {
+    let agent = ureq::AgentBuilder::new().timeout(FETCH_TIMEOUT).build();
+    let (html, base) = fetch_page(&agent, url)?;
+
+    let dir = archive_dir(url);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    save_text(url, &extract_blocks(&html))?;
+
+    let html = inline_resources(&agent, &html, &base);
+    let path = dir.join("index.html");
+    let partial = path.with_extension("part");
+    fs::write(&partial, html).map_err(|e| e.to_string())?;
+    fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+
+    Ok(path)
+}
+
+/// Readable text of the archived copy of `url`, see [`extract_blocks`]. Extracted from the
+/// snapshot for pages archived before their text was kept.
+pub fn page_text(url: &str) -> Option<Vec<String>> {
+    let path = archive_dir(url).join("text.json");
+    if let Ok(data) = fs::read_to_string(&path) {
+        return serde_json::from_str(&data).ok();
+    }
+
+    let html = fs::read_to_string(archived_page(url)?).ok()?;
+    let blocks = extract_blocks(&html);
+    if let Err(e) = save_text(url, &blocks) {
+        eprintln!("Failed to save text of {}: {}", url, e);
+    }
+
+    Some(blocks)
+}
+
+fn save_text(url: &str, blocks: &[String]) -> Result<(), String> {
+    let data = serde_json::to_string(blocks).expect("Failed to serialize page text");
+    fs::write(archive_dir(url).join("text.json"), data).map_err(|e| e.to_string())
+}
+
+/// HTML of the page at `url` and the URL its relative links resolve against, which is where
+/// redirects ended up. Blocking.
+pub fn fetch_page(agent: &ureq::Agent, url: &str) -> Result<(String, Url), String> {
+    let (_, body, final_url) = fetch(agent, url, MAX_PAGE_BYTES)?;
+    let base = Url::parse(&final_url).map_err(|e| e.to_string())?;
+
+    Ok((String::from_utf8_lossy(&body).into_owned(), base))
+}
+
+/// Content type, body and final URL of `url`, refused when larger than `limit`.
+fn fetch(agent: &ureq::Agent, url: &str, limit: u64) -> Result<(String, Vec<u8>, String), String> {
+    let response = agent
+        .get(url)
+        .set("User-Agent", concat!("stash/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| e.to_string())?;
+    let content_type = response.content_type().to_owned();
+    let final_url = response.get_url().to_owned();
+
+    let mut bytes = vec![];
+    response
+        .into_reader()
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > limit {
+        return Err(format!("{} is larger than {} bytes", url, limit));
+    }
+
+    Ok((content_type, bytes, final_url))
+}
+
+/// `data:` URI of the resource at `href`, `None` when it could not be fetched.
+fn data_uri(agent: &ureq::Agent, href: &str, base: &Url) -> Option<String> {
+    let url = base.join(href).ok()?;
+    let (content_type, bytes, _) = fetch(agent, url.as_str(), MAX_RESOURCE_BYTES)
+        .map_err(|e| eprintln!("Failed to inline {}: {}", url, e))
+        .ok()?;
+    let mime = match image::guess_format(&bytes) {
+        Ok(format) => format.to_mime_type().to_owned(),
+        Err(_) => content_type,
+    };
+
+    Some(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+}
+
+/// Rewrites the page so it renders without the network.
+fn inline_resources(agent: &ureq::Agent, html: &str, base: &Url) -> String {
+    let mut snapshot = String::with_capacity(html.len());
+
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        snapshot.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let name = rest[1..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+        let tag = &rest[..end];
+        let attrs = attributes(tag[1 + name.len()..].trim_end_matches('>'));
+        let attr = |key: &str| attribute(&attrs, key);
+
+        match name.as_str() {
+            "script" => {
+                // * Scripts would fetch more or rewrite the page, the snapshot is static
+                let close = find_ignore_case(rest, "</script")
+                    .and_then(|close| rest[close..].find('>').map(|end| close + end + 1))
+                    .unwrap_or(rest.len());
+                rest = &rest[close..];
+                continue;
+            }
+            "style" => {
+                let close = find_ignore_case(rest, "</style").unwrap_or(rest.len());
+                snapshot.push_str(tag);
+                snapshot.push_str(&inline_css(agent, &rest[end.min(close)..close], base));
+                rest = &rest[close..];
+                continue;
+            }
+            "head" => {
+                snapshot.push_str(tag);
+                snapshot.push_str(&format!(
+                    "<meta charset=\"utf-8\"><base href=\"{}\">",
+                    base.as_str().replace('"', "%22")
+                ));
+            }
+            // * The page is saved as UTF-8 whatever it declared
+            "meta"
+                if attr("charset").is_some()
+                    || attr("http-equiv")
+                        .is_some_and(|h| h.eq_ignore_ascii_case("content-type")) => {}
+            "link"
+                if attr("rel").is_some_and(|rel| {
+                    rel.split_whitespace()
+                        .any(|r| r.eq_ignore_ascii_case("stylesheet"))
+                }) =>
+            {
+                let css = attr("href")
+                    .and_then(|href| base.join(&href).ok())
+                    .and_then(|url| {
+                        fetch(agent, url.as_str(), MAX_RESOURCE_BYTES)
+                            .map_err(|e| eprintln!("Failed to inline {}: {}", url, e))
+                            .ok()
+                            .map(|(_, bytes, _)| (url, bytes))
+                    });
+                match css {
+                    Some((url, bytes)) => snapshot.push_str(&format!(
+                        "<style>{}</style>",
+                        inline_css(agent, &String::from_utf8_lossy(&bytes), &url)
+                    )),
+                    None => snapshot.push_str(tag),
+                }
+            }
+            "img" => {
+                // * Lazy-loaded images keep the real source aside
+                let src = attr("data-src").or_else(|| attr("src"));
+                let src = src.map(|src| {
+                    data_uri(agent, &src, base)
+                        .unwrap_or_else(|| base.join(&src).map_or(src, |url| url.to_string()))
+                });
+                let attrs = attrs
+                    .iter()
+                    .filter(|(key, _)| {
+                        !["src", "srcset", "data-src", "data-srcset", "loading"]
+                            .iter()
+                            .any(|k| key.eq_ignore_ascii_case(k))
+                    })
+                    .cloned()
+                    .chain(src.map(|src| ("src".to_owned(), src)))
+                    .collect::<Vec<(String, String)>>();
+                snapshot.push_str(&write_tag(&name, &attrs));
+            }
+            // * `<picture>` sources would win over the inlined `<img>`
+            "source" if attr("srcset").is_some() => {}
+            _ => snapshot.push_str(tag),
+        }
+
+        rest = &rest[end..];
+    }
+    snapshot.push_str(rest);
+
+    snapshot
+}
+
+/// Inlines the `url(...)` references of a stylesheet, which are relative to `base`.
+fn inline_css(agent: &ureq::Agent, css: &str, base: &Url) -> String {
+    let mut inlined = String::with_capacity(css.len());
+
+    let mut rest = css;
+    while let Some(start) = find_ignore_case(rest, "url(") {
+        inlined.push_str(&rest[..start + 4]);
+        rest = &rest[start + 4..];
+
+        let end = rest.find(')').unwrap_or(rest.len());
+        let href = rest[..end].trim().trim_matches(['"', '\'']);
+        if href.is_empty() || href.starts_with("data:") || href.starts_with('#') {
+            inlined.push_str(&rest[..end]);
+        } else {
+            // * Left pointing at the site when it cannot be fetched
+            let uri = data_uri(agent, href, base).unwrap_or_else(|| {
+                base.join(href)
+                    .map_or(href.to_owned(), |url| url.to_string())
+            });
+            inlined.push_str(&format!("\"{}\"", uri));
+        }
+        rest = &rest[end..];
+    }
+    inlined.push_str(rest);
+
+    inlined
+}
+
+fn write_tag(name: &str, attributes: &[(String, String)]) -> String {
+    let mut tag = format!("<{}", name);
+    for (key, value) in attributes {
+        if value.is_empty() {
+            tag.push_str(&format!(" {}", key));
+        } else {
+            tag.push_str(&format!(" {}=\"{}\"", key, value.replace('"', "&quot;")));
+        }
+    }
+    tag.push('>');
+
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_server::{Response, TestServer};
+
+    const PIXEL: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    fn server() -> TestServer {
+        TestServer::start(|_, path| match path {
+            "/css/site.css" => Response::ok("text/css", "body { background: url(../bg.png) }"),
+            "/bg.png" | "/img/a.png" => Response::ok("image/png", PIXEL),
+            "/big" => Response::ok("text/plain", vec![b'x'; 64]),
+            _ => Response::status(404),
+        })
+    }
+
+    #[test]
+    fn inlines_resources() {
+        let server = server();
+        let agent = ureq::AgentBuilder::new().timeout(FETCH_TIMEOUT).build();
+        let base = Url::parse(&server.url("/post/")).unwrap();
+        let pixel = format!("data:image/png;base64,{}", STANDARD.encode(PIXEL));
+
+        let snapshot = inline_resources(
+            &agent,
+            r#"<html><head><meta charset="iso-8859-1"><link rel="stylesheet" href="/css/site.css"></head>
+<body><script>alert("<b>")</script><img data-src="../img/a.png" src="spinner.gif" loading="lazy" alt="A">
+<picture><source srcset="/img/a.webp"><img src="missing.png"></picture>
+<style>p { background: url('/bg.png') } a { background: url(data:x) }</style></body></html>"#,
+            &base,
+        );
+
+        assert!(snapshot.contains(&format!(
+            "<head><meta charset=\"utf-8\"><base href=\"{}\"><style>body {{ background: url(\"{}\") }}</style></head>",
+            base, pixel
+        )));
+        assert!(!snapshot.contains("iso-8859-1"));
+        assert!(!snapshot.contains("alert"));
+        assert!(snapshot.contains(&format!("<img alt=\"A\" src=\"{}\">", pixel)));
+        assert!(!snapshot.contains("<source"));
+        // * Left pointing at the site when it cannot be fetched
+        assert!(snapshot.contains(&format!(
+            "<img src=\"{}\">",
+            server.url("/post/missing.png")
+        )));
+        assert!(snapshot.contains(&format!(
+            "p {{ background: url(\"{}\") }} a {{ background: url(data:x) }}",
+            pixel
+        )));
+    }
+
+    #[test]
+    fn refuses_large_resources() {
+        let server = server();
+        let agent = ureq::AgentBuilder::new().timeout(FETCH_TIMEOUT).build();
+
+        assert!(fetch(&agent, &server.url("/big"), 64).is_ok());
+        assert!(fetch(&agent, &server.url("/big"), 63).is_err());
+    }
+
+    #[test]
+    fn names_links_stably() {
+        assert_eq!(link_id("https://a.test/"), link_id("https://a.test/"));
+        assert_ne!(link_id("https://a.test/"), link_id("https://a.test"));
+        assert_eq!(link_id("").len(), 16);
+    }
+}
@@ -1,35 +1,48 @@
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
 
-use directories::UserDirs;
+use chrono::{Duration, Utc};
 use indexmap::IndexMap;
+use rayon::prelude::*;
+use serde::de::IgnoredAny;
 
-use super::models::{LinkModel, TopicModel};
-use crate::utils::enums::BookmarkItem;
+use super::{
+    file_writer,
+    link_checker::LinkCheck,
+    models::{LinkHealth, LinkModel, SmartTopicModel, TopicModel, TrashEntry, TrashedItem},
+    page_meta::PageMeta,
+    query::{PageMatches, Query},
+    search::SearchHit,
+    text_index::TextIndex,
+};
+use crate::utils::{enums::BookmarkItem, normalize_url, stash_dir};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct BookmarkManager {
     path: String,
     pub filename: String,
     bookmarks: IndexMap<BookmarkItem, Vec<BookmarkItem>>,
+    smart_topics: Vec<SmartTopicModel>,
+    trash: Vec<TrashEntry>,
+    text_index: TextIndex,
+    /// Files are written off the calling thread, see [`Self::set_background_saves`]
+    background_saves: bool,
+    /// Saves are put off until [`Self::release_saves`], see [`Self::hold_saves`]
+    is_holding_saves: bool,
+    has_unsaved_bookmarks: bool,
+    has_unsaved_text_index: bool,
 }
 
 impl BookmarkManager {
     pub fn new(is_debug: bool) -> Self {
-        let dirs = UserDirs::new().expect("Failed to get user directories");
-        let documents = dirs
-            .document_dir()
-            .expect("Failed to get document directory");
-        let path = documents.join("stash");
-        if !path.exists() {
-            fs::create_dir_all(&path).expect("Failed to create stash directory");
-        }
-
-        let filename = if is_debug {
-            "bookmarks_debug.json"
-        } else {
-            "bookmarks.json"
-        };
+        Self::load(&stash_dir(), bookmarks_filename(is_debug))
+    }
 
+    /// Reads the bookmarks from `filename` and the files next to it in `path`.
+    pub fn load(path: &Path, filename: &str) -> Self {
         let mut bookmarks = IndexMap::new();
         if let Ok(data) = fs::read_to_string(format!("{}/{}", path.to_str().unwrap(), filename)) {
             let json: HashMap<String, Vec<LinkModel>> =
@@ -62,14 +75,35 @@ impl BookmarkManager {
             }
         }
 
-        Self {
+        let smart_topics =
+            fs::read_to_string(path.join(filename.replace("bookmarks", "smart_topics")))
+                .map(|data| {
+                    serde_json::from_str(&data).expect("Failed to deserialize smart topics")
+                })
+                .unwrap_or_default();
+
+        let trash = fs::read_to_string(path.join(filename.replace("bookmarks", "trash")))
+            .map(|data| serde_json::from_str(&data).expect("Failed to deserialize trash"))
+            .unwrap_or_default();
+
+        let mut manager = Self {
             path: path
                 .to_str()
                 .expect("Failed to convert path to string")
                 .to_string(),
             filename: filename.to_string(),
             bookmarks,
-        }
+            smart_topics,
+            trash,
+            text_index: TextIndex::load(path.join(filename.replace("bookmarks", "text_index"))),
+            background_saves: false,
+            is_holding_saves: false,
+            has_unsaved_bookmarks: false,
+            has_unsaved_text_index: false,
+        };
+        manager.update_text_index();
+
+        manager
     }
 
     pub fn add_topic(&mut self, topic: BookmarkItem) {
@@ -88,12 +122,33 @@ impl BookmarkManager {
 
         let bookmarks = self.bookmarks.clone();
         let (idx, _, value) = bookmarks.get_full(&old_topic).expect("Failed to get topic");
-        self.remove_topic(old_topic);
+        self.bookmarks.shift_remove(&old_topic);
         self.bookmarks.shift_insert(idx, new_topic, value.to_vec());
+
+        self.save_bookmarks(None);
     }
 
+    /// Moves the topic and its links to the trash.
     pub fn remove_topic(&mut self, topic: BookmarkItem) {
-        self.bookmarks.shift_remove(&topic);
+        if let Some((index, BookmarkItem::Topic(topic), links)) =
+            self.bookmarks.shift_remove_full(&topic)
+        {
+            let links = links
+                .into_iter()
+                .filter_map(|item| match item {
+                    BookmarkItem::Link(link) => Some(link),
+                    _ => None,
+                })
+                .collect();
+            self.trash.push(TrashEntry::new(TrashedItem::Topic {
+                topic,
+                index,
+                links,
+            }));
+            self.save_trash();
+        }
+
+        self.update_text_index();
         self.save_bookmarks(None);
     }
 
@@ -106,6 +161,15 @@ impl BookmarkManager {
         self.save_bookmarks(None);
     }
 
+    /// Puts a topic back at `index` with its links, or at the end if the list got shorter.
+    pub fn insert_topic(&mut self, index: usize, topic: BookmarkItem, links: Vec<BookmarkItem>) {
+        let index = index.min(self.bookmarks.len());
+        self.bookmarks.shift_insert(index, topic, links);
+
+        self.update_text_index();
+        self.save_bookmarks(None);
+    }
+
     pub fn get_topics(&self) -> Vec<TopicModel> {
         self.bookmarks
             .keys()
@@ -116,14 +180,35 @@ impl BookmarkManager {
             .collect()
     }
 
-    pub fn add_link(&mut self, topic: BookmarkItem, link: BookmarkItem) {
-        if !self.bookmarks.contains_key(&topic) {
-            self.bookmarks.insert(topic.clone(), vec![]);
-        } else {
-            self.bookmarks.get_mut(&topic).unwrap().push(link);
-        }
+    pub fn add_link(&mut self, topic: BookmarkItem, link: BookmarkItem) -> Vec<LinkModel> {
+        self.add_links(topic, vec![link])
+    }
 
+    /// Appends `links` to the topic, returning the ones skipped as its URL was already there.
+    pub fn add_links(&mut self, topic: BookmarkItem, links: Vec<BookmarkItem>) -> Vec<LinkModel> {
+        let skipped = self.extend_links(topic, links);
+
+        self.update_text_index();
         self.save_bookmarks(None);
+
+        skipped
+    }
+
+    fn extend_links(&mut self, topic: BookmarkItem, links: Vec<BookmarkItem>) -> Vec<LinkModel> {
+        let mut skipped = vec![];
+        let topic_links = self.bookmarks.entry(topic).or_default();
+        for item in links {
+            let BookmarkItem::Link(link) = &item else {
+                continue;
+            };
+            if has_url(topic_links, &link.url) {
+                skipped.push(link.clone());
+            } else {
+                topic_links.push(item);
+            }
+        }
+
+        skipped
     }
 
     pub fn edit_link(
@@ -134,22 +219,221 @@ impl BookmarkManager {
     ) {
         if self.bookmarks.contains_key(&topic) {
             let links = self.bookmarks.get_mut(&topic).unwrap();
-            if let Some(index) = links.iter().position(|l| l == &old_link) {
+            if let Some(index) = link_position(links, &old_link) {
                 links[index] = new_link;
             }
         }
 
+        self.update_text_index();
         self.save_bookmarks(None);
     }
 
+    /// Moves the link to the trash.
     pub fn remove_link(&mut self, topic: BookmarkItem, link: BookmarkItem) {
         if self.bookmarks.contains_key(&topic) {
             let links = self.bookmarks.get_mut(&topic).unwrap();
-            if let Some(index) = links.iter().position(|l| l == &link) {
-                links.remove(index);
+            if let Some(index) = link_position(links, &link) {
+                // * Trashed as it is now, background updates may have changed it since
+                let link = links.remove(index);
+
+                if let (BookmarkItem::Topic(topic), BookmarkItem::Link(link)) = (topic, link) {
+                    self.trash.push(TrashEntry::new(TrashedItem::Link {
+                        topic: topic.name,
+                        index,
+                        link,
+                    }));
+                    self.save_trash();
+                }
+            }
+        }
+
+        self.update_text_index();
+        self.save_bookmarks(None);
+    }
+
+    /// Puts links back at their indices, which must be ascending within a topic. Leaves the
+    /// trash as it is, the history takes out what a removal put there.
+    pub fn insert_links(&mut self, links: Vec<(BookmarkItem, usize, BookmarkItem)>) {
+        for (topic, index, link) in links {
+            let topic_links = self.bookmarks.entry(topic).or_default();
+            let index = index.min(topic_links.len());
+            topic_links.insert(index, link);
+        }
+
+        self.update_text_index();
+        self.save_bookmarks(None);
+    }
+
+    pub fn move_link(
+        &mut self,
+        from: BookmarkItem,
+        link: BookmarkItem,
+        to: BookmarkItem,
+    ) -> Vec<LinkModel> {
+        self.move_links(from, vec![link], to)
+    }
+
+    /// Moves `links` to the end of `to` as they are, keeping tags and history. Returns the
+    /// ones left in `from` as their URL was already in `to`.
+    pub fn move_links(
+        &mut self,
+        from: BookmarkItem,
+        links: Vec<BookmarkItem>,
+        to: BookmarkItem,
+    ) -> Vec<LinkModel> {
+        if from == to || !self.bookmarks.contains_key(&from) {
+            return vec![];
+        }
+
+        let mut skipped = vec![];
+        let mut moving = vec![];
+        let from_links = &self.bookmarks[&from];
+        let to_links = self
+            .bookmarks
+            .get(&to)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for item in links {
+            let BookmarkItem::Link(link) = &item else {
+                continue;
+            };
+            if link_position(from_links, &item).is_none() {
+                continue;
+            }
+            if has_url(to_links, &link.url) || has_url(&moving, &link.url) {
+                skipped.push(link.clone());
+            } else {
+                moving.push(item);
+            }
+        }
+
+        let from_links = self.bookmarks.get_mut(&from).unwrap();
+        let moved = moving
+            .into_iter()
+            .filter_map(|link| {
+                link_position(from_links, &link).map(|index| from_links.remove(index))
+            })
+            .collect::<Vec<BookmarkItem>>();
+        self.bookmarks.entry(to).or_default().extend(moved);
+
+        self.save_bookmarks(None);
+
+        skipped
+    }
+
+    /// Moves `link` to `to` at `index`, as dropping it on a row of another topic does. Returns
+    /// the link if it stayed, as its URL was already in `to`.
+    pub fn move_link_to(
+        &mut self,
+        from: BookmarkItem,
+        link: BookmarkItem,
+        to: BookmarkItem,
+        index: usize,
+    ) -> Vec<LinkModel> {
+        if from == to {
+            return vec![];
+        }
+        let BookmarkItem::Link(model) = &link else {
+            return vec![];
+        };
+        if self
+            .bookmarks
+            .get(&to)
+            .is_some_and(|to_links| has_url(to_links, &model.url))
+        {
+            return vec![model.clone()];
+        }
+        let Some(from_links) = self.bookmarks.get_mut(&from) else {
+            return vec![];
+        };
+        let Some(position) = link_position(from_links, &link) else {
+            return vec![];
+        };
+
+        let link = from_links.remove(position);
+        let to_links = self.bookmarks.entry(to).or_default();
+        to_links.insert(index.min(to_links.len()), link);
+
+        self.save_bookmarks(None);
+
+        vec![]
+    }
+
+    pub fn copy_link(&mut self, link: BookmarkItem, to: BookmarkItem) -> Vec<LinkModel> {
+        self.copy_links(vec![link], to)
+    }
+
+    /// Appends copies of `links` to `to`, returning the ones skipped as their URL was already
+    /// there.
+    pub fn copy_links(&mut self, links: Vec<BookmarkItem>, to: BookmarkItem) -> Vec<LinkModel> {
+        let skipped = self.extend_links(to, links);
+        self.save_bookmarks(None);
+
+        skipped
+    }
+
+    /// Every link as `(topic name, link)`, in tree order.
+    pub fn get_all_links(&self) -> Vec<(String, LinkModel)> {
+        self.bookmarks
+            .iter()
+            .flat_map(|(topic, links)| {
+                let name = match topic {
+                    BookmarkItem::Topic(topic) => topic.name.clone(),
+                    _ => String::new(),
+                };
+                links.iter().filter_map(move |item| match item {
+                    BookmarkItem::Link(link) => Some((name.clone(), link.clone())),
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    /// Links saved more than once across the stash, grouped by normalized URL in tree order.
+    pub fn find_duplicates(&self) -> Vec<Vec<(String, LinkModel)>> {
+        let mut groups: IndexMap<String, Vec<(String, LinkModel)>> = IndexMap::new();
+        for (name, link) in self.get_all_links() {
+            groups
+                .entry(normalize_url(&link.url))
+                .or_default()
+                .push((name, link));
+        }
+
+        groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .collect()
+    }
+
+    /// Replaces the first link of each group with the merge of the group and drops the rest.
+    /// The dropped links are not trashed, nothing of them is lost.
+    pub fn merge_duplicates(&mut self, groups: &[Vec<(String, LinkModel)>]) {
+        for group in groups {
+            let merged = LinkModel::merge(
+                &group
+                    .iter()
+                    .map(|(_, link)| link.clone())
+                    .collect::<Vec<LinkModel>>(),
+            );
+
+            for (idx, (name, link)) in group.iter().enumerate() {
+                let topic = BookmarkItem::Topic(TopicModel::new(name.clone()));
+                let Some(links) = self.bookmarks.get_mut(&topic) else {
+                    continue;
+                };
+                let Some(position) = link_position(links, &BookmarkItem::Link(link.clone())) else {
+                    continue;
+                };
+
+                if idx == 0 {
+                    links[position] = BookmarkItem::Link(merged.clone());
+                } else {
+                    links.remove(position);
+                }
             }
         }
 
+        self.update_text_index();
         self.save_bookmarks(None);
     }
 
@@ -163,6 +447,17 @@ impl BookmarkManager {
         self.save_bookmarks(None);
     }
 
+    /// The current version of the link at `url` in topic `name`.
+    pub fn find_link(&self, name: &str, url: &str) -> Option<LinkModel> {
+        self.bookmarks
+            .get(&BookmarkItem::Topic(TopicModel::new(name.to_owned())))?
+            .iter()
+            .find_map(|item| match item {
+                BookmarkItem::Link(link) if link.url == url => Some(link.clone()),
+                _ => None,
+            })
+    }
+
     pub fn get_links_for_topic(&self, topic: &BookmarkItem) -> Vec<LinkModel> {
         self.bookmarks
             .get(topic)
@@ -178,7 +473,481 @@ impl BookmarkManager {
             .unwrap_or_default()
     }
 
-    pub fn save_bookmarks(&self, path: Option<PathBuf>) {
+    /// Records that the links at `urls` were opened, and read too when `mark_read` is set.
+    pub fn mark_opened(&mut self, urls: &[String], mark_read: bool) {
+        let now = Utc::now();
+        for links in self.bookmarks.values_mut() {
+            for item in links.iter_mut() {
+                if let BookmarkItem::Link(link) = item {
+                    if urls.contains(&link.url) {
+                        link.last_opened = Some(now);
+                        if mark_read && link.read_at.is_none() {
+                            link.read_at = Some(now);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.save_bookmarks(None);
+    }
+
+    /// Marks the links given as `(topic name, url)` as read or unread. Links keep when they were
+    /// first read.
+    pub fn mark_read(&mut self, links: &[(String, String)], read: bool) {
+        let now = Utc::now();
+        for (topic, url) in links {
+            let Some(items) = self
+                .bookmarks
+                .get_mut(&BookmarkItem::Topic(TopicModel::new(topic.clone())))
+            else {
+                continue;
+            };
+
+            for item in items.iter_mut() {
+                if let BookmarkItem::Link(link) = item {
+                    if &link.url == url {
+                        link.read_at = if read {
+                            link.read_at.or(Some(now))
+                        } else {
+                            None
+                        };
+                    }
+                }
+            }
+        }
+
+        self.save_bookmarks(None);
+    }
+
+    /// The first unread link of `topic`, or of the first topic having one when `None`.
+    pub fn next_unread(&self, topic: Option<&str>) -> Option<(String, LinkModel)> {
+        self.get_topics()
+            .into_iter()
+            .filter(|t| topic.is_none() || topic == Some(t.name.as_str()))
+            .find_map(|t| {
+                let link = self
+                    .get_links_for_topic(&BookmarkItem::Topic(t.clone()))
+                    .into_iter()
+                    .find(|link| !link.is_read())?;
+                Some((t.name, link))
+            })
+    }
+
+    /// Records what the link checker found on the links it checked, by topic and URL.
+    pub fn record_checks(&mut self, checks: &[LinkCheck]) {
+        let now = Utc::now();
+        for check in checks {
+            let Some(links) = self
+                .bookmarks
+                .get_mut(&BookmarkItem::Topic(TopicModel::new(check.topic.clone())))
+            else {
+                continue;
+            };
+
+            for item in links.iter_mut() {
+                if let BookmarkItem::Link(link) = item {
+                    if link.url != check.url {
+                        continue;
+                    }
+
+                    let mut health = LinkHealth {
+                        status: check.status,
+                        redirect: check.redirect.clone(),
+                        error: check.error.clone(),
+                        checked_at: now,
+                        failures: 0,
+                    };
+                    if health.is_broken() {
+                        health.failures = link.health.as_ref().map_or(0, |h| h.failures) + 1;
+                    }
+                    link.health = Some(Box::new(health));
+                    if check.error.is_none() {
+                        link.resolved_url = check.redirect.clone();
+                    }
+                }
+            }
+        }
+
+        self.save_bookmarks(None);
+    }
+
+    /// Records where the links resolved by the link checker end up, without touching their
+    /// health.
+    pub fn record_resolved(&mut self, checks: &[LinkCheck]) {
+        for check in checks.iter().filter(|check| check.error.is_none()) {
+            let Some(links) = self
+                .bookmarks
+                .get_mut(&BookmarkItem::Topic(TopicModel::new(check.topic.clone())))
+            else {
+                continue;
+            };
+
+            for item in links.iter_mut() {
+                if let BookmarkItem::Link(link) = item {
+                    if link.url == check.url {
+                        link.resolved_url = check.redirect.clone();
+                    }
+                }
+            }
+        }
+
+        self.save_bookmarks(None);
+    }
+
+    /// Fills in the title and preview fetched for the link at `url` in `topic`, unless the
+    /// link already has them.
+    pub fn record_meta(&mut self, topic: &str, url: &str, meta: &PageMeta) {
+        let Some(links) = self
+            .bookmarks
+            .get_mut(&BookmarkItem::Topic(TopicModel::new(topic.to_owned())))
+        else {
+            return;
+        };
+
+        for item in links.iter_mut() {
+            if let BookmarkItem::Link(link) = item {
+                if link.url != url {
+                    continue;
+                }
+                if let Some(title) = meta.title.as_ref().filter(|_| link.has_placeholder_title()) {
+                    link.title = title.clone();
+                }
+                if link.preview.is_none() {
+                    link.preview = meta.preview().map(Box::new);
+                }
+            }
+        }
+
+        self.save_bookmarks(None);
+    }
+
+    /// Records that the pages at `urls`, given as `(topic name, url)`, were just archived.
+    pub fn mark_archived(&mut self, urls: &[(String, String)]) {
+        let now = Utc::now();
+        for (topic, url) in urls {
+            let Some(links) = self
+                .bookmarks
+                .get_mut(&BookmarkItem::Topic(TopicModel::new(topic.clone())))
+            else {
+                continue;
+            };
+
+            for item in links.iter_mut() {
+                if let BookmarkItem::Link(link) = item {
+                    if &link.url == url {
+                        link.archived_at = Some(now);
+                    }
+                }
+            }
+        }
+
+        // * Archiving again replaces the text
+        for (_, url) in urls {
+            self.text_index.add(url);
+        }
+        self.save_text_index();
+
+        self.save_bookmarks(None);
+    }
+
+    /// Indexes the archived pages of new links and forgets the pages no link points to anymore.
+    fn update_text_index(&mut self) {
+        let urls = self
+            .bookmarks
+            .values()
+            .flatten()
+            .filter_map(|item| match item {
+                BookmarkItem::Link(link) if link.archived_at.is_some() => Some(link.url.clone()),
+                _ => None,
+            })
+            .collect::<HashSet<String>>();
+
+        let mut changed = false;
+        for url in self.text_index.urls() {
+            if !urls.contains(&url) {
+                self.text_index.remove(&url);
+                changed = true;
+            }
+        }
+        for url in &urls {
+            if !self.text_index.contains(url) {
+                changed |= self.text_index.add(url);
+            }
+        }
+
+        if changed {
+            self.save_text_index();
+        }
+    }
+
+    /// Every link matching `query`, ranked by its free text terms, best matches first.
+    pub fn search(&self, query: &Query) -> Vec<SearchHit> {
+        let now = Utc::now();
+        let terms = query.text_terms();
+        let terms = &terms;
+        let pages = query
+            .all_text_terms()
+            .into_iter()
+            .map(|term| {
+                let urls = self.text_index.search(&term);
+                (term, urls)
+            })
+            .collect::<PageMatches>();
+        let pages = &pages;
+
+        let mut hits = self
+            .bookmarks
+            .par_iter()
+            .flat_map_iter(|(topic, links)| {
+                let topic = match topic {
+                    BookmarkItem::Topic(topic) => Some(topic),
+                    _ => None,
+                };
+
+                links.iter().filter_map(move |item| match (topic, item) {
+                    (Some(topic), BookmarkItem::Link(link))
+                        if query.matches(topic, link, now, pages) =>
+                    {
+                        Some(SearchHit::new(terms, topic, link, pages))
+                    }
+                    _ => None,
+                })
+            })
+            .collect::<Vec<SearchHit>>();
+        hits.par_sort_by(|a, b| b.score.cmp(&a.score));
+
+        // * Only pages that matched have a passage worth showing
+        hits.par_iter_mut()
+            .filter(|hit| {
+                terms.iter().any(|term| {
+                    pages
+                        .get(term)
+                        .is_some_and(|urls| urls.contains_key(&hit.link.url))
+                })
+            })
+            .for_each(|hit| hit.snippet = self.text_index.snippet(&hit.link.url, terms));
+
+        hits
+    }
+
+    pub fn get_smart_topics(&self) -> Vec<SmartTopicModel> {
+        self.smart_topics.clone()
+    }
+
+    pub fn add_smart_topic(&mut self, smart_topic: SmartTopicModel) {
+        if self.smart_topics.iter().any(|t| t.name == smart_topic.name) {
+            return;
+        }
+
+        self.smart_topics.push(smart_topic);
+        self.save_smart_topics();
+    }
+
+    /// Puts a smart topic back at `index`, or at the end if the list got shorter.
+    pub fn insert_smart_topic(&mut self, index: usize, smart_topic: SmartTopicModel) {
+        if self.smart_topics.iter().any(|t| t.name == smart_topic.name) {
+            return;
+        }
+
+        let index = index.min(self.smart_topics.len());
+        self.smart_topics.insert(index, smart_topic);
+        self.save_smart_topics();
+    }
+
+    pub fn remove_smart_topic(&mut self, smart_topic: &SmartTopicModel) {
+        self.smart_topics.retain(|t| t != smart_topic);
+        self.save_smart_topics();
+    }
+
+    /// Trashed items, most recently deleted last.
+    pub fn get_trash(&self) -> Vec<TrashEntry> {
+        self.trash.clone()
+    }
+
+    /// Puts a trashed item back where it was. Returns the index of the topic if one had
+    /// to be created for it.
+    pub fn restore_from_trash(&mut self, entry: &TrashEntry) -> Option<usize> {
+        let position = self.trash.iter().position(|e| e == entry)?;
+        let entry = self.trash.remove(position);
+        self.save_trash();
+
+        let created_at = match entry.item {
+            TrashedItem::Topic {
+                topic,
+                index,
+                links,
+            } => {
+                let topic = BookmarkItem::Topic(topic);
+                let links = links.into_iter().map(BookmarkItem::Link);
+                match self.bookmarks.get_mut(&topic) {
+                    // * A topic with the same name was created since, merge into it
+                    Some(existing) => {
+                        existing.extend(links);
+                        None
+                    }
+                    None => {
+                        let index = index.min(self.bookmarks.len());
+                        self.bookmarks.shift_insert(index, topic, links.collect());
+                        Some(index)
+                    }
+                }
+            }
+            TrashedItem::Link { topic, index, link } => {
+                let topic = BookmarkItem::Topic(TopicModel::new(topic));
+                let created_at = (!self.bookmarks.contains_key(&topic)).then(|| {
+                    self.bookmarks.insert(topic.clone(), vec![]);
+                    self.bookmarks.len() - 1
+                });
+
+                let links = self.bookmarks.get_mut(&topic).unwrap();
+                let index = index.min(links.len());
+                links.insert(index, BookmarkItem::Link(link));
+                created_at
+            }
+        };
+
+        self.update_text_index();
+        self.save_bookmarks(None);
+        created_at
+    }
+
+    pub fn delete_from_trash(&mut self, entry: &TrashEntry) {
+        self.trash.retain(|e| e != entry);
+        self.save_trash();
+    }
+
+    pub fn empty_trash(&mut self) {
+        self.trash.clear();
+        self.save_trash();
+    }
+
+    /// Permanently deletes items that have been in the trash for more than `retention_days`.
+    pub fn purge_trash(&mut self, retention_days: u32) {
+        let cutoff = Utc::now() - Duration::days(retention_days.into());
+        let len = self.trash.len();
+        self.trash.retain(|e| e.deleted_at >= cutoff);
+
+        if self.trash.len() != len {
+            self.save_trash();
+        }
+    }
+
+    /// Takes back a restore from the trash: the items `entry` restored are removed again and
+    /// it goes back to the trash. `created_topic` tells whether restoring created the topic.
+    /// Returns the index of the topic removed with them, if any.
+    pub fn return_to_trash(&mut self, entry: &TrashEntry, created_topic: bool) -> Option<usize> {
+        let (name, links) = match &entry.item {
+            TrashedItem::Topic { topic, links, .. } => (topic.name.clone(), links.clone()),
+            TrashedItem::Link { topic, link, .. } => (topic.clone(), vec![link.clone()]),
+        };
+        let topic = BookmarkItem::Topic(TopicModel::new(name));
+
+        let removed_at = if created_topic {
+            let index = self.bookmarks.get_index_of(&topic);
+            self.bookmarks.shift_remove(&topic);
+            index
+        } else {
+            if let Some(topic_links) = self.bookmarks.get_mut(&topic) {
+                topic_links.retain(|item| match item {
+                    BookmarkItem::Link(l) => !links.iter().any(|link| link.url == l.url),
+                    _ => true,
+                });
+            }
+            None
+        };
+
+        // * Back where it was among the others, which are in the order they were deleted
+        let position = self
+            .trash
+            .partition_point(|e| e.deleted_at <= entry.deleted_at);
+        self.trash.insert(position, entry.clone());
+        self.save_trash();
+        self.update_text_index();
+        self.save_bookmarks(None);
+
+        removed_at
+    }
+
+    /// Drops entries trashed after the first `len`, when a removal only reverts an addition.
+    pub fn truncate_trash(&mut self, len: usize) {
+        if self.trash.len() > len {
+            self.trash.truncate(len);
+            self.save_trash();
+        }
+    }
+
+    /// Saves files in the background from now on, so edits don't wait on the disk. Pending
+    /// saves must be flushed with [`file_writer::flush_writes`] before exiting.
+    pub fn set_background_saves(&mut self, background_saves: bool) {
+        self.background_saves = background_saves;
+    }
+
+    /// Puts off saving the bookmarks and text index until [`Self::release_saves`], so a batch
+    /// of changes is written once.
+    pub fn hold_saves(&mut self) {
+        self.is_holding_saves = true;
+    }
+
+    /// Saves what changed since [`Self::hold_saves`].
+    pub fn release_saves(&mut self) {
+        self.is_holding_saves = false;
+        if std::mem::take(&mut self.has_unsaved_bookmarks) {
+            self.save_bookmarks(None);
+        }
+        if std::mem::take(&mut self.has_unsaved_text_index) {
+            self.save_text_index();
+        }
+    }
+
+    fn save_text_index(&mut self) {
+        if self.is_holding_saves {
+            self.has_unsaved_text_index = true;
+        } else if self.background_saves {
+            file_writer::write_later(
+                self.text_index.path().to_path_buf(),
+                self.text_index.to_json(),
+            );
+        } else {
+            self.text_index.save();
+        }
+    }
+
+    fn write_file(&self, path: PathBuf, data: String) {
+        if self.background_saves {
+            file_writer::write_later(path, data);
+        } else {
+            fs::write(&path, data)
+                .unwrap_or_else(|e| panic!("Failed to write {}: {:?}", path.display(), e));
+        }
+    }
+
+    fn save_trash(&self) {
+        let filename = self.filename.replace("bookmarks", "trash");
+        let data = serde_json::to_string(&self.trash).expect("Failed to serialize trash");
+        self.write_file(PathBuf::from(format!("{}/{}", self.path, filename)), data);
+    }
+
+    fn save_smart_topics(&self) {
+        let filename = self.filename.replace("bookmarks", "smart_topics");
+        let data =
+            serde_json::to_string(&self.smart_topics).expect("Failed to serialize smart topics");
+        self.write_file(PathBuf::from(format!("{}/{}", self.path, filename)), data);
+    }
+
+    pub fn save_bookmarks(&mut self, path: Option<PathBuf>) {
+        if path.is_none() && self.is_holding_saves {
+            self.has_unsaved_bookmarks = true;
+            return;
+        }
+
+        let path =
+            path.unwrap_or_else(|| PathBuf::from(format!("{}/{}", self.path, self.filename)));
+        self.write_file(path, self.to_json());
+    }
+
+    /// The bookmarks in the `idx_name` layout of `bookmarks.json`.
+    pub fn to_json(&self) -> String {
         let mut data: HashMap<String, Vec<LinkModel>> = HashMap::new();
         let mut count = 0;
         for (topic, links) in &self.bookmarks {
@@ -199,39 +968,389 @@ impl BookmarkManager {
             count += 1;
         }
 
-        let data = serde_json::to_string(&data).expect("Failed to serialize bookmarks");
-        let path =
-            path.unwrap_or_else(|| PathBuf::from(format!("{}/{}", self.path, self.filename)));
-        fs::write(path, data).expect("Failed to write bookmarks to file");
+        serde_json::to_string(&data).expect("Failed to serialize bookmarks")
     }
 
-    pub fn export_bookmarks(&self, path: &str) {
-        self.save_bookmarks(Some(PathBuf::from(path)));
+    /// Adds the topics of an exported file, read with [`read_export`]. Returns the links
+    /// skipped as `(topic name, link)`.
+    pub fn import_topics(
+        &mut self,
+        topics: Vec<(String, Vec<LinkModel>)>,
+    ) -> Vec<(String, LinkModel)> {
+        let mut skipped = vec![];
+        for (name, links) in topics {
+            // * Merged into an existing topic, links it already has are skipped
+            let topic = BookmarkItem::Topic(TopicModel { name: name.clone() });
+            let links = links.into_iter().map(BookmarkItem::Link).collect();
+            skipped.extend(
+                self.extend_links(topic, links)
+                    .into_iter()
+                    .map(|link| (name.clone(), link)),
+            );
+        }
+
+        self.update_text_index();
+        self.save_bookmarks(None);
+
+        skipped
     }
+}
 
-    pub fn import_bookmarks(&mut self, path: &str) {
-        if let Ok(data) = fs::read_to_string(path) {
-            let json: HashMap<String, Vec<LinkModel>> =
-                serde_json::from_str(&data).expect("Failed to deserialize bookmarks");
-            let mut json = json.into_iter().collect::<Vec<(String, Vec<LinkModel>)>>();
-            json.sort_by(|a, b| a.0.cmp(&b.0));
+/// Index of `link` in `links`. Links are found by URL, which is unique within a topic, as
+/// checks, opens and other background updates change the rest of a link under the history.
+fn link_position(links: &[BookmarkItem], link: &BookmarkItem) -> Option<usize> {
+    let BookmarkItem::Link(link) = link else {
+        return links.iter().position(|l| l == link);
+    };
+    links
+        .iter()
+        .position(|l| matches!(l, BookmarkItem::Link(l) if l.url == link.url))
+}
 
-            for (topic, links) in json {
-                let mut split = topic.split('_').collect::<Vec<&str>>();
-                split.remove(0);
-                let name = if split.len() > 1 {
-                    split.join("_")
-                } else {
-                    split[0].to_string()
-                };
+/// Whether one of `links` has `url`, compared the way duplicates are.
+fn has_url(links: &[BookmarkItem], url: &str) -> bool {
+    links
+        .iter()
+        .any(|item| matches!(item, BookmarkItem::Link(link) if link.same_url(url)))
+}
 
-                self.bookmarks.insert(
-                    BookmarkItem::Topic(TopicModel { name }),
-                    links.into_iter().map(BookmarkItem::Link).collect(),
-                );
-            }
+fn bookmarks_filename(is_debug: bool) -> &'static str {
+    if is_debug {
+        "bookmarks_debug.json"
+    } else {
+        "bookmarks.json"
+    }
+}
 
-            self.save_bookmarks(None);
+/// Names of the topics in the bookmarks file in `dir`, in order, without reading their
+/// links. The shell completions run this on every TAB press.
+pub fn read_topic_names(dir: &Path, is_debug: bool) -> Vec<String> {
+    fs::read_to_string(dir.join(bookmarks_filename(is_debug)))
+        .map(|data| topic_names(&data))
+        .unwrap_or_default()
+}
+
+fn topic_names(data: &str) -> Vec<String> {
+    let Ok(json) = serde_json::from_str::<HashMap<String, IgnoredAny>>(data) else {
+        return vec![];
+    };
+
+    let mut names = json
+        .into_keys()
+        .filter_map(|topic| {
+            let (idx, name) = topic.split_once('_')?;
+            Some((idx.parse::<usize>().unwrap_or(usize::MAX), name.to_owned()))
+        })
+        .collect::<Vec<(usize, String)>>();
+    names.sort_by_key(|(idx, _)| *idx);
+
+    names.into_iter().map(|(_, name)| name).collect()
+}
+
+/// Topics of a file exported from Stash, in their order. Blocking.
+pub fn read_export(path: &Path) -> Result<Vec<(String, Vec<LinkModel>)>, String> {
+    let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let json: HashMap<String, Vec<LinkModel>> =
+        serde_json::from_str(&data).map_err(|e| e.to_string())?;
+
+    let mut topics = json
+        .into_iter()
+        .filter_map(|(topic, links)| {
+            let (idx, name) = topic.split_once('_')?;
+            Some((
+                idx.parse::<usize>().unwrap_or(usize::MAX),
+                name.to_owned(),
+                links,
+            ))
+        })
+        .collect::<Vec<(usize, String, Vec<LinkModel>)>>();
+    topics.sort_by_key(|(idx, _, _)| *idx);
+
+    Ok(topics
+        .into_iter()
+        .map(|(_, name, links)| (name, links))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A manager saving to a directory of its own under the temporary directory.
+    fn manager(name: &str) -> BookmarkManager {
+        let dir = std::env::temp_dir().join(format!("stash-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        BookmarkManager::load(&dir, "bookmarks.json")
+    }
+
+    fn topic(name: &str) -> BookmarkItem {
+        BookmarkItem::Topic(TopicModel::new(name.to_owned()))
+    }
+
+    fn link(url: &str) -> LinkModel {
+        LinkModel::new(url.to_owned(), url.to_owned(), None)
+    }
+
+    fn urls(links: &[LinkModel]) -> Vec<&str> {
+        links.iter().map(|link| link.url.as_str()).collect()
+    }
+
+    #[test]
+    fn returns_the_links_it_skips() {
+        let mut manager = manager("skips");
+        let skipped = manager.add_links(
+            topic("Rust"),
+            vec![
+                BookmarkItem::Link(link("https://a.test/")),
+                BookmarkItem::Link(link("https://www.a.test")),
+                BookmarkItem::Link(link("https://b.test/")),
+            ],
+        );
+        assert_eq!(urls(&skipped), vec!["https://www.a.test"]);
+        let skipped =
+            manager.add_link(topic("Rust"), BookmarkItem::Link(link("http://b.test#top")));
+        assert_eq!(urls(&skipped), vec!["http://b.test#top"]);
+
+        // * Copies are compared by URL too, not by the rest of the link
+        let mut read = link("https://a.test");
+        read.read_at = Some(Utc::now());
+        manager.add_link(topic("Later"), BookmarkItem::Link(read));
+        let skipped = manager.copy_links(
+            vec![
+                BookmarkItem::Link(link("https://a.test/")),
+                BookmarkItem::Link(link("https://c.test/")),
+            ],
+            topic("Later"),
+        );
+        assert_eq!(urls(&skipped), vec!["https://a.test/"]);
+        assert_eq!(
+            urls(&manager.get_links_for_topic(&topic("Later"))),
+            vec!["https://a.test", "https://c.test/"]
+        );
+
+        let skipped = manager.import_topics(vec![
+            (
+                "Rust".to_owned(),
+                vec![link("https://A.test"), link("https://d.test")],
+            ),
+            ("New".to_owned(), vec![link("https://a.test")]),
+        ]);
+        assert_eq!(skipped, vec![("Rust".to_owned(), link("https://A.test"))]);
+        assert_eq!(
+            urls(&manager.get_links_for_topic(&topic("Rust"))),
+            vec!["https://a.test/", "https://b.test/", "https://d.test"]
+        );
+    }
+
+    #[test]
+    fn moves_leave_links_the_target_has() {
+        let mut manager = manager("move");
+        manager.add_links(
+            topic("Inbox"),
+            vec![
+                BookmarkItem::Link(link("https://a.test/")),
+                BookmarkItem::Link(link("https://b.test/")),
+                BookmarkItem::Link(link("https://c.test/")),
+            ],
+        );
+        manager.add_link(
+            topic("Rust"),
+            BookmarkItem::Link(link("https://www.a.test")),
+        );
+
+        let skipped = manager.move_links(
+            topic("Inbox"),
+            vec![
+                BookmarkItem::Link(link("https://a.test/")),
+                BookmarkItem::Link(link("https://b.test/")),
+                BookmarkItem::Link(link("https://missing.test/")),
+            ],
+            topic("Rust"),
+        );
+        assert_eq!(urls(&skipped), vec!["https://a.test/"]);
+        assert_eq!(
+            urls(&manager.get_links_for_topic(&topic("Inbox"))),
+            vec!["https://a.test/", "https://c.test/"]
+        );
+        assert_eq!(
+            urls(&manager.get_links_for_topic(&topic("Rust"))),
+            vec!["https://www.a.test", "https://b.test/"]
+        );
+
+        let skipped = manager.move_link_to(
+            topic("Inbox"),
+            BookmarkItem::Link(link("https://a.test/")),
+            topic("Rust"),
+            0,
+        );
+        assert_eq!(urls(&skipped), vec!["https://a.test/"]);
+        let skipped = manager.move_link_to(
+            topic("Inbox"),
+            BookmarkItem::Link(link("https://c.test/")),
+            topic("Rust"),
+            0,
+        );
+        assert!(skipped.is_empty());
+        assert_eq!(
+            urls(&manager.get_links_for_topic(&topic("Inbox"))),
+            vec!["https://a.test/"]
+        );
+        assert_eq!(
+            urls(&manager.get_links_for_topic(&topic("Rust"))),
+            vec!["https://c.test/", "https://www.a.test", "https://b.test/"]
+        );
+    }
+
+    fn topic_names_of(manager: &BookmarkManager) -> Vec<String> {
+        manager.get_topics().into_iter().map(|t| t.name).collect()
+    }
+
+    #[test]
+    fn trashes_and_restores_where_it_was() {
+        let mut manager = manager("trash");
+        manager.add_topic(topic("News"));
+        manager.add_links(
+            topic("Rust"),
+            vec![
+                BookmarkItem::Link(link("https://a.test/")),
+                BookmarkItem::Link(link("https://b.test/")),
+            ],
+        );
+
+        manager.remove_link(topic("Rust"), BookmarkItem::Link(link("https://a.test/")));
+        manager.remove_topic(topic("News"));
+        let trash = manager.get_trash();
+        assert!(matches!(
+            &trash[0].item,
+            TrashedItem::Link { topic, index: 0, link } if topic == "Rust" && link.url == "https://a.test/"
+        ));
+        assert!(matches!(
+            &trash[1].item,
+            TrashedItem::Topic { topic, index: 0, links } if topic.name == "News" && links.is_empty()
+        ));
+
+        // * The trash is saved with the bookmarks
+        let reloaded = BookmarkManager::load(Path::new(&manager.path), "bookmarks.json");
+        assert_eq!(reloaded.get_trash(), trash);
+
+        assert_eq!(manager.restore_from_trash(&trash[1]), Some(0));
+        assert_eq!(manager.restore_from_trash(&trash[0]), None);
+        assert_eq!(topic_names_of(&manager), vec!["News", "Rust"]);
+        assert_eq!(
+            urls(&manager.get_links_for_topic(&topic("Rust"))),
+            vec!["https://a.test/", "https://b.test/"]
+        );
+        assert!(manager.get_trash().is_empty());
+        // * Only entries still in the trash are restored
+        assert_eq!(manager.restore_from_trash(&trash[0]), None);
+        assert_eq!(manager.get_links_for_topic(&topic("Rust")).len(), 2);
+    }
+
+    #[test]
+    fn restores_into_the_topic_it_finds() {
+        let mut manager = manager("restore-topic");
+        manager.add_link(topic("Rust"), BookmarkItem::Link(link("https://a.test/")));
+        manager.add_topic(topic("News"));
+
+        // * A link of a removed topic brings the topic back, at the end
+        manager.remove_link(topic("Rust"), BookmarkItem::Link(link("https://a.test/")));
+        manager.remove_topic(topic("Rust"));
+        let link_entry = manager.get_trash()[0].clone();
+        assert_eq!(manager.restore_from_trash(&link_entry), Some(1));
+        assert_eq!(topic_names_of(&manager), vec!["News", "Rust"]);
+
+        // * A topic trashed while another of its name was created is merged into that one
+        manager.add_link(topic("News"), BookmarkItem::Link(link("https://b.test/")));
+        manager.remove_topic(topic("News"));
+        manager.add_link(topic("News"), BookmarkItem::Link(link("https://c.test/")));
+        let topic_entry = manager.get_trash()[1].clone();
+        assert_eq!(manager.restore_from_trash(&topic_entry), None);
+        assert_eq!(topic_names_of(&manager), vec!["Rust", "News"]);
+        assert_eq!(
+            urls(&manager.get_links_for_topic(&topic("News"))),
+            vec!["https://c.test/", "https://b.test/"]
+        );
+    }
+
+    #[test]
+    fn returns_restored_items_to_the_trash() {
+        let mut manager = manager("return");
+        manager.add_links(
+            topic("Rust"),
+            vec![
+                BookmarkItem::Link(link("https://a.test/")),
+                BookmarkItem::Link(link("https://b.test/")),
+            ],
+        );
+        manager.add_topic(topic("News"));
+
+        manager.remove_link(topic("Rust"), BookmarkItem::Link(link("https://a.test/")));
+        let entry = manager.get_trash()[0].clone();
+        manager.restore_from_trash(&entry);
+        assert_eq!(manager.return_to_trash(&entry, false), None);
+        assert_eq!(
+            urls(&manager.get_links_for_topic(&topic("Rust"))),
+            vec!["https://b.test/"]
+        );
+        assert_eq!(manager.get_trash(), vec![entry]);
+
+        // * A topic the restore created goes away again
+        manager.remove_topic(topic("Rust"));
+        let entry = manager.get_trash()[1].clone();
+        assert_eq!(manager.restore_from_trash(&entry), Some(0));
+        assert_eq!(manager.return_to_trash(&entry, true), Some(0));
+        assert_eq!(topic_names_of(&manager), vec!["News"]);
+        assert_eq!(manager.get_trash()[1], entry);
+    }
+
+    #[test]
+    fn purges_and_truncates_the_trash() {
+        let mut manager = manager("purge");
+        for url in ["https://a.test/", "https://b.test/", "https://c.test/"] {
+            manager.add_link(topic("Rust"), BookmarkItem::Link(link(url)));
+            manager.remove_link(topic("Rust"), BookmarkItem::Link(link(url)));
         }
+        manager.trash[0].deleted_at = Utc::now() - Duration::days(31);
+        manager.trash[1].deleted_at = Utc::now() - Duration::days(29);
+        let trash = manager.get_trash();
+
+        manager.purge_trash(30);
+        assert_eq!(manager.get_trash(), trash[1..]);
+
+        manager.truncate_trash(5);
+        assert_eq!(manager.get_trash(), trash[1..]);
+        manager.truncate_trash(1);
+        assert_eq!(manager.get_trash(), trash[1..2]);
+
+        let reloaded = BookmarkManager::load(Path::new(&manager.path), "bookmarks.json");
+        assert_eq!(reloaded.get_trash(), trash[1..2]);
+    }
+
+    #[test]
+    fn reads_topic_names_in_order() {
+        let data =
+            r#"{"10_Later":[],"2_Two_Words":[{"title":"a","url":"https://a.test"}],"0_First":[]}"#;
+        assert_eq!(
+            topic_names(data),
+            vec![
+                "First".to_owned(),
+                "Two_Words".to_owned(),
+                "Later".to_owned()
+            ]
+        );
+        assert!(topic_names("not json").is_empty());
+
+        let dir = std::env::temp_dir().join(format!("stash-topics-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("bookmarks_debug.json"),
+            r#"{"1_Rust":[],"0_News":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(read_topic_names(&dir, true), vec!["News", "Rust"]);
+        assert!(read_topic_names(&dir, false).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,85 @@
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::utils::stash_dir;
+
+/// Images bigger than this are not worth a thumbnail.
+const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// `cache/<name>` in the stash directory, created if missing.
+pub fn cache_dir(name: &str) -> PathBuf {
+    let path = stash_dir().join("cache").join(name);
+    if !path.exists() {
+        fs::create_dir_all(&path).expect("Failed to create cache directory");
+    }
+
+    path
+}
+
+/// 64-bit FNV-1a, stable across runs and platforms unlike `DefaultHasher`.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// URI the egui image loaders read a cached file from.
+pub fn file_uri(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+/// Where the preview image at `url` is cached, the format is sniffed when it is loaded.
+pub fn preview_image_path(url: &str) -> PathBuf {
+    cache_dir("previews").join(format!("{:016x}", fnv1a(url.as_bytes())))
+}
+
+/// `file://` URI of the preview image at `url` once it has been downloaded.
+pub fn cached_preview_uri(url: &str) -> Option<String> {
+    let path = preview_image_path(url);
+    path.exists().then(|| file_uri(&path))
+}
+
+/// Downloads the preview image at `url` unless it is cached already. Blocking.
+pub fn cache_preview_image(url: &str) -> Result<PathBuf, String> {
+    let path = preview_image_path(url);
+    if path.exists() {
+        return Ok(path);
+    }
+
+    let bytes = download(url)?;
+
+    // * Written aside first, so a half-written file is never loaded
+    let partial = path.with_extension("part");
+    fs::write(&partial, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+
+    Ok(path)
+}
+
+/// Body of `url`, refused when larger than [`MAX_IMAGE_BYTES`].
+pub fn download(url: &str) -> Result<Vec<u8>, String> {
+    let response = ureq::AgentBuilder::new()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .get(url)
+        .set("User-Agent", concat!("stash/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| e.to_string())?;
+
+    let mut bytes = vec![];
+    response
+        .into_reader()
+        .take(MAX_IMAGE_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > MAX_IMAGE_BYTES {
+        return Err(format!("{} is larger than {} bytes", url, MAX_IMAGE_BYTES));
+    }
+
+    Ok(bytes)
+}
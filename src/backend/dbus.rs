@@ -0,0 +1,330 @@
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+use crossbeam::channel::Sender;
+use tokio::sync::oneshot;
+use zbus::{connection, fdo, interface, Connection, SignalContext};
+
+use super::{bookmark_manager::BookmarkManager, models::LinkModel, query::Query};
+use crate::utils::enums::AppMessage;
+
+pub const SERVICE_NAME: &str = "io.github.aymanfarsi.stash";
+pub const OBJECT_PATH: &str = "/io/github/aymanfarsi/stash";
+
+/// Longest wait for the app to answer a request, it only does between frames.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where the app sends the answer to a [`DbusRequest`].
+#[derive(Debug)]
+pub struct Reply<T>(Arc<Mutex<Option<oneshot::Sender<T>>>>);
+
+impl<T> Reply<T> {
+    fn new() -> (Self, oneshot::Receiver<T>) {
+        let (tx, rx) = oneshot::channel();
+        (Self(Arc::new(Mutex::new(Some(tx)))), rx)
+    }
+
+    /// Answers the request, only the first answer is kept.
+    pub fn send(&self, value: T) {
+        let tx = self.0.lock().expect("Failed to lock D-Bus reply").take();
+        if let Some(tx) = tx {
+            // * The caller may have given up waiting
+            let _ = tx.send(value);
+        }
+    }
+}
+
+impl<T> Clone for Reply<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for Reply<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Questions from the bus, answered from the bookmarks the app has in memory rather than the
+/// file, which may be behind.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DbusRequest {
+    ListTopics(Reply<Vec<String>>),
+    /// `(topic, title, url)` of the links matching the query
+    Search(String, Reply<Vec<(String, String, String)>>),
+}
+
+impl DbusRequest {
+    pub fn answer(&self, bookmark_manager: &BookmarkManager) {
+        match self {
+            DbusRequest::ListTopics(reply) => reply.send(
+                bookmark_manager
+                    .get_topics()
+                    .into_iter()
+                    .map(|topic| topic.name)
+                    .collect(),
+            ),
+            DbusRequest::Search(query, reply) => reply.send(
+                bookmark_manager
+                    .search(&Query::parse_lenient(query))
+                    .into_iter()
+                    .map(|hit| (hit.topic.name, hit.link.title, hit.link.url))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+struct StashInterface {
+    ctx: egui::Context,
+    tx: Sender<AppMessage>,
+}
+
+impl StashInterface {
+    fn send(&self, msg: AppMessage) -> fdo::Result<()> {
+        self.tx
+            .send(msg)
+            .map_err(|e| fdo::Error::Failed(format!("Unable to send: {}", e)))?;
+        self.ctx.request_repaint();
+
+        Ok(())
+    }
+
+    /// Sends a request to the app and waits for its answer.
+    async fn ask<T>(&self, request: impl FnOnce(Reply<T>) -> DbusRequest) -> fdo::Result<T> {
+        let (reply, rx) = Reply::new();
+        self.send(AppMessage::Dbus(request(reply)))?;
+
+        match tokio::time::timeout(REPLY_TIMEOUT, rx).await {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(_)) => Err(fdo::Error::Failed("Stash dropped the request".to_owned())),
+            Err(_) => Err(fdo::Error::Failed(
+                "Stash did not answer in time".to_owned(),
+            )),
+        }
+    }
+}
+
+#[interface(name = "io.github.aymanfarsi.stash")]
+impl StashInterface {
+    /// Adds a link to the given topic, creating the topic if it does not exist yet.
+    async fn add_link(&self, topic: String, title: String, url: String) -> fdo::Result<()> {
+        if topic.is_empty() || url.is_empty() {
+            return Err(fdo::Error::InvalidArgs(
+                "Topic and URL must not be empty".to_owned(),
+            ));
+        }
+
+        let title = if title.is_empty() { url.clone() } else { title };
+        self.send(AppMessage::AddLink(topic, LinkModel::new(title, url, None)))
+    }
+
+    async fn list_topics(&self) -> fdo::Result<Vec<String>> {
+        self.ask(DbusRequest::ListTopics).await
+    }
+
+    /// Returns `(topic, title, url)` for every link matching `query`, best matches first.
+    async fn search(&self, query: String) -> fdo::Result<Vec<(String, String, String)>> {
+        self.ask(|reply| DbusRequest::Search(query, reply)).await
+    }
+
+    async fn show(&self) -> fdo::Result<()> {
+        self.send(AppMessage::SetVisible(true))
+    }
+
+    async fn hide(&self) -> fdo::Result<()> {
+        self.send(AppMessage::SetVisible(false))
+    }
+
+    #[zbus(signal)]
+    async fn bookmarks_changed(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+}
+
+/// Exposes Stash on the session bus as `io.github.aymanfarsi.stash`.
+#[derive(Debug, Clone, Default)]
+pub struct DbusService {
+    connection: Arc<OnceLock<Connection>>,
+}
+
+impl DbusService {
+    pub fn start(ctx: egui::Context, tx: Sender<AppMessage>) -> Self {
+        let service = Self::default();
+        let connection = service.connection.clone();
+
+        tokio::spawn(async move {
+            let interface = StashInterface { ctx, tx };
+            let res = async {
+                connection::Builder::session()?
+                    .name(SERVICE_NAME)?
+                    .serve_at(OBJECT_PATH, interface)?
+                    .build()
+                    .await
+            }
+            .await;
+
+            match res {
+                Ok(conn) => {
+                    let _ = connection.set(conn);
+                }
+                Err(e) => {
+                    eprintln!("Failed to start D-Bus service: {:?}", e);
+                }
+            }
+        });
+
+        service
+    }
+
+    pub fn notify_bookmarks_changed(&self) {
+        let Some(conn) = self.connection.get().cloned() else {
+            return;
+        };
+
+        tokio::spawn(async move {
+            let iface = conn
+                .object_server()
+                .interface::<_, StashInterface>(OBJECT_PATH)
+                .await;
+            let res = match iface {
+                Ok(iface) => StashInterface::bookmarks_changed(iface.signal_context()).await,
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = res {
+                eprintln!("Failed to emit BookmarksChanged: {:?}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Command, Stdio},
+    };
+
+    use crossbeam::channel::unbounded;
+
+    use super::*;
+    use crate::{backend::models::TopicModel, utils::enums::BookmarkItem};
+
+    /// Starts a bus of its own, `None` where `dbus-daemon` isn't installed.
+    fn private_bus() -> Option<(std::process::Child, String)> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+
+        Some((daemon, address.trim().to_owned()))
+    }
+
+    #[test]
+    fn answers_from_memory() {
+        let Some((mut daemon, address)) = private_bus() else {
+            eprintln!("Skipped, dbus-daemon is not available");
+            return;
+        };
+
+        let dir = std::env::temp_dir().join(format!("stash-dbus-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut bookmark_manager = BookmarkManager::load(&dir, "bookmarks.json");
+        let topic = BookmarkItem::Topic(TopicModel::new("Rust".to_owned()));
+        bookmark_manager.add_topic(topic.clone());
+        bookmark_manager.add_link(
+            topic,
+            BookmarkItem::Link(LinkModel::new(
+                "The Book".to_owned(),
+                "https://doc.rust-lang.org/book/".to_owned(),
+                None,
+            )),
+        );
+        // * The saved file is deleted, so only the in-memory bookmarks know the topic
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // * Stands in for the update loop
+        let (tx, rx) = unbounded();
+        let app = std::thread::spawn(move || {
+            let mut added = vec![];
+            for msg in rx.iter() {
+                match msg {
+                    AppMessage::Dbus(request) => request.answer(&bookmark_manager),
+                    msg => added.push(msg),
+                }
+            }
+            added
+        });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let interface = StashInterface {
+                ctx: egui::Context::default(),
+                tx,
+            };
+            let _service = connection::Builder::address(address.as_str())
+                .unwrap()
+                .name(SERVICE_NAME)
+                .unwrap()
+                .serve_at(OBJECT_PATH, interface)
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+
+            let client = connection::Builder::address(address.as_str())
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let proxy = zbus::Proxy::new(&client, SERVICE_NAME, OBJECT_PATH, SERVICE_NAME)
+                .await
+                .unwrap();
+
+            let topics: Vec<String> = proxy.call("ListTopics", &()).await.unwrap();
+            assert_eq!(topics, vec!["Rust".to_owned()]);
+
+            let hits: Vec<(String, String, String)> =
+                proxy.call("Search", &("title:book",)).await.unwrap();
+            assert_eq!(
+                hits,
+                vec![(
+                    "Rust".to_owned(),
+                    "The Book".to_owned(),
+                    "https://doc.rust-lang.org/book/".to_owned()
+                )]
+            );
+
+            let () = proxy
+                .call("AddLink", &("News", "", "https://lwn.net/"))
+                .await
+                .unwrap();
+        });
+        // * Drops the interface and its sender, ending the stand-in loop
+        drop(runtime);
+
+        let added = app.join().unwrap();
+        daemon.kill().unwrap();
+        daemon.wait().unwrap();
+
+        assert_eq!(
+            added,
+            vec![AppMessage::AddLink(
+                "News".to_owned(),
+                LinkModel::new(
+                    "https://lwn.net/".to_owned(),
+                    "https://lwn.net/".to_owned(),
+                    None
+                )
+            )]
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use url::Url;
+
+use super::{
+    cache::{cache_dir, download, file_uri, fnv1a},
+    page_meta::PageMeta,
+};
+
+/// How a link shows its site.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SiteIcon {
+    /// `file://` URI of the cached icon
+    Cached(String),
+    /// First letter of the site, while fetching or when it has no usable icon
+    Letter(char),
+}
+
+/// Site icons by host, stored under `cache/icons` by the hash of their content and listed in
+/// `index.json` there. Missing icons are fetched in the background the first time a host
+/// is shown.
+#[derive(Debug)]
+pub struct FaviconCache {
+    /// Host to icon file name, `None` when no icon could be fetched
+    index: HashMap<String, Option<String>>,
+    pending: HashSet<String>,
+
+    tx: Sender<(String, Option<String>)>,
+    rx: Receiver<(String, Option<String>)>,
+}
+
+impl FaviconCache {
+    pub fn load() -> Self {
+        let index = fs::read_to_string(Self::index_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        let (tx, rx) = unbounded();
+
+        Self {
+            index,
+            pending: HashSet::new(),
+            tx,
+            rx,
+        }
+    }
+
+    /// The icon of the site `url` belongs to, fetching it if it was never tried.
+    pub fn icon(&mut self, ctx: &egui::Context, url: &str) -> SiteIcon {
+        let Some(host) = host_of(url) else {
+            return SiteIcon::Letter(letter_of(url));
+        };
+
+        match self.index.get(&host) {
+            Some(Some(file)) => return SiteIcon::Cached(file_uri(&Self::dir().join(file))),
+            Some(None) => {}
+            None => {
+                if self.pending.insert(host.clone()) {
+                    let tx = self.tx.clone();
+                    let ctx = ctx.clone();
+                    let url = url.to_owned();
+                    tokio::task::spawn_blocking(move || {
+                        let file = fetch_icon(&url).map_err(|e| {
+                            eprintln!("Failed to fetch icon for {}: {}", url, e);
+                        });
+                        let _ = tx.send((host, file.ok()));
+                        ctx.request_repaint();
+                    });
+                }
+            }
+        }
+
+        SiteIcon::Letter(letter_of(url))
+    }
+
+    /// Records the fetches that finished since the last frame.
+    pub fn poll(&mut self) {
+        let mut changed = false;
+        for (host, file) in self.rx.try_iter() {
+            self.pending.remove(&host);
+            self.index.insert(host, file);
+            changed = true;
+        }
+
+        if changed {
+            self.save();
+        }
+    }
+
+    /// Forgets every icon, so each site is fetched again the next time it is shown.
+    pub fn refresh(&mut self) {
+        self.index.clear();
+        if let Ok(entries) = fs::read_dir(Self::dir()) {
+            for entry in entries.flatten() {
+                if let Err(e) = fs::remove_file(entry.path()) {
+                    eprintln!("Failed to remove icon {:?}: {:?}", entry.path(), e);
+                }
+            }
+        }
+    }
+
+    fn save(&self) {
+        let data = serde_json::to_string(&self.index).expect("Failed to serialize icon index");
+        if let Err(e) = fs::write(Self::index_path(), data) {
+            eprintln!("Failed to save icon index: {:?}", e);
+        }
+    }
+
+    fn dir() -> PathBuf {
+        cache_dir("icons")
+    }
+
+    fn index_path() -> PathBuf {
+        Self::dir().join("index.json")
+    }
+}
+
+fn host_of(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(|host| host.trim_start_matches("www.").to_lowercase())
+}
+
+fn letter_of(url: &str) -> char {
+    host_of(url)
+        .and_then(|host| host.chars().find(|c| c.is_alphanumeric()))
+        .unwrap_or('?')
+        .to_ascii_uppercase()
+}
+
+/// Tries the icons the page links to, then the ones of its web manifest, then `/favicon.ico`.
+/// Returns the file name the first usable one was stored under. Blocking.
+fn fetch_icon(url: &str) -> Result<String, String> {
+    let origin = Url::parse(url)
+        .map_err(|e| e.to_string())?
+        .join("/")
+        .map_err(|e| e.to_string())?;
+
+    let mut candidates = vec![];
+    match PageMeta::fetch(origin.as_str()) {
+        Ok(meta) => {
+            candidates.extend(meta.icons);
+            if let Some(manifest) = meta.manifest {
+                candidates.extend(manifest_icons(&manifest).unwrap_or_default());
+            }
+        }
+        Err(e) => eprintln!("Failed to fetch {} for its icon: {}", origin, e),
+    }
+    candidates.push(
+        origin
+            .join("/favicon.ico")
+            .map_err(|e| e.to_string())?
+            .to_string(),
+    );
+
+    for candidate in candidates {
+        let Ok(bytes) = download(&candidate) else {
+            continue;
+        };
+        let Some(extension) = icon_extension(&bytes) else {
+            continue;
+        };
+
+        // * Content-addressed, sites sharing an icon share the file
+        let file = format!("{:016x}.{}", fnv1a(&bytes), extension);
+        let path = FaviconCache::dir().join(&file);
+        if !path.exists() {
+            fs::write(&path, &bytes).map_err(|e| e.to_string())?;
+        }
+
+        return Ok(file);
+    }
+
+    Err("no usable icon".to_owned())
+}
+
+/// Absolute URLs of the icons in the web manifest at `url`, smallest first.
+fn manifest_icons(url: &str) -> Result<Vec<String>, String> {
+    let base = Url::parse(url).map_err(|e| e.to_string())?;
+    let bytes = download(url)?;
+    let manifest =
+        serde_json::from_slice::<serde_json::Value>(&bytes).map_err(|e| e.to_string())?;
+
+    let mut icons = manifest
+        .get("icons")
+        .and_then(|icons| icons.as_array())
+        .map(|icons| {
+            icons
+                .iter()
+                .filter_map(|icon| {
+                    let src = base.join(icon.get("src")?.as_str()?).ok()?;
+                    let size = icon
+                        .get("sizes")
+                        .and_then(|sizes| sizes.as_str())
+                        .and_then(|sizes| sizes.split(['x', 'X']).next()?.parse::<u32>().ok())
+                        .unwrap_or(u32::MAX);
+                    Some((size, src.to_string()))
+                })
+                .collect::<Vec<(u32, String)>>()
+        })
+        .unwrap_or_default();
+    icons.sort_by_key(|(size, _)| *size);
+
+    Ok(icons.into_iter().map(|(_, src)| src).collect())
+}
+
+/// Extension the egui image loaders recognize the icon by, `None` if it is not an image.
+fn icon_extension(bytes: &[u8]) -> Option<&'static str> {
+    match image::guess_format(bytes) {
+        Ok(format) => format.extensions_str().first().copied(),
+        Err(_) => {
+            let head = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]);
+            head.contains("<svg").then_some("svg")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_server::{Response, TestServer};
+
+    #[test]
+    fn names_sites() {
+        assert_eq!(
+            host_of("https://WWW.Example.com/a"),
+            Some("example.com".to_owned())
+        );
+        assert_eq!(host_of("not a url"), None);
+        assert_eq!(letter_of("https://www.example.com"), 'E');
+        assert_eq!(letter_of("https://-x.test"), 'X');
+        assert_eq!(letter_of("file:///tmp/a"), '?');
+    }
+
+    #[test]
+    fn recognizes_icons() {
+        assert_eq!(
+            icon_extension(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("png")
+        );
+        assert_eq!(icon_extension(b"\0\0\x01\0\x01\0"), Some("ico"));
+        assert_eq!(
+            icon_extension(br#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg"/>"#),
+            Some("svg")
+        );
+        assert_eq!(icon_extension(b"<html><body>Not found</body></html>"), None);
+    }
+
+    #[test]
+    fn lists_manifest_icons_smallest_first() {
+        let server = TestServer::start(|_, path| match path {
+            "/app/site.webmanifest" => Response::ok(
+                "application/manifest+json",
+                r#"{"icons": [
+                    {"src": "icons/512.png", "sizes": "512x512"},
+                    {"src": "/any.svg"},
+                    {"src": "icons/192.png", "sizes": "192x192 384x384"},
+                    {"sizes": "16x16"}
+                ]}"#,
+            ),
+            _ => Response::status(404),
+        });
+
+        assert_eq!(
+            manifest_icons(&server.url("/app/site.webmanifest")).unwrap(),
+            vec![
+                server.url("/app/icons/192.png"),
+                server.url("/app/icons/512.png"),
+                server.url("/any.svg"),
+            ]
+        );
+        assert!(manifest_icons(&server.url("/missing.json")).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+use crossbeam::channel::{unbounded, Sender};
+use lazy_static::lazy_static;
+
+/// Longest wait for pending writes on exit.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    static ref WRITER: FileWriter = FileWriter::start();
+}
+
+/// Writes files on a thread of its own. Writes to the same file that queue up while one is
+/// running are collapsed into the latest.
+#[derive(Debug)]
+struct FileWriter {
+    tx: Sender<(PathBuf, String)>,
+    /// Writes queued and not done yet
+    pending: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl FileWriter {
+    fn start() -> Self {
+        let (tx, rx) = unbounded::<(PathBuf, String)>();
+        let pending = Arc::new((Mutex::new(0usize), Condvar::new()));
+
+        let done = pending.clone();
+        std::thread::spawn(move || {
+            while let Ok(first) = rx.recv() {
+                let (writes, count) = coalesce(std::iter::once(first).chain(rx.try_iter()));
+                for (path, data) in writes {
+                    if let Err(e) = write_atomic(&path, &data) {
+                        eprintln!("Failed to write {}: {:?}", path.display(), e);
+                    }
+                }
+
+                let (lock, cvar) = &*done;
+                let mut pending = lock.lock().expect("Failed to lock pending writes");
+                *pending -= count;
+                cvar.notify_all();
+            }
+        });
+
+        Self { tx, pending }
+    }
+}
+
+/// The latest data queued for each path among `writes`, and how many writes there were.
+fn coalesce(writes: impl Iterator<Item = (PathBuf, String)>) -> (HashMap<PathBuf, String>, usize) {
+    let mut latest = HashMap::new();
+    let mut count = 0;
+    for (path, data) in writes {
+        latest.insert(path, data);
+        count += 1;
+    }
+
+    (latest, count)
+}
+
+/// Writes through a temporary file, so a crash mid-write leaves the old file whole.
+fn write_atomic(path: &Path, data: &str) -> std::io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    fs::write(&partial, data)?;
+    fs::rename(&partial, path)
+}
+
+/// Queues `data` to be written to `path` in the background.
+pub fn write_later(path: PathBuf, data: String) {
+    let (lock, _) = &*WRITER.pending;
+    *lock.lock().expect("Failed to lock pending writes") += 1;
+    WRITER.tx.send((path, data)).expect("Unable to send");
+}
+
+/// Waits for the queued writes to be done.
+pub fn flush_writes() {
+    if !wait_for(&WRITER.pending, FLUSH_TIMEOUT) {
+        eprintln!("Gave up waiting for files to be written");
+    }
+}
+
+/// Waits up to `timeout` for no write to be pending, `false` if some still are.
+fn wait_for(pending: &(Mutex<usize>, Condvar), timeout: Duration) -> bool {
+    let (lock, cvar) = pending;
+    let pending = lock.lock().expect("Failed to lock pending writes");
+    let (pending, timeout) = cvar
+        .wait_timeout_while(pending, timeout, |pending| *pending > 0)
+        .expect("Failed to lock pending writes");
+    drop(pending);
+
+    !timeout.timed_out()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("stash-writer-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn keeps_the_latest_write_of_a_file() {
+        let (a, b) = (PathBuf::from("a.json"), PathBuf::from("b.json"));
+        let (writes, count) = coalesce(
+            vec![
+                (a.clone(), "1".to_owned()),
+                (b.clone(), "2".to_owned()),
+                (a.clone(), "3".to_owned()),
+            ]
+            .into_iter(),
+        );
+
+        assert_eq!(count, 3);
+        assert_eq!(
+            writes,
+            HashMap::from([(a, "3".to_owned()), (b, "2".to_owned())])
+        );
+    }
+
+    #[test]
+    fn replaces_files_through_a_part_file() {
+        let dir = temp_dir("atomic");
+        let path = dir.join("bookmarks.json");
+        fs::write(&path, "old").unwrap();
+
+        write_atomic(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!dir.join("bookmarks.json.part").exists());
+
+        // * The old file stays whole when the write fails
+        let missing = dir.join("missing").join("bookmarks.json");
+        assert!(write_atomic(&missing, "new").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    }
+
+    #[test]
+    fn flushes_the_queued_writes() {
+        let dir = temp_dir("flush");
+        let path = dir.join("trash.json");
+        for data in ["1", "2", "3"] {
+            write_later(path.clone(), data.to_owned());
+        }
+
+        flush_writes();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "3");
+        assert_eq!(*WRITER.pending.0.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn gives_up_waiting_after_the_timeout() {
+        let pending = Arc::new((Mutex::new(1usize), Condvar::new()));
+        assert!(!wait_for(&pending, Duration::from_millis(50)));
+
+        let done = pending.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            let (lock, cvar) = &*done;
+            *lock.lock().unwrap() -= 1;
+            cvar.notify_all();
+        });
+        assert!(wait_for(&pending, Duration::from_secs(5)));
+    }
+}
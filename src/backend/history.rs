@@ -0,0 +1,762 @@
+use std::collections::VecDeque;
+
+use super::{
+    bookmark_manager::BookmarkManager,
+    models::{LinkModel, TopicModel, TrashEntry, TrashedItem},
+};
+use crate::utils::{
+    enums::{AppMessage, BookmarkItem},
+    group_by_topic,
+};
+
+const MAX_HISTORY: usize = 100;
+
+/// A recorded mutation: the message that was applied and the messages that revert it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub label: String,
+    pub undo: Vec<AppMessage>,
+    pub redo: AppMessage,
+    /// What applying `redo` put in the trash, taken out again when it is undone
+    pub trashed: Vec<TrashEntry>,
+}
+
+/// Bounded undo and redo stacks, kept in memory until the app quits.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct History {
+    undo_stack: VecDeque<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Records a new mutation, which makes the undone ones unreachable.
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.redo_stack.clear();
+        self.push_undo(entry);
+    }
+
+    /// Records a redone mutation, keeping the rest of the redo stack.
+    pub fn push_undo(&mut self, entry: HistoryEntry) {
+        if self.undo_stack.len() == MAX_HISTORY {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(entry);
+    }
+
+    pub fn pop_undo(&mut self) -> Option<HistoryEntry> {
+        self.undo_stack.pop_back()
+    }
+
+    pub fn push_redo(&mut self, entry: HistoryEntry) {
+        self.redo_stack.push(entry);
+    }
+
+    pub fn pop_redo(&mut self) -> Option<HistoryEntry> {
+        self.redo_stack.pop()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
+
+/// Builds the history entry for `msg` from the bookmarks as they are before it is applied.
+/// Returns `None` for messages that don't change bookmarks or would be a no-op.
+pub fn record(manager: &BookmarkManager, msg: &AppMessage) -> Option<HistoryEntry> {
+    let topics = manager.get_topics();
+    let topic_exists = |name: &str| topics.iter().any(|t| t.name == name);
+    let links_of = |name: &str| {
+        manager.get_links_for_topic(&BookmarkItem::Topic(TopicModel::new(name.to_owned())))
+    };
+    // * Positions of links about to be removed, in the order they can be re-inserted
+    let positions = |links: &[(String, LinkModel)]| {
+        let mut positions = links
+            .iter()
+            .filter_map(|(name, link)| {
+                // * Restored as they are now, found by URL like `BookmarkManager` does
+                let topic_links = links_of(name);
+                let idx = topic_links.iter().position(|l| l.url == link.url)?;
+                Some((name.clone(), idx, topic_links[idx].clone()))
+            })
+            .collect::<Vec<(String, usize, LinkModel)>>();
+        positions.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+        positions
+    };
+    // * Moved links are taken out of the target and put back where they were
+    let move_undo = |from: &str, links: &[LinkModel], to: &str| {
+        // * Links the target has stay where they are, see `BookmarkManager::move_links`
+        let from_links = links_of(from);
+        let links = new_links(&links_of(to), links)
+            .into_iter()
+            .filter(|link| from_links.iter().any(|l| l.url == link.url))
+            .collect::<Vec<LinkModel>>();
+        if from == to || links.is_empty() {
+            return None;
+        }
+
+        let moved = links
+            .iter()
+            .map(|link| (from.to_owned(), link.clone()))
+            .collect::<Vec<(String, LinkModel)>>();
+        Some(vec![
+            AppMessage::RemoveLinks(
+                links
+                    .iter()
+                    .map(|link| (to.to_owned(), link.clone()))
+                    .collect(),
+            ),
+            AppMessage::RestoreLinks(positions(&moved)),
+        ])
+    };
+
+    let (label, undo) = match msg {
+        AppMessage::AddTopic(topic) => {
+            if topic_exists(&topic.name) {
+                return None;
+            }
+            ("Topic added", vec![AppMessage::RemoveTopic(topic.clone())])
+        }
+        AppMessage::EditTopic(old_topic, new_topic) => (
+            "Topic renamed",
+            vec![AppMessage::EditTopic(new_topic.clone(), old_topic.clone())],
+        ),
+        AppMessage::RemoveTopic(topic) => {
+            let idx = topics.iter().position(|t| t == topic)?;
+            (
+                "Topic removed",
+                vec![AppMessage::RestoreTopic(
+                    idx,
+                    topic.clone(),
+                    links_of(&topic.name),
+                )],
+            )
+        }
+        AppMessage::ReorderTopic(old_index, new_index) => (
+            "Topic moved",
+            vec![AppMessage::ReorderTopic(*new_index, *old_index)],
+        ),
+        AppMessage::AddLink(name, link) => {
+            // * Ignored by the topic, see `BookmarkManager::add_links`
+            if links_of(name).iter().any(|l| l.same_url(&link.url)) {
+                return None;
+            }
+            let undo = if topic_exists(name) {
+                AppMessage::RemoveLink(name.clone(), link.clone())
+            } else {
+                AppMessage::RemoveTopic(TopicModel::new(name.clone()))
+            };
+            ("Link added", vec![undo])
+        }
+        AppMessage::EditLink(name, old_link, link) => (
+            "Link edited",
+            vec![AppMessage::EditLink(
+                name.clone(),
+                link.clone(),
+                old_link.clone(),
+            )],
+        ),
+        AppMessage::EditLinks(edits) => (
+            if edits.len() == 1 {
+                "Link edited"
+            } else {
+                "Links edited"
+            },
+            vec![AppMessage::EditLinks(
+                edits
+                    .iter()
+                    .map(|(name, old_link, link)| (name.clone(), link.clone(), old_link.clone()))
+                    .collect(),
+            )],
+        ),
+        AppMessage::RemoveLink(name, link) => (
+            "Link removed",
+            vec![AppMessage::RestoreLinks(positions(&[(
+                name.clone(),
+                link.clone(),
+            )]))],
+        ),
+        AppMessage::ReorderLink(name, old_index, new_index) => (
+            "Link moved",
+            vec![AppMessage::ReorderLink(
+                name.clone(),
+                *new_index,
+                *old_index,
+            )],
+        ),
+        AppMessage::MoveLinks(from, links, to) => (
+            if links.len() == 1 {
+                "Link moved"
+            } else {
+                "Links moved"
+            },
+            move_undo(from, links, to)?,
+        ),
+        AppMessage::MoveLinkTo(from, link, to, _) => (
+            "Link moved",
+            move_undo(from, std::slice::from_ref(link), to)?,
+        ),
+        AppMessage::CopyLinks(links, to) => {
+            // * Links the topic has are skipped, see `BookmarkManager::copy_links`
+            let copied = new_links(&links_of(to), links);
+            if copied.is_empty() {
+                return None;
+            }
+            (
+                "Links copied",
+                vec![AppMessage::RemoveLinks(
+                    copied.into_iter().map(|link| (to.clone(), link)).collect(),
+                )],
+            )
+        }
+        AppMessage::RemoveLinks(links) => (
+            if links.len() == 1 {
+                "Link removed"
+            } else {
+                "Links removed"
+            },
+            vec![AppMessage::RestoreLinks(positions(links))],
+        ),
+        AppMessage::TagLinks(links, tag) => {
+            let undo = group_by_topic(links)
+                .into_iter()
+                .flat_map(|(name, links)| {
+                    links
+                        .into_iter()
+                        .filter(|link| !link.has_tag(tag))
+                        .map(move |link| {
+                            let mut tagged = link.clone();
+                            tagged.tags.push(tag.clone());
+                            AppMessage::EditLink(name.clone(), tagged, link)
+                        })
+                })
+                .collect::<Vec<AppMessage>>();
+            if undo.is_empty() {
+                return None;
+            }
+            ("Links tagged", undo)
+        }
+        AppMessage::MergeDuplicates(groups) => {
+            let mut undo = groups
+                .iter()
+                .filter_map(|group| {
+                    let (name, link) = group.first()?;
+                    let links = group
+                        .iter()
+                        .map(|(_, link)| link.clone())
+                        .collect::<Vec<LinkModel>>();
+                    Some(AppMessage::EditLink(
+                        name.clone(),
+                        LinkModel::merge(&links),
+                        link.clone(),
+                    ))
+                })
+                .collect::<Vec<AppMessage>>();
+            let dropped = groups
+                .iter()
+                .flat_map(|group| group.iter().skip(1).cloned())
+                .collect::<Vec<(String, LinkModel)>>();
+            undo.push(AppMessage::RestoreLinks(positions(&dropped)));
+            ("Duplicates merged", undo)
+        }
+        AppMessage::MarkRead(links, read) => {
+            let changed = links
+                .iter()
+                .filter(|(name, url)| {
+                    links_of(name)
+                        .iter()
+                        .any(|link| &link.url == url && link.is_read() != *read)
+                })
+                .cloned()
+                .collect::<Vec<(String, String)>>();
+            if changed.is_empty() {
+                return None;
+            }
+            (
+                if *read {
+                    "Marked as read"
+                } else {
+                    "Marked as unread"
+                },
+                vec![AppMessage::MarkRead(changed, !read)],
+            )
+        }
+        AppMessage::ImportTopics(imported) => {
+            let mut undo = vec![];
+            let mut created = vec![];
+            for (name, links) in imported {
+                if !topic_exists(name) {
+                    if !created.contains(name) {
+                        created.push(name.clone());
+                        undo.push(AppMessage::RemoveTopic(TopicModel::new(name.clone())));
+                    }
+                    continue;
+                }
+
+                // * Links the topic has are skipped, see `BookmarkManager::import_topics`
+                let added = new_links(&links_of(name), links);
+                if !added.is_empty() {
+                    undo.push(AppMessage::RemoveLinks(
+                        added.into_iter().map(|link| (name.clone(), link)).collect(),
+                    ));
+                }
+            }
+            if undo.is_empty() {
+                return None;
+            }
+            ("Bookmarks imported", undo)
+        }
+        AppMessage::RestoreFromTrash(entry) => {
+            let name = match &entry.item {
+                TrashedItem::Topic { topic, .. } => &topic.name,
+                TrashedItem::Link { topic, .. } => topic,
+            };
+            (
+                "Restored from trash",
+                vec![AppMessage::ReturnToTrash(
+                    entry.clone(),
+                    !topic_exists(name),
+                )],
+            )
+        }
+        AppMessage::AddSmartTopic(smart_topic) => {
+            if manager
+                .get_smart_topics()
+                .iter()
+                .any(|t| t.name == smart_topic.name)
+            {
+                return None;
+            }
+            (
+                "Smart topic added",
+                vec![AppMessage::RemoveSmartTopic(smart_topic.clone())],
+            )
+        }
+        AppMessage::RemoveSmartTopic(smart_topic) => {
+            let idx = manager
+                .get_smart_topics()
+                .iter()
+                .position(|t| t == smart_topic)?;
+            (
+                "Smart topic removed",
+                vec![AppMessage::RestoreSmartTopic(idx, smart_topic.clone())],
+            )
+        }
+        _ => return None,
+    };
+
+    Some(HistoryEntry {
+        label: label.to_owned(),
+        undo,
+        redo: msg.clone(),
+        trashed: vec![],
+    })
+}
+
+/// Applies `msg` to `target` with `apply` and returns its history entry, if it has one.
+/// `manager` gets the bookmarks of `target`.
+pub fn apply<T>(
+    target: &mut T,
+    manager: fn(&mut T) -> &mut BookmarkManager,
+    msg: AppMessage,
+    apply: impl FnOnce(&mut T, AppMessage),
+) -> Option<HistoryEntry> {
+    let entry = record(manager(target), &msg);
+    let trashed = manager(target).get_trash().len();
+    apply(target, msg);
+
+    // * A message only adds to the end of the trash, or only takes from it
+    let trash = manager(target).get_trash();
+    entry.map(|entry| HistoryEntry {
+        trashed: trash.get(trashed..).unwrap_or_default().to_vec(),
+        ..entry
+    })
+}
+
+/// Reverts `entry` with `apply`. What it put in the trash is taken out again, and so is what
+/// reverting it puts there, as removing an addition is not a deletion worth keeping.
+pub fn undo<T>(
+    target: &mut T,
+    manager: fn(&mut T) -> &mut BookmarkManager,
+    entry: &HistoryEntry,
+    mut apply: impl FnMut(&mut T, AppMessage),
+) {
+    let (returns, undo) = entry
+        .undo
+        .clone()
+        .into_iter()
+        .partition::<Vec<AppMessage>, _>(|msg| matches!(msg, AppMessage::ReturnToTrash(_, _)));
+
+    // * The other undo messages only add to the trash, so its new entries are theirs
+    let trashed = manager(target).get_trash().len();
+    for msg in undo {
+        apply(target, msg);
+    }
+    let manager = manager(target);
+    manager.truncate_trash(trashed);
+    for trash_entry in &entry.trashed {
+        manager.delete_from_trash(trash_entry);
+    }
+
+    // * Undoing a restore is the one case where the trash should grow back
+    for msg in returns {
+        apply(target, msg);
+    }
+}
+
+/// Applies `entry` again, returning it recorded anew as the positions it undoes to may have
+/// changed.
+pub fn redo<T>(
+    target: &mut T,
+    manager: fn(&mut T) -> &mut BookmarkManager,
+    entry: &HistoryEntry,
+    apply_msg: impl FnOnce(&mut T, AppMessage),
+) -> Option<HistoryEntry> {
+    apply(target, manager, entry.redo.clone(), apply_msg)
+}
+
+/// The links of `links` a topic with `existing` takes, leaving out URLs it already has and
+/// repeated ones.
+fn new_links(existing: &[LinkModel], links: &[LinkModel]) -> Vec<LinkModel> {
+    let mut added: Vec<LinkModel> = vec![];
+    for link in links {
+        let is_duplicate = existing
+            .iter()
+            .chain(added.iter())
+            .any(|l| l.same_url(&link.url));
+        if !is_duplicate {
+            added.push(link.clone());
+        }
+    }
+
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::backend::models::SmartTopicModel;
+
+    fn manager(name: &str) -> BookmarkManager {
+        let dir =
+            std::env::temp_dir().join(format!("stash-history-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        BookmarkManager::load(&dir, "bookmarks.json")
+    }
+
+    fn topic(name: &str) -> BookmarkItem {
+        BookmarkItem::Topic(TopicModel::new(name.to_owned()))
+    }
+
+    fn link(url: &str) -> LinkModel {
+        LinkModel::new(url.to_owned(), url.to_owned(), None)
+    }
+
+    fn links(urls: &[&str]) -> Vec<BookmarkItem> {
+        urls.iter()
+            .map(|url| BookmarkItem::Link(link(url)))
+            .collect()
+    }
+
+    /// Rust and News with links and an empty topic, three smart topics, and a trashed copy of a
+    /// link Rust has again, which undoing must not take for its own.
+    fn stash(name: &str) -> BookmarkManager {
+        let mut manager = manager(name);
+        manager.add_links(
+            topic("Rust"),
+            links(&["https://a.test/", "https://b.test/", "https://x.test/"]),
+        );
+        manager.remove_link(topic("Rust"), BookmarkItem::Link(link("https://x.test/")));
+        manager.add_link(topic("Rust"), BookmarkItem::Link(link("https://x.test/")));
+        manager.add_links(
+            topic("News"),
+            links(&["https://d.test/", "https://www.a.test"]),
+        );
+        manager.add_topic(topic("Empty"));
+        for name in ["One", "Two", "Three"] {
+            manager.add_smart_topic(SmartTopicModel::new(name.to_owned(), name.to_lowercase()));
+        }
+
+        manager
+    }
+
+    /// The bookmark side of `App::apply_message`, for the messages these tests send.
+    fn apply_to(manager: &mut BookmarkManager, msg: AppMessage) {
+        match msg {
+            AppMessage::RemoveTopic(topic) => manager.remove_topic(BookmarkItem::Topic(topic)),
+            AppMessage::RestoreTopic(idx, topic, links) => manager.insert_topic(
+                idx,
+                BookmarkItem::Topic(topic),
+                links.into_iter().map(BookmarkItem::Link).collect(),
+            ),
+            AppMessage::EditLink(name, old_link, link) => manager.edit_link(
+                BookmarkItem::Topic(TopicModel::new(name)),
+                BookmarkItem::Link(old_link),
+                BookmarkItem::Link(link),
+            ),
+            AppMessage::RemoveLink(name, link) => manager.remove_link(
+                BookmarkItem::Topic(TopicModel::new(name)),
+                BookmarkItem::Link(link),
+            ),
+            AppMessage::MoveLinks(from, links, to) => {
+                manager.move_links(
+                    BookmarkItem::Topic(TopicModel::new(from)),
+                    links.into_iter().map(BookmarkItem::Link).collect(),
+                    BookmarkItem::Topic(TopicModel::new(to)),
+                );
+            }
+            AppMessage::MoveLinkTo(from, link, to, idx) => {
+                manager.move_link_to(
+                    BookmarkItem::Topic(TopicModel::new(from)),
+                    BookmarkItem::Link(link),
+                    BookmarkItem::Topic(TopicModel::new(to)),
+                    idx,
+                );
+            }
+            AppMessage::CopyLinks(links, to) => {
+                manager.copy_links(
+                    links.into_iter().map(BookmarkItem::Link).collect(),
+                    BookmarkItem::Topic(TopicModel::new(to)),
+                );
+            }
+            AppMessage::RemoveLinks(links) => {
+                for (name, link) in links {
+                    manager.remove_link(
+                        BookmarkItem::Topic(TopicModel::new(name)),
+                        BookmarkItem::Link(link),
+                    );
+                }
+            }
+            AppMessage::RestoreLinks(links) => manager.insert_links(
+                links
+                    .into_iter()
+                    .map(|(name, idx, link)| {
+                        (
+                            BookmarkItem::Topic(TopicModel::new(name)),
+                            idx,
+                            BookmarkItem::Link(link),
+                        )
+                    })
+                    .collect(),
+            ),
+            AppMessage::MergeDuplicates(groups) => manager.merge_duplicates(&groups),
+            AppMessage::MarkRead(links, read) => manager.mark_read(&links, read),
+            AppMessage::ImportTopics(topics) => {
+                manager.import_topics(topics);
+            }
+            AppMessage::RemoveSmartTopic(smart_topic) => manager.remove_smart_topic(&smart_topic),
+            AppMessage::RestoreSmartTopic(idx, smart_topic) => {
+                manager.insert_smart_topic(idx, smart_topic)
+            }
+            AppMessage::RestoreFromTrash(entry) => {
+                manager.restore_from_trash(&entry);
+            }
+            AppMessage::ReturnToTrash(entry, created_topic) => {
+                manager.return_to_trash(&entry, created_topic);
+            }
+            msg => panic!("Not applied in these tests: {:?}", msg),
+        }
+    }
+
+    type State = (
+        Vec<String>,
+        Vec<(String, String, String, Vec<String>, bool)>,
+        Vec<SmartTopicModel>,
+    );
+
+    /// Topics, links and smart topics in order, leaving out the times links were changed at.
+    fn state(manager: &BookmarkManager) -> State {
+        (
+            manager.get_topics().into_iter().map(|t| t.name).collect(),
+            manager
+                .get_all_links()
+                .into_iter()
+                .map(|(name, link)| {
+                    let is_read = link.is_read();
+                    (name, link.url, link.title, link.tags, is_read)
+                })
+                .collect(),
+            manager.get_smart_topics(),
+        )
+    }
+
+    fn trashed_items(manager: &BookmarkManager) -> Vec<TrashedItem> {
+        manager.get_trash().into_iter().map(|e| e.item).collect()
+    }
+
+    /// Applies `msg`, undoes it, redoes it and undoes it again. Undoing has to bring back the
+    /// bookmarks and the trash exactly, redoing has to do what applying did. Returns the state
+    /// after applying.
+    fn check_undo(manager: &mut BookmarkManager, msg: AppMessage) -> State {
+        let before = (state(manager), manager.get_trash());
+        let entry = apply(manager, |m| m, msg, apply_to).expect("Not recorded");
+        let after = (state(manager), trashed_items(manager));
+        assert_ne!(after.0, before.0);
+
+        undo(manager, |m| m, &entry, apply_to);
+        assert_eq!((state(manager), manager.get_trash()), before);
+
+        let entry = redo(manager, |m| m, &entry, apply_to).expect("Not recorded again");
+        assert_eq!((state(manager), trashed_items(manager)), after);
+
+        undo(manager, |m| m, &entry, apply_to);
+        assert_eq!((state(manager), manager.get_trash()), before);
+
+        after.0
+    }
+
+    #[test]
+    fn undoes_removing_a_topic_where_it_was() {
+        let mut manager = stash("topic");
+        let links = manager.get_links_for_topic(&topic("News"));
+
+        let (topics, ..) = check_undo(
+            &mut manager,
+            AppMessage::RemoveTopic(TopicModel::new("News".to_owned())),
+        );
+        assert_eq!(topics, vec!["Rust", "Empty"]);
+        assert_eq!(manager.get_links_for_topic(&topic("News")), links);
+
+        check_undo(
+            &mut manager,
+            AppMessage::RemoveLink("Rust".to_owned(), link("https://x.test/")),
+        );
+    }
+
+    #[test]
+    fn undoes_removing_a_smart_topic_where_it_was() {
+        let mut manager = stash("smart-topic");
+        let two = manager.get_smart_topics()[1].clone();
+
+        let (.., smart_topics) = check_undo(&mut manager, AppMessage::RemoveSmartTopic(two));
+        assert_eq!(smart_topics.len(), 2);
+        assert_eq!(manager.get_smart_topics()[1].name, "Two");
+    }
+
+    #[test]
+    fn undoes_moves_without_touching_older_trash() {
+        let mut manager = stash("move");
+
+        let (_, links, _) = check_undo(
+            &mut manager,
+            AppMessage::MoveLinks(
+                "Rust".to_owned(),
+                vec![link("https://a.test/"), link("https://x.test/")],
+                "News".to_owned(),
+            ),
+        );
+        // * News had the first one already
+        assert_eq!(
+            links
+                .iter()
+                .map(|(name, url, ..)| (name.as_str(), url.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Rust", "https://a.test/"),
+                ("Rust", "https://b.test/"),
+                ("News", "https://d.test/"),
+                ("News", "https://www.a.test"),
+                ("News", "https://x.test/"),
+            ]
+        );
+
+        check_undo(
+            &mut manager,
+            AppMessage::MoveLinkTo(
+                "Rust".to_owned(),
+                link("https://x.test/"),
+                "Empty".to_owned(),
+                0,
+            ),
+        );
+    }
+
+    #[test]
+    fn undoes_copies_and_imports() {
+        let mut manager = stash("copy");
+
+        check_undo(
+            &mut manager,
+            AppMessage::CopyLinks(
+                vec![link("https://b.test/"), link("https://d.test/")],
+                "Rust".to_owned(),
+            ),
+        );
+        check_undo(
+            &mut manager,
+            AppMessage::ImportTopics(vec![
+                (
+                    "Rust".to_owned(),
+                    vec![link("https://A.test"), link("https://y.test/")],
+                ),
+                ("New".to_owned(), vec![link("https://z.test/")]),
+            ]),
+        );
+    }
+
+    #[test]
+    fn undoes_merging_duplicates() {
+        let mut manager = stash("merge");
+        manager.add_link(topic("Empty"), BookmarkItem::Link(link("https://x.test")));
+        let mut tagged = link("https://www.a.test");
+        tagged.tags.push("later".to_owned());
+        manager.edit_link(
+            topic("News"),
+            BookmarkItem::Link(link("https://www.a.test")),
+            BookmarkItem::Link(tagged),
+        );
+
+        let groups = manager.find_duplicates();
+        let (_, links, _) = check_undo(&mut manager, AppMessage::MergeDuplicates(groups));
+        assert_eq!(links.len(), 4);
+        assert_eq!(links[0].3, vec!["later".to_owned()]);
+    }
+
+    #[test]
+    fn undoes_marking_read() {
+        let mut manager = stash("read");
+        let read = vec![
+            ("Rust".to_owned(), "https://a.test/".to_owned()),
+            ("News".to_owned(), "https://d.test/".to_owned()),
+        ];
+
+        check_undo(&mut manager, AppMessage::MarkRead(read.clone(), true));
+        manager.mark_read(&read, true);
+        check_undo(&mut manager, AppMessage::MarkRead(read, false));
+        // * Nothing to undo when nothing changes
+        assert_eq!(
+            record(
+                &manager,
+                &AppMessage::MarkRead(
+                    vec![("Rust".to_owned(), "https://a.test/".to_owned())],
+                    true
+                )
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn undoes_restoring_from_the_trash() {
+        let mut manager = stash("restore");
+        manager.remove_topic(topic("News"));
+        let entry = manager.get_trash().pop().unwrap();
+
+        let (topics, ..) = check_undo(&mut manager, AppMessage::RestoreFromTrash(entry));
+        assert_eq!(topics, vec!["Rust", "News", "Empty"]);
+
+        // * Restoring a link of a removed topic brings the topic back, undoing removes it
+        manager.remove_link(topic("Rust"), BookmarkItem::Link(link("https://b.test/")));
+        manager.remove_topic(topic("Rust"));
+        let link_entry = manager.get_trash()[2].clone();
+        let (topics, ..) = check_undo(&mut manager, AppMessage::RestoreFromTrash(link_entry));
+        assert_eq!(topics, vec!["Empty", "Rust"]);
+    }
+}
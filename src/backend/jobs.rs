@@ -0,0 +1,502 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+};
+
+use crossbeam::channel::{unbounded, Sender};
+
+use super::{
+    archive::ArchiveResult, link_checker::LinkCheck, models::LinkModel, page_meta::PageMeta,
+};
+use crate::utils::enums::AppMessage;
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    Import,
+    Export,
+    Metadata,
+    LinkCheck,
+    Resolve,
+    Archive,
+}
+
+impl JobKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobKind::Import => "Import",
+            JobKind::Export => "Export",
+            JobKind::Metadata => "Fetch titles",
+            JobKind::LinkCheck => "Link check",
+            JobKind::Resolve => "Resolve links",
+            JobKind::Archive => "Archive",
+        }
+    }
+
+    /// Workers of one job, the global limit applies on top.
+    fn workers(&self) -> usize {
+        match self {
+            JobKind::Import | JobKind::Export => 1,
+            JobKind::Metadata | JobKind::Archive => 4,
+            JobKind::LinkCheck | JobKind::Resolve => 8,
+        }
+    }
+}
+
+/// Result of one item of a job, applied to the bookmarks on the UI thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobOutput {
+    /// Topics read from an exported file
+    Imported(Result<Vec<(String, Vec<LinkModel>)>, String>),
+    /// File the bookmarks were written to
+    Exported(Result<PathBuf, String>),
+    Fetched {
+        topic: String,
+        url: String,
+        meta: Result<PageMeta, String>,
+    },
+    Checked(LinkCheck),
+    Resolved(LinkCheck),
+    Archived(ArchiveResult),
+}
+
+impl JobOutput {
+    pub fn is_error(&self) -> bool {
+        match self {
+            JobOutput::Imported(result) => result.is_err(),
+            JobOutput::Exported(result) => result.is_err(),
+            JobOutput::Fetched { meta, .. } => meta.is_err(),
+            JobOutput::Checked(check) | JobOutput::Resolved(check) => check.error.is_some(),
+            JobOutput::Archived(result) => result.error.is_some(),
+        }
+    }
+
+    /// Whether applying the output records something on links. Imports are merged by a
+    /// message of their own.
+    pub fn changes_bookmarks(&self) -> bool {
+        match self {
+            JobOutput::Imported(_) | JobOutput::Exported(_) => false,
+            JobOutput::Fetched { meta, .. } => meta.is_ok(),
+            JobOutput::Checked(_) | JobOutput::Resolved(_) => true,
+            JobOutput::Archived(result) => result.error.is_none(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Running,
+    Done,
+    Cancelled,
+}
+
+/// Progress of a job, updated as its outputs come in.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub label: String,
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub state: JobState,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Job {
+    pub fn is_running(&self) -> bool {
+        self.state == JobState::Running
+    }
+
+    /// Share of the items done.
+    pub fn progress(&self) -> f32 {
+        self.done as f32 / self.total.max(1) as f32
+    }
+}
+
+/// Limits how many items run at once over all jobs.
+#[derive(Debug)]
+struct Slots {
+    /// `(running, limit)`
+    state: Mutex<(usize, usize)>,
+    freed: Condvar,
+}
+
+impl Slots {
+    fn acquire(self: &Arc<Self>) -> SlotGuard {
+        let mut state = self.state.lock().expect("Failed to lock job slots");
+        while state.0 >= state.1 {
+            state = self.freed.wait(state).expect("Failed to lock job slots");
+        }
+        state.0 += 1;
+
+        SlotGuard(self.clone())
+    }
+}
+
+struct SlotGuard(Arc<Slots>);
+
+impl Drop for SlotGuard {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().expect("Failed to lock job slots");
+        state.0 -= 1;
+        self.0.freed.notify_one();
+    }
+}
+
+/// Runs long work off the UI thread. Every finished item is sent back as
+/// [`AppMessage::JobOutput`] and recorded with [`JobManager::record`].
+#[derive(Debug)]
+pub struct JobManager {
+    tx: Sender<AppMessage>,
+    slots: Arc<Slots>,
+    next_id: JobId,
+    jobs: Vec<Job>,
+}
+
+impl JobManager {
+    pub fn new(tx: Sender<AppMessage>, max_tasks: usize) -> Self {
+        Self {
+            tx,
+            slots: Arc::new(Slots {
+                state: Mutex::new((0, max_tasks.max(1))),
+                freed: Condvar::new(),
+            }),
+            next_id: 0,
+            jobs: vec![],
+        }
+    }
+
+    /// Items of all jobs allowed to run at once.
+    pub fn set_max_tasks(&self, max_tasks: usize) {
+        let mut state = self.slots.state.lock().expect("Failed to lock job slots");
+        state.1 = max_tasks.max(1);
+        self.slots.freed.notify_all();
+    }
+
+    /// Starts running `work` on each of `items` in the background.
+    pub fn spawn<T, F>(
+        &mut self,
+        ctx: &egui::Context,
+        kind: JobKind,
+        label: String,
+        items: Vec<T>,
+        work: F,
+    ) -> JobId
+    where
+        T: Send + 'static,
+        F: Fn(T) -> JobOutput + Send + Sync + 'static,
+    {
+        self.spawn_paced(ctx, kind, label, items, |_: &T| {}, work)
+    }
+
+    /// Like [`Self::spawn`], with `pace` run on each item before it takes a slot, so waiting
+    /// for a rate limit doesn't hold up other jobs.
+    pub fn spawn_paced<T, P, F>(
+        &mut self,
+        ctx: &egui::Context,
+        kind: JobKind,
+        label: String,
+        items: Vec<T>,
+        pace: P,
+        work: F,
+    ) -> JobId
+    where
+        T: Send + 'static,
+        P: Fn(&T) + Send + Sync + 'static,
+        F: Fn(T) -> JobOutput + Send + Sync + 'static,
+    {
+        self.next_id += 1;
+        let id = self.next_id;
+        let total = items.len();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let (items_tx, items_rx) = unbounded();
+        for item in items {
+            items_tx.send(item).expect("Unable to send");
+        }
+        drop(items_tx);
+
+        let pace = Arc::new(pace);
+        let work = Arc::new(work);
+        for _ in 0..kind.workers().min(total) {
+            let items_rx = items_rx.clone();
+            let tx = self.tx.clone();
+            let slots = self.slots.clone();
+            let cancelled = cancelled.clone();
+            let pace = pace.clone();
+            let work = work.clone();
+            let ctx = ctx.clone();
+            tokio::task::spawn_blocking(move || {
+                for item in items_rx.iter() {
+                    if cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    pace(&item);
+                    if cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let slot = slots.acquire();
+                    // * Cancelled while waiting for a slot
+                    if cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    let output = work(item);
+                    drop(slot);
+                    if tx.send(AppMessage::JobOutput(id, output)).is_err() {
+                        break;
+                    }
+                    ctx.request_repaint();
+                }
+            });
+        }
+
+        self.jobs.push(Job {
+            id,
+            kind,
+            label,
+            total,
+            done: 0,
+            failed: 0,
+            state: if total == 0 {
+                JobState::Done
+            } else {
+                JobState::Running
+            },
+            cancelled,
+        });
+
+        id
+    }
+
+    /// Counts an output of job `id`, returning the job once it just finished.
+    pub fn record(&mut self, id: JobId, output: &JobOutput) -> Option<&Job> {
+        let job = self.jobs.iter_mut().find(|job| job.id == id)?;
+        job.done += 1;
+        if output.is_error() {
+            job.failed += 1;
+        }
+
+        if job.is_running() && job.done >= job.total {
+            job.state = JobState::Done;
+            return Some(job);
+        }
+
+        None
+    }
+
+    /// Stops job `id` after the items it is running, whose outputs still come in.
+    pub fn cancel(&mut self, id: JobId) -> Option<&Job> {
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.id == id && job.is_running())?;
+        job.cancelled.store(true, Ordering::Relaxed);
+        job.state = JobState::Cancelled;
+
+        Some(job)
+    }
+
+    pub fn cancel_all(&mut self) {
+        let ids = self
+            .jobs
+            .iter()
+            .filter(|job| job.is_running())
+            .map(|job| job.id)
+            .collect::<Vec<JobId>>();
+        for id in ids {
+            self.cancel(id);
+        }
+    }
+
+    /// The running job of `kind`, if any.
+    pub fn running(&self, kind: JobKind) -> Option<&Job> {
+        self.jobs
+            .iter()
+            .find(|job| job.kind == kind && job.is_running())
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(Job::is_running);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::AtomicUsize, time::Duration};
+
+    use crossbeam::channel::Receiver;
+
+    use super::*;
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn exported(ok: bool) -> JobOutput {
+        JobOutput::Exported(if ok {
+            Ok(PathBuf::from("stash.json"))
+        } else {
+            Err("Failed".to_owned())
+        })
+    }
+
+    /// Outputs of job `id` until none came for a while.
+    fn outputs(rx: &Receiver<AppMessage>, id: JobId) -> Vec<JobOutput> {
+        let mut outputs = vec![];
+        while let Ok(msg) = rx.recv_timeout(Duration::from_millis(300)) {
+            match msg {
+                AppMessage::JobOutput(job_id, output) if job_id == id => outputs.push(output),
+                msg => panic!("Unexpected message: {:?}", msg),
+            }
+        }
+        outputs
+    }
+
+    #[test]
+    fn records_outputs_until_done() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let (tx, rx) = unbounded();
+        let mut jobs = JobManager::new(tx, 2);
+
+        let id = jobs.spawn(
+            &egui::Context::default(),
+            JobKind::Metadata,
+            "Fetch titles".to_owned(),
+            (0..5).collect(),
+            |item: usize| exported(item < 3),
+        );
+        assert!(jobs.running(JobKind::Metadata).is_some());
+
+        let outputs = outputs(&rx, id);
+        assert_eq!(outputs.len(), 5);
+        for output in &outputs[..4] {
+            assert!(jobs.record(id, output).is_none());
+        }
+        let job = jobs.record(id, &outputs[4]).unwrap();
+        assert_eq!((job.done, job.failed, job.state), (5, 2, JobState::Done));
+        assert_eq!(job.progress(), 1.);
+        assert!(jobs.running(JobKind::Metadata).is_none());
+
+        // * Nothing to do is done right away
+        jobs.spawn(
+            &egui::Context::default(),
+            JobKind::Export,
+            "Export".to_owned(),
+            vec![],
+            |_: ()| exported(true),
+        );
+        assert!(jobs.jobs().iter().all(|job| !job.is_running()));
+        jobs.clear_finished();
+        assert!(jobs.jobs().is_empty());
+    }
+
+    #[test]
+    fn cancels_after_the_running_items() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let (tx, rx) = unbounded();
+        let mut jobs = JobManager::new(tx, 4);
+
+        let (started_tx, started_rx) = unbounded();
+        let (gate_tx, gate_rx) = unbounded::<()>();
+        let id = jobs.spawn(
+            &egui::Context::default(),
+            JobKind::Import,
+            "Import".to_owned(),
+            vec![1, 2, 3],
+            move |_: usize| {
+                started_tx.send(()).unwrap();
+                gate_rx.recv().unwrap();
+                exported(true)
+            },
+        );
+        started_rx.recv_timeout(WAIT).unwrap();
+
+        assert_eq!(jobs.cancel(id).unwrap().state, JobState::Cancelled);
+        assert!(jobs.cancel(id).is_none());
+        for _ in 0..3 {
+            gate_tx.send(()).unwrap();
+        }
+
+        // * The item it was running still comes in, but doesn't finish the job again
+        let outputs = outputs(&rx, id);
+        assert_eq!(outputs.len(), 1);
+        assert!(jobs.record(id, &outputs[0]).is_none());
+        assert_eq!(jobs.jobs()[0].done, 1);
+        assert!(started_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn runs_no_more_items_than_the_slots() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let (tx, rx) = unbounded();
+        let mut jobs = JobManager::new(tx, 2);
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let work = {
+            let (running, most) = (running.clone(), most.clone());
+            move |_: usize| {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+                exported(true)
+            }
+        };
+        let ctx = egui::Context::default();
+        let first = jobs.spawn(
+            &ctx,
+            JobKind::LinkCheck,
+            "Check".to_owned(),
+            (0..6).collect(),
+            work.clone(),
+        );
+        let second = jobs.spawn(
+            &ctx,
+            JobKind::Resolve,
+            "Resolve".to_owned(),
+            (0..6).collect(),
+            work,
+        );
+
+        let mut outputs = vec![];
+        while let Ok(AppMessage::JobOutput(id, output)) =
+            rx.recv_timeout(Duration::from_millis(300))
+        {
+            outputs.push((id, output));
+        }
+        assert_eq!(outputs.iter().filter(|(id, _)| *id == first).count(), 6);
+        assert_eq!(outputs.iter().filter(|(id, _)| *id == second).count(), 6);
+        assert!(most.load(Ordering::SeqCst) <= 2);
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn tells_outputs_that_change_bookmarks() {
+        assert!(!exported(true).changes_bookmarks());
+        assert!(!JobOutput::Imported(Ok(vec![])).changes_bookmarks());
+        assert!(JobOutput::Archived(ArchiveResult {
+            topic: "Rust".to_owned(),
+            url: "https://a.test/".to_owned(),
+            error: None,
+        })
+        .changes_bookmarks());
+        assert!(!JobOutput::Fetched {
+            topic: "Rust".to_owned(),
+            url: "https://a.test/".to_owned(),
+            meta: Err("Timed out".to_owned()),
+        }
+        .changes_bookmarks());
+    }
+}
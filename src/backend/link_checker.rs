@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use url::Url;
+
+use super::page_meta::FETCH_TIMEOUT;
+
+/// Requests to the same host are spaced by at least this much.
+const HOST_INTERVAL: Duration = Duration::from_millis(1000);
+const MAX_REDIRECTS: usize = 10;
+/// Hosts of URL shorteners, whose links say nothing about where they go.
+const SHORTENERS: [&str; 22] = [
+    "bit.ly",
+    "t.co",
+    "tinyurl.com",
+    "goo.gl",
+    "ow.ly",
+    "buff.ly",
+    "is.gd",
+    "lnkd.in",
+    "rb.gy",
+    "t.ly",
+    "shorturl.at",
+    "cutt.ly",
+    "tiny.cc",
+    "bl.ink",
+    "amzn.to",
+    "fb.me",
+    "youtu.be",
+    "dlvr.it",
+    "trib.al",
+    "s.id",
+    "v.gd",
+    "qr.ae",
+];
+
+/// Result of checking one link, to be recorded on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkCheck {
+    pub topic: String,
+    pub url: String,
+    pub status: Option<u16>,
+    pub redirect: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Checks links politely, spacing the requests to each host. Shared by the workers of a job.
+#[derive(Debug)]
+pub struct LinkChecker {
+    agent: ureq::Agent,
+    /// When each host may be asked next
+    next_slots: Mutex<HashMap<String, Instant>>,
+}
+
+impl Default for LinkChecker {
+    fn default() -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(FETCH_TIMEOUT)
+                .redirects(0)
+                .build(),
+            next_slots: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl LinkChecker {
+    /// Waits until the host of `url` may be asked again and claims that turn.
+    pub fn wait_for_host(&self, url: &str) {
+        // * Claim the next free slot of the host, then wait for it
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_owned))
+            .unwrap_or_default();
+        let wait = {
+            let mut next_slots = self.next_slots.lock().expect("Failed to lock host slots");
+            let now = Instant::now();
+            let slot = next_slots.get(&host).copied().unwrap_or(now).max(now);
+            next_slots.insert(host, slot + HOST_INTERVAL);
+            slot - now
+        };
+        std::thread::sleep(wait);
+    }
+
+    /// Checks `url` of `topic`, after [`Self::wait_for_host`]. Blocking.
+    pub fn check(&self, topic: String, url: String) -> LinkCheck {
+        check_url(&self.agent, topic, url)
+    }
+}
+
+/// Whether `url` is on a known URL shortener or is a `go/` link, which only make sense once
+/// they are resolved.
+pub fn is_short_link(url: &str) -> bool {
+    let Some(host) = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+    else {
+        return false;
+    };
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+
+    SHORTENERS.contains(&host) || host == "go" || host.starts_with("go.")
+}
+
+/// Follows the redirects of `url` one by one to report where it ends up. Blocking.
+fn check_url(agent: &ureq::Agent, topic: String, url: String) -> LinkCheck {
+    let mut check = LinkCheck {
+        topic,
+        url: url.clone(),
+        status: None,
+        redirect: None,
+        error: None,
+    };
+
+    let mut current = url;
+    for _ in 0..=MAX_REDIRECTS {
+        let (status, location) = match request(agent, &current) {
+            Ok(answer) => answer,
+            Err(e) => {
+                check.error = Some(e);
+                return check;
+            }
+        };
+
+        let next = location
+            .filter(|_| (300..400).contains(&status))
+            .and_then(|location| Url::parse(&current).ok()?.join(&location).ok());
+        match next {
+            Some(next) => current = next.to_string(),
+            None => {
+                check.status = Some(status);
+                if current != check.url {
+                    check.redirect = Some(current);
+                }
+                return check;
+            }
+        }
+    }
+
+    check.error = Some("too many redirects".to_owned());
+    check
+}
+
+/// Status and `Location` of `url`. Asks with `HEAD` first and retries with `GET`, as some
+/// servers refuse or mishandle `HEAD`.
+fn request(agent: &ureq::Agent, url: &str) -> Result<(u16, Option<String>), String> {
+    let send = |method: &str| match agent
+        .request(method, url)
+        .set("User-Agent", concat!("stash/", env!("CARGO_PKG_VERSION")))
+        .call()
+    {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => Ok((
+            response.status(),
+            response.header("location").map(str::to_owned),
+        )),
+        Err(e) => Err(e.to_string()),
+    };
+
+    match send("HEAD") {
+        Ok((status, _)) if status >= 400 => send("GET"),
+        Err(_) => send("GET"),
+        answer => answer,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_server::{Response, TestServer};
+
+    fn check(url: String) -> LinkCheck {
+        LinkChecker::default().check("t".to_owned(), url)
+    }
+
+    #[test]
+    fn follows_redirects_up_to_the_limit() {
+        let server = TestServer::start(|_, path| match path.strip_prefix("/hop/") {
+            Some(n) => match n.parse::<usize>().unwrap() {
+                0 => Response::ok("text/plain", "here"),
+                n => Response::redirect(&format!("/hop/{}", n - 1)),
+            },
+            None => Response::status(404),
+        });
+
+        let within = check(server.url(&format!("/hop/{}", MAX_REDIRECTS)));
+        assert_eq!(within.status, Some(200));
+        assert_eq!(within.redirect, Some(server.url("/hop/0")));
+        assert_eq!(within.error, None);
+
+        let beyond = check(server.url(&format!("/hop/{}", MAX_REDIRECTS + 1)));
+        assert_eq!(beyond.status, None);
+        assert_eq!(beyond.error, Some("too many redirects".to_owned()));
+
+        let missing = check(server.url("/gone"));
+        assert_eq!(missing.status, Some(404));
+        assert_eq!(missing.redirect, None);
+    }
+
+    #[test]
+    fn falls_back_to_get() {
+        let server = TestServer::start(|method, _| match method {
+            "HEAD" => Response::status(405),
+            _ => Response::ok("text/plain", "fine"),
+        });
+
+        let result = check(server.url("/page"));
+        assert_eq!(result.status, Some(200));
+        assert_eq!(
+            server.requests(),
+            vec![
+                ("HEAD".to_owned(), "/page".to_owned()),
+                ("GET".to_owned(), "/page".to_owned())
+            ]
+        );
+
+        // * Answered by HEAD, no GET needed
+        let server = TestServer::start(|_, _| Response::ok("text/plain", "fine"));
+        assert_eq!(check(server.url("/page")).status, Some(200));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn spaces_requests_to_a_host() {
+        let checker = LinkChecker::default();
+
+        let start = Instant::now();
+        for path in ["/a", "/b", "/c"] {
+            checker.wait_for_host(&format!("http://a.test{}", path));
+        }
+        assert!(start.elapsed() >= HOST_INTERVAL * 2);
+
+        // * Other hosts have turns of their own
+        let start = Instant::now();
+        checker.wait_for_host("http://b.test/");
+        checker.wait_for_host("http://c.test/");
+        assert!(start.elapsed() < HOST_INTERVAL);
+    }
+}
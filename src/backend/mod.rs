@@ -1,2 +1,21 @@
+pub mod archive;
 pub mod bookmark_manager;
-pub mod models;
\ No newline at end of file
+pub mod cache;
+#[cfg(target_os = "linux")]
+pub mod dbus;
+pub mod favicons;
+pub mod file_writer;
+pub mod history;
+pub mod jobs;
+pub mod link_checker;
+pub mod models;
+pub mod page_meta;
+pub mod query;
+pub mod readability;
+pub mod reader;
+pub mod search;
+pub mod settings;
+#[cfg(test)]
+mod test_server;
+pub mod text_index;
+pub mod url_rules;
@@ -1,4 +1,7 @@
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::utils::normalize_url;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TopicModel {
@@ -16,33 +19,251 @@ impl TopicModel {
     }
 }
 
+/// A saved search whose links are computed from its query.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SmartTopicModel {
+    pub name: String,
+    pub query: String,
+}
+
+impl SmartTopicModel {
+    pub fn new(name: String, query: String) -> Self {
+        Self { name, query }
+    }
+}
+
+/// What a page says about itself, shown under its link.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkPreview {
+    pub description: Option<String>,
+    /// Absolute URL of the image, cached on disk by `backend::cache`
+    pub image: Option<String>,
+    pub site_name: Option<String>,
+}
+
+impl LinkPreview {
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none() && self.image.is_none() && self.site_name.is_none()
+    }
+}
+
+/// Older files stored the preview as plain text, it is kept as the description.
+fn deserialize_preview<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Box<LinkPreview>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredPreview {
+        Text(String),
+        Preview(LinkPreview),
+    }
+
+    Ok(
+        Option::<StoredPreview>::deserialize(deserializer)?.map(|preview| match preview {
+            StoredPreview::Text(text) => Box::new(LinkPreview {
+                description: Some(text),
+                ..Default::default()
+            }),
+            StoredPreview::Preview(preview) => Box::new(preview),
+        }),
+    )
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct LinkModel {
     pub title: String,
     pub url: String,
-    pub preview: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_preview")]
+    pub preview: Option<Box<LinkPreview>>,
+
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub last_opened: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub health: Option<Box<LinkHealth>>,
+    /// When a copy of the page was last saved under `archive/<link-id>`
+    #[serde(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+    /// Where the URL ends up after its redirects, when that is somewhere else
+    #[serde(default)]
+    pub resolved_url: Option<String>,
+    /// When the link was marked as read, `None` while unread
+    #[serde(default)]
+    pub read_at: Option<DateTime<Utc>>,
 }
 
 impl LinkModel {
-    pub fn new(title: String, url: String, preview: Option<String>) -> Self {
+    pub fn new(title: String, url: String, preview: Option<LinkPreview>) -> Self {
         Self {
             title,
             url,
-            preview,
+            preview: preview.map(Box::new),
+            ..Default::default()
         }
     }
 
-    // pub fn fetch_link(url: &str, ctx: &Context) -> Self {
-    //     let request = Request::get(url);
-    //     fetch(request, move |result: Result<Response>| {
-    //         if let Ok(response) = result {
-    //             let title = response.t
-    //             let preview = response.text().unwrap_or_default();
-    //             Self::new(title, url.to_string(), Some(preview))
-    //         } else {
-    //             Self::new(url.to_string(), url.to_string(), None)
-    //         }
-    //         ctx.request_repaint();
-    //     });
-    // }
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.read_at.is_some()
+    }
+
+    pub fn same_url(&self, url: &str) -> bool {
+        normalize_url(&self.url) == normalize_url(url)
+    }
+
+    /// Whether the title is missing or just the URL, as for links added from the CLI.
+    pub fn has_placeholder_title(&self) -> bool {
+        self.title.trim().is_empty() || self.same_url(self.title.trim())
+    }
+
+    /// Folds duplicates of the same page into the first one: the most descriptive title,
+    /// the longest notes, every tag and the latest open and read are kept.
+    pub fn merge(links: &[LinkModel]) -> Self {
+        let mut merged = links.first().cloned().unwrap_or_default();
+        for link in links.iter().skip(1) {
+            // * Links added from the CLI are titled with their URL
+            let is_better_title = |title: &str, than: &str| {
+                let is_url = |t: &str| t.is_empty() || normalize_url(t) == normalize_url(&link.url);
+                (is_url(than) && !is_url(title)) || (!is_url(title) && title.len() > than.len())
+            };
+            if is_better_title(&link.title, &merged.title) {
+                merged.title = link.title.clone();
+            }
+            if link.notes.trim().len() > merged.notes.trim().len() {
+                merged.notes = link.notes.clone();
+            }
+            for tag in &link.tags {
+                if !merged.has_tag(tag) {
+                    merged.tags.push(tag.clone());
+                }
+            }
+            merged.last_opened = merged.last_opened.max(link.last_opened);
+            merged.read_at = merged.read_at.max(link.read_at);
+            if merged.preview.is_none() {
+                merged.preview = link.preview.clone();
+            }
+        }
+
+        merged
+    }
+}
+
+/// What the last check of a link's URL found.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LinkHealth {
+    /// Final HTTP status, `None` when the site could not be reached
+    pub status: Option<u16>,
+    /// Where the URL redirects to, when it does
+    pub redirect: Option<String>,
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+    /// Checks in a row that found the link broken
+    pub failures: u32,
+}
+
+impl LinkHealth {
+    pub fn is_broken(&self) -> bool {
+        match self.status {
+            Some(status) => self.error.is_some() || status >= 400,
+            None => true,
+        }
+    }
+
+    pub fn is_redirected(&self) -> bool {
+        !self.is_broken() && self.redirect.is_some()
+    }
+}
+
+/// What was deleted and where it was, so it can be put back in place.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TrashedItem {
+    Topic {
+        topic: TopicModel,
+        index: usize,
+        links: Vec<LinkModel>,
+    },
+    Link {
+        topic: String,
+        index: usize,
+        link: LinkModel,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub item: TrashedItem,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl TrashEntry {
+    pub fn new(item: TrashedItem) -> Self {
+        Self {
+            item,
+            deleted_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn link(title: &str) -> LinkModel {
+        LinkModel::new(title.to_owned(), "https://a.test/page".to_owned(), None)
+    }
+
+    #[test]
+    fn merge_keeps_the_most_descriptive_title() {
+        let merged = LinkModel::merge(&[link("https://a.test/page"), link("Page")]);
+        assert_eq!(merged.title, "Page");
+
+        // * A URL title never wins, even over a shorter one
+        let merged = LinkModel::merge(&[link("Page"), link("https://www.a.test/page/")]);
+        assert_eq!(merged.title, "Page");
+
+        let merged = LinkModel::merge(&[link("Page"), link("A longer page title"), link("")]);
+        assert_eq!(merged.title, "A longer page title");
+    }
+
+    #[test]
+    fn merge_keeps_notes_tags_and_latest_dates() {
+        let now = Utc::now();
+        let mut first = link("First");
+        first.notes = "short".to_owned();
+        first.tags = vec!["Rust".to_owned()];
+        first.last_opened = Some(now - Duration::days(3));
+        let mut second = link("Second");
+        second.notes = "  the longer notes  ".to_owned();
+        second.tags = vec!["rust".to_owned(), "Docs".to_owned()];
+        second.read_at = Some(now - Duration::days(1));
+        second.preview = Some(Box::new(LinkPreview {
+            description: Some("About".to_owned()),
+            ..Default::default()
+        }));
+        let mut third = link("Third");
+        third.last_opened = Some(now);
+        third.read_at = Some(now - Duration::days(2));
+
+        let merged = LinkModel::merge(&[first.clone(), second.clone(), third]);
+        assert_eq!(merged.url, first.url);
+        assert_eq!(merged.title, "Second");
+        assert_eq!(merged.notes, second.notes);
+        assert_eq!(merged.tags, vec!["Rust".to_owned(), "Docs".to_owned()]);
+        assert_eq!(merged.last_opened, Some(now));
+        assert_eq!(merged.read_at, second.read_at);
+        assert_eq!(merged.preview, second.preview);
+
+        // * Unread stays unread only while every copy is
+        assert_eq!(LinkModel::merge(&[first.clone(), first]).read_at, None);
+        assert_eq!(LinkModel::merge(&[]), LinkModel::default());
+    }
 }
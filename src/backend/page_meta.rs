@@ -0,0 +1,442 @@
+use std::{io::Read, time::Duration};
+
+use url::Url;
+
+use super::models::LinkPreview;
+
+/// How long a page may take to answer before the fetch is given up.
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Only the start of a page is read, metadata lives in its `<head>`.
+const MAX_PAGE_BYTES: u64 = 512 * 1024;
+
+/// Metadata read from the `<head>` of a web page.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PageMeta {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Image URL as written in the page, possibly relative
+    pub image: Option<String>,
+    pub site_name: Option<String>,
+    /// JSON oEmbed endpoint advertised by the page
+    pub oembed: Option<String>,
+    /// Icons from `<link rel=icon>`, small ones first
+    pub icons: Vec<String>,
+    /// Web app manifest, which may list more icons
+    pub manifest: Option<String>,
+}
+
+impl PageMeta {
+    /// Downloads the start of the page at `url`, and its oEmbed data when the page itself
+    /// lacks a title or an image. Blocking, run it off the UI thread.
+    pub fn fetch(url: &str) -> Result<Self, String> {
+        let agent = ureq::AgentBuilder::new().timeout(FETCH_TIMEOUT).build();
+        Self::fetch_with(&agent, url)
+    }
+
+    fn fetch_with(agent: &ureq::Agent, url: &str) -> Result<Self, String> {
+        let response = agent
+            .get(url)
+            .set("User-Agent", concat!("stash/", env!("CARGO_PKG_VERSION")))
+            .set("Accept", "text/html,application/xhtml+xml")
+            .call()
+            .map_err(|e| e.to_string())?;
+        // * Relative image URLs resolve against where redirects ended up
+        let base = Url::parse(response.get_url()).map_err(|e| e.to_string())?;
+
+        let mut body = vec![];
+        response
+            .into_reader()
+            .take(MAX_PAGE_BYTES)
+            .read_to_end(&mut body)
+            .map_err(|e| e.to_string())?;
+
+        let mut meta = Self::parse(&String::from_utf8_lossy(&body));
+        if meta.title.is_none() || meta.image.is_none() {
+            if let Some(oembed) = meta.oembed.as_deref().and_then(|o| base.join(o).ok()) {
+                let json = agent
+                    .get(oembed.as_str())
+                    .call()
+                    .map_err(|e| e.to_string())
+                    .and_then(|response| response.into_string().map_err(|e| e.to_string()))
+                    .and_then(|body| {
+                        serde_json::from_str::<serde_json::Value>(&body).map_err(|e| e.to_string())
+                    });
+                match json {
+                    Ok(json) => meta.fill_from_oembed(&json),
+                    Err(e) => eprintln!("Failed to fetch oEmbed of {}: {}", url, e),
+                }
+            }
+        }
+        let resolve = |href: &str| base.join(href).ok().map(|url| url.to_string());
+        meta.image = meta.image.as_deref().and_then(resolve);
+        meta.icons = meta.icons.iter().filter_map(|icon| resolve(icon)).collect();
+        meta.manifest = meta.manifest.as_deref().and_then(resolve);
+
+        Ok(meta)
+    }
+
+    /// The description, absolute image URL and site name, if the page had any.
+    pub fn preview(&self) -> Option<LinkPreview> {
+        let preview = LinkPreview {
+            description: self.description.clone(),
+            image: self.image.clone(),
+            site_name: self.site_name.clone(),
+        };
+
+        (!preview.is_empty()).then_some(preview)
+    }
+
+    fn fill_from_oembed(&mut self, json: &serde_json::Value) {
+        let field = |key: &str| {
+            json.get(key)
+                .and_then(|value| value.as_str())
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty())
+        };
+
+        self.title = self.title.take().or_else(|| field("title"));
+        self.image = self.image.take().or_else(|| field("thumbnail_url"));
+        self.site_name = self.site_name.take().or_else(|| field("provider_name"));
+        self.description = self
+            .description
+            .take()
+            .or_else(|| field("author_name").map(|author| format!("By {}", author)));
+    }
+
+    /// Prefers OpenGraph, then Twitter cards, then the plain `<title>` and description.
+    pub fn parse(html: &str) -> Self {
+        let head = match find_ignore_case(html, "</head") {
+            Some(end) => &html[..end],
+            None => html,
+        };
+
+        let metas = meta_tags(head);
+        let meta = |key: &str| {
+            metas
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, content)| content.clone())
+                .filter(|content| !content.is_empty())
+        };
+
+        let title = meta("og:title")
+            .or_else(|| meta("twitter:title"))
+            .or_else(|| title_tag(head));
+        let description = meta("og:description")
+            .or_else(|| meta("twitter:description"))
+            .or_else(|| meta("description"));
+        let image = meta("og:image")
+            .or_else(|| meta("og:image:url"))
+            .or_else(|| meta("twitter:image"))
+            .or_else(|| meta("twitter:image:src"));
+        let site_name = meta("og:site_name").or_else(|| meta("application-name"));
+
+        let links = tags(head, "<link");
+        let oembed = links.iter().find_map(|attributes| {
+            let attribute = |key: &str| attribute(attributes, key);
+            (attribute("type")?.eq_ignore_ascii_case("application/json+oembed"))
+                .then(|| attribute("href"))
+                .flatten()
+        });
+        let rel_hrefs = |wanted: &[&str]| {
+            links
+                .iter()
+                .filter(|attributes| {
+                    attribute(attributes, "rel").is_some_and(|rel| {
+                        rel.split_whitespace()
+                            .any(|r| wanted.iter().any(|w| r.eq_ignore_ascii_case(w)))
+                    })
+                })
+                .filter_map(|attributes| attribute(attributes, "href"))
+                .collect::<Vec<String>>()
+        };
+        let mut icons = rel_hrefs(&["icon"]);
+        icons.extend(rel_hrefs(&[
+            "apple-touch-icon",
+            "apple-touch-icon-precomposed",
+        ]));
+        let manifest = rel_hrefs(&["manifest"]).into_iter().next();
+
+        Self {
+            title,
+            description,
+            image,
+            site_name,
+            oembed,
+            icons,
+            manifest,
+        }
+    }
+}
+
+/// `(property or name, content)` of every `<meta>` tag.
+fn meta_tags(html: &str) -> Vec<(String, String)> {
+    tags(html, "<meta")
+        .into_iter()
+        .filter_map(|attributes| {
+            let name =
+                attribute(&attributes, "property").or_else(|| attribute(&attributes, "name"))?;
+            let content = attribute(&attributes, "content")?;
+            Some((name, content))
+        })
+        .collect()
+}
+
+/// Attributes of every tag opened with `open`, like `<meta`.
+fn tags(html: &str, open: &str) -> Vec<Vec<(String, String)>> {
+    let mut tags = vec![];
+
+    let mut rest = html;
+    while let Some(start) = find_ignore_case(rest, open) {
+        let tag = &rest[start + open.len()..];
+        let end = tag.find('>').unwrap_or(tag.len());
+        tags.push(attributes(&tag[..end]));
+        rest = &tag[end..];
+    }
+
+    tags
+}
+
+/// Decoded value of the attribute `key`.
+pub(super) fn attribute(attributes: &[(String, String)], key: &str) -> Option<String> {
+    attributes
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| decode_entities(value.trim()))
+}
+
+/// Attributes of a tag body like ` property="og:title" content='Stash'`.
+pub(super) fn attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = vec![];
+
+    let mut chars = tag.trim_end_matches('/').chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let name = std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace() && *c != '='))
+            .collect::<String>();
+        if name.is_empty() {
+            break;
+        }
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.next_if_eq(&'=').is_none() {
+            attributes.push((name, String::new()));
+            continue;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let value = match chars.next_if(|c| *c == '"' || *c == '\'') {
+            Some(quote) => {
+                let value =
+                    std::iter::from_fn(|| chars.next_if(|c| *c != quote)).collect::<String>();
+                chars.next();
+                value
+            }
+            None => {
+                std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace())).collect::<String>()
+            }
+        };
+        attributes.push((name, value));
+    }
+
+    attributes
+}
+
+fn title_tag(html: &str) -> Option<String> {
+    let start = find_ignore_case(html, "<title")?;
+    let content = &html[start..];
+    let content = &content[content.find('>')? + 1..];
+    let end = find_ignore_case(content, "</title").unwrap_or(content.len());
+
+    let title = content[..end]
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let title = decode_entities(&title);
+
+    (!title.is_empty()).then_some(title)
+}
+
+/// Decodes the named entities common in titles and every numeric one.
+pub(super) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let c = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => match entity.strip_prefix('#') {
+                Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok(),
+                Some(dec) => dec.parse().ok(),
+                None => None,
+            }
+            .and_then(char::from_u32),
+        });
+
+        match (entity, c) {
+            (Some(entity), Some(c)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+/// Byte index of `needle` in `haystack`, ignoring ASCII case.
+pub(super) fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_server::{Response, TestServer};
+
+    #[test]
+    fn prefers_opengraph_then_twitter_then_title() {
+        let head = |metas: &str| {
+            format!(
+                "<html><head>{}<title>\n  Plain   &amp; simple </title></head>\
+                 <body><meta property=\"og:title\" content=\"In the body\"></body></html>",
+                metas
+            )
+        };
+
+        let all = head(
+            r#"<meta name="twitter:title" content="Twitter"><meta property="og:title" content="OpenGraph">"#,
+        );
+        assert_eq!(PageMeta::parse(&all).title.as_deref(), Some("OpenGraph"));
+
+        let twitter = head(
+            r#"<meta property="og:title" content=""><META NAME='twitter:title' CONTENT='Twitter'>"#,
+        );
+        assert_eq!(PageMeta::parse(&twitter).title.as_deref(), Some("Twitter"));
+
+        assert_eq!(
+            PageMeta::parse(&head("")).title.as_deref(),
+            Some("Plain & simple")
+        );
+        assert_eq!(PageMeta::parse("<p>No head</p>").title, None);
+    }
+
+    #[test]
+    fn reads_description_image_and_links() {
+        let meta = PageMeta::parse(
+            r#"<head>
+                <meta name="description" content="Plain">
+                <meta name="twitter:description" content="Card">
+                <meta property="og:image:url" content="/og.png">
+                <meta name=application-name content=Stash>
+                <link rel="apple-touch-icon" href="/touch.png">
+                <link rel="shortcut icon" href="/favicon.png" />
+                <link rel="manifest" href="/site.webmanifest">
+                <link type="application/json+oembed" href="/oembed?url=x">
+            </head>"#,
+        );
+        assert_eq!(meta.description.as_deref(), Some("Card"));
+        assert_eq!(meta.image.as_deref(), Some("/og.png"));
+        assert_eq!(meta.site_name.as_deref(), Some("Stash"));
+        assert_eq!(meta.icons, vec!["/favicon.png", "/touch.png"]);
+        assert_eq!(meta.manifest.as_deref(), Some("/site.webmanifest"));
+        assert_eq!(meta.oembed.as_deref(), Some("/oembed?url=x"));
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(
+            decode_entities("a &amp; b &lt;c&gt; &quot;d&quot; &apos;e&apos;&nbsp;f"),
+            "a & b <c> \"d\" 'e' f"
+        );
+        assert_eq!(decode_entities("&#169; &#x2014; &#X41;"), "© — A");
+        assert_eq!(
+            decode_entities("AT&T &unknown; &#xzz; & &averyverylongname;"),
+            "AT&T &unknown; &#xzz; & &averyverylongname;"
+        );
+    }
+
+    #[test]
+    fn parses_attributes() {
+        assert_eq!(
+            attributes(r#" a="1 2" b='3' c=4 d e = "5" /"#),
+            vec![
+                ("a".to_owned(), "1 2".to_owned()),
+                ("b".to_owned(), "3".to_owned()),
+                ("c".to_owned(), "4".to_owned()),
+                ("d".to_owned(), String::new()),
+                ("e".to_owned(), "5".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_links_against_the_redirected_url() {
+        let server = TestServer::start(|_, path| match path {
+            "/old" => Response::redirect("/new/page"),
+            "/new/page" => Response::ok(
+                "text/html",
+                r#"<head>
+                    <meta property="og:image" content="img/cover.png">
+                    <link rel="icon" href="../favicon.ico">
+                    <link type="application/json+oembed" href="oembed.json">
+                </head>"#,
+            ),
+            "/new/oembed.json" => Response::ok(
+                "application/json",
+                r#"{"title": "From oEmbed", "provider_name": "Site", "author_name": "Ada"}"#,
+            ),
+            _ => Response::status(404),
+        });
+
+        let meta = PageMeta::fetch(&server.url("/old")).unwrap();
+        assert_eq!(meta.image, Some(server.url("/new/img/cover.png")));
+        assert_eq!(meta.icons, vec![server.url("/favicon.ico")]);
+        assert_eq!(meta.title.as_deref(), Some("From oEmbed"));
+        assert_eq!(meta.site_name.as_deref(), Some("Site"));
+        assert_eq!(meta.description.as_deref(), Some("By Ada"));
+        assert_eq!(
+            meta.preview().and_then(|preview| preview.image),
+            Some(server.url("/new/img/cover.png"))
+        );
+
+        assert!(PageMeta::fetch(&server.url("/missing")).is_err());
+    }
+
+    #[test]
+    fn gives_up_on_slow_pages() {
+        let server = TestServer::start(|_, _| {
+            std::thread::sleep(Duration::from_secs(2));
+            Response::ok("text/html", "<title>Late</title>")
+        });
+
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_millis(200))
+            .build();
+        let start = std::time::Instant::now();
+        assert!(PageMeta::fetch_with(&agent, &server.url("/")).is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,546 @@
+use std::{collections::HashMap, fmt};
+
+use chrono::{DateTime, Duration, Utc};
+use url::Url;
+
+use super::models::{LinkModel, TopicModel};
+use crate::utils::fuzzy::fuzzy_match;
+
+/// Longest age `opened:` takes, a thousand years.
+const MAX_AGE_DAYS: i64 = 1000 * 365;
+
+/// A parsed search query such as `topic:rust tag:todo site:github.com opened:>30d is:unread
+/// -title:draft`.
+///
+/// Terms are joined with an implicit AND, `OR` binds looser than AND, `-` negates a term
+/// and parentheses group terms. Words without a known `field:` prefix are fuzzy-matched
+/// against the topic name, title, URL and tags.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub filter: Filter,
+}
+
+/// For each free text term, the URLs of the archived pages that have it and how often, as
+/// found by the text index.
+pub type PageMatches = HashMap<String, HashMap<String, u32>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    All,
+    Text(String),
+    Topic(String),
+    Tag(String),
+    Site(String),
+    Title(String),
+    Url(String),
+    Opened(Comparison, Duration),
+    /// `is:read` or `is:unread`
+    Read(bool),
+    Not(Box<Filter>),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    /// Opened longer ago than the duration, never opened links included
+    OlderThan,
+    /// Opened within the duration
+    NewerThan,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryError {
+    EmptyValue(String),
+    InvalidDuration(String),
+    UnbalancedParentheses,
+    DanglingOperator(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::EmptyValue(field) => write!(f, "`{}:` needs a value", field),
+            QueryError::InvalidDuration(value) => write!(
+                f,
+                "Invalid duration `{}`, expected something like `>30d` or `<2w`",
+                value
+            ),
+            QueryError::UnbalancedParentheses => write!(f, "Unbalanced parentheses"),
+            QueryError::DanglingOperator(op) => write!(f, "`{}` needs a term after it", op),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Term(String),
+    Negate,
+    Or,
+    LParen,
+    RParen,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let tokens = tokenize(input);
+        let mut parser = Parser { tokens, pos: 0 };
+
+        let filter = parser.parse_or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(QueryError::UnbalancedParentheses);
+        }
+
+        Ok(Self { filter })
+    }
+
+    /// Falls back to fuzzy-matching the whole input when it does not parse.
+    pub fn parse_lenient(input: &str) -> Self {
+        Self::parse(input).unwrap_or_else(|_| Self {
+            filter: Filter::Text(input.to_owned()),
+        })
+    }
+
+    pub fn matches(
+        &self,
+        topic: &TopicModel,
+        link: &LinkModel,
+        now: DateTime<Utc>,
+        pages: &PageMatches,
+    ) -> bool {
+        self.filter.matches(topic, link, now, pages)
+    }
+
+    /// Free text terms that are not negated, used to rank and highlight results.
+    pub fn text_terms(&self) -> Vec<String> {
+        let mut terms = vec![];
+        self.filter.collect_text(&mut terms, false);
+
+        terms
+    }
+
+    /// Every free text term, negated ones included, to look up in the text index.
+    pub fn all_text_terms(&self) -> Vec<String> {
+        let mut terms = vec![];
+        self.filter.collect_text(&mut terms, true);
+
+        terms
+    }
+}
+
+impl Filter {
+    pub fn matches(
+        &self,
+        topic: &TopicModel,
+        link: &LinkModel,
+        now: DateTime<Utc>,
+        pages: &PageMatches,
+    ) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Text(text) => {
+                fuzzy_match(text, &topic.name).is_some()
+                    || fuzzy_match(text, &link.title).is_some()
+                    || fuzzy_match(text, &link.url).is_some()
+                    || link.tags.iter().any(|tag| fuzzy_match(text, tag).is_some())
+                    || pages
+                        .get(text)
+                        .is_some_and(|urls| urls.contains_key(&link.url))
+            }
+            Filter::Topic(name) => contains_ignore_case(&topic.name, name),
+            Filter::Tag(tag) => link.has_tag(tag),
+            Filter::Site(site) => Url::parse(&link.url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_lowercase))
+                .is_some_and(|host| {
+                    let site = site.to_lowercase();
+                    host == site || host.ends_with(&format!(".{}", site))
+                }),
+            Filter::Title(title) => contains_ignore_case(&link.title, title),
+            Filter::Url(url) => contains_ignore_case(&link.url, url),
+            Filter::Opened(comparison, duration) => {
+                // * A cutoff before the earliest date nothing can be opened before
+                let cutoff = now.checked_sub_signed(*duration);
+                match (comparison, link.last_opened) {
+                    (Comparison::OlderThan, Some(opened)) => cutoff.is_some_and(|c| opened < c),
+                    (Comparison::OlderThan, None) => true,
+                    (Comparison::NewerThan, Some(opened)) => cutoff.is_none_or(|c| opened >= c),
+                    (Comparison::NewerThan, None) => false,
+                }
+            }
+            Filter::Read(read) => link.is_read() == *read,
+            Filter::Not(filter) => !filter.matches(topic, link, now, pages),
+            Filter::And(filters) => filters.iter().all(|f| f.matches(topic, link, now, pages)),
+            Filter::Or(filters) => filters.iter().any(|f| f.matches(topic, link, now, pages)),
+        }
+    }
+
+    fn collect_text(&self, terms: &mut Vec<String>, negated: bool) {
+        match self {
+            Filter::Text(text) => terms.push(text.clone()),
+            Filter::Not(filter) if negated => filter.collect_text(terms, negated),
+            Filter::And(filters) | Filter::Or(filters) => {
+                filters.iter().for_each(|f| f.collect_text(terms, negated))
+            }
+            _ => {}
+        }
+    }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Negate);
+            }
+            _ => {
+                // * A term runs until whitespace or a paren, quotes may contain both
+                let mut term = String::new();
+                let mut in_quotes = false;
+                let mut is_quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if c == '"' {
+                        in_quotes = !in_quotes;
+                        is_quoted = true;
+                        chars.next();
+                        continue;
+                    }
+                    if !in_quotes && (c.is_whitespace() || c == '(' || c == ')') {
+                        break;
+                    }
+                    term.push(c);
+                    chars.next();
+                }
+
+                if term == "OR" && !is_quoted {
+                    tokens.push(Token::Or);
+                } else {
+                    tokens.push(Token::Term(term));
+                }
+            }
+        }
+    }
+
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, QueryError> {
+        let mut filters = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            if matches!(self.peek(), None | Some(Token::RParen) | Some(Token::Or)) {
+                return Err(QueryError::DanglingOperator("OR".to_owned()));
+            }
+            filters.push(self.parse_and()?);
+        }
+
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            Filter::Or(filters)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, QueryError> {
+        let mut filters = vec![];
+        while let Some(token) = self.peek() {
+            if matches!(token, Token::Or | Token::RParen) {
+                break;
+            }
+            filters.push(self.parse_unary()?);
+        }
+
+        Ok(match filters.len() {
+            0 => Filter::All,
+            1 => filters.remove(0),
+            _ => Filter::And(filters),
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, QueryError> {
+        match self.tokens.get(self.pos).cloned() {
+            Some(Token::Negate) => {
+                self.pos += 1;
+                if matches!(self.peek(), None | Some(Token::RParen) | Some(Token::Or)) {
+                    return Err(QueryError::DanglingOperator("-".to_owned()));
+                }
+                Ok(Filter::Not(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let filter = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return Err(QueryError::UnbalancedParentheses);
+                }
+                self.pos += 1;
+                Ok(filter)
+            }
+            Some(Token::Term(term)) => {
+                self.pos += 1;
+                parse_term(&term)
+            }
+            Some(Token::RParen) | Some(Token::Or) | None => Err(QueryError::UnbalancedParentheses),
+        }
+    }
+}
+
+fn parse_term(term: &str) -> Result<Filter, QueryError> {
+    let Some((field, value)) = term.split_once(':') else {
+        return Ok(Filter::Text(term.to_owned()));
+    };
+
+    let field = field.to_lowercase();
+    let constructor: fn(String) -> Filter = match field.as_str() {
+        "topic" => Filter::Topic,
+        "tag" => Filter::Tag,
+        "site" => Filter::Site,
+        "title" => Filter::Title,
+        "url" => Filter::Url,
+        "opened" => {
+            if value.is_empty() {
+                return Err(QueryError::EmptyValue(field));
+            }
+            let (comparison, duration) = parse_age(value)?;
+            return Ok(Filter::Opened(comparison, duration));
+        }
+        "is" => {
+            return Ok(match value.to_lowercase().as_str() {
+                "read" => Filter::Read(true),
+                "unread" => Filter::Read(false),
+                _ => Filter::Text(term.to_owned()),
+            });
+        }
+        // * Unknown prefixes such as `https:` are plain text
+        _ => return Ok(Filter::Text(term.to_owned())),
+    };
+
+    if value.is_empty() {
+        return Err(QueryError::EmptyValue(field));
+    }
+
+    Ok(constructor(value.to_owned()))
+}
+
+/// Parses `>30d`, `<2w`, `>6m`, `>1y` or `<12h`. Without a sign, `30d` means `>30d`.
+fn parse_age(value: &str) -> Result<(Comparison, Duration), QueryError> {
+    let invalid = || QueryError::InvalidDuration(value.to_owned());
+
+    let (comparison, rest) = if let Some(rest) = value.strip_prefix('>') {
+        (Comparison::OlderThan, rest)
+    } else if let Some(rest) = value.strip_prefix('<') {
+        (Comparison::NewerThan, rest)
+    } else {
+        (Comparison::OlderThan, value)
+    };
+
+    let unit = rest.chars().last().ok_or_else(invalid)?;
+    let amount = rest[..rest.len() - unit.len_utf8()]
+        .parse::<i64>()
+        .ok()
+        .filter(|amount| *amount >= 0)
+        .ok_or_else(invalid)?;
+    let duration = match unit.to_ascii_lowercase() {
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        'w' => Duration::try_weeks(amount),
+        'm' => amount.checked_mul(30).and_then(Duration::try_days),
+        'y' => amount.checked_mul(365).and_then(Duration::try_days),
+        _ => None,
+    };
+
+    let duration = duration
+        .filter(|duration| *duration <= Duration::days(MAX_AGE_DAYS))
+        .ok_or_else(invalid)?;
+
+    Ok((comparison, duration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(title: &str, url: &str) -> LinkModel {
+        LinkModel::new(title.to_owned(), url.to_owned(), None)
+    }
+
+    fn matches(query: &str, topic: &str, link: &LinkModel) -> bool {
+        Query::parse(query).unwrap().matches(
+            &TopicModel::new(topic.to_owned()),
+            link,
+            Utc::now(),
+            &PageMatches::new(),
+        )
+    }
+
+    #[test]
+    fn tokenizes_quotes_parens_and_operators() {
+        assert_eq!(
+            tokenize(r#"-(title:"two words" OR "OR") x"#),
+            vec![
+                Token::Negate,
+                Token::LParen,
+                Token::Term("title:two words".to_owned()),
+                Token::Or,
+                Token::Term("OR".to_owned()),
+                Token::RParen,
+                Token::Term("x".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let query = Query::parse("a b OR c").unwrap();
+        assert_eq!(
+            query.filter,
+            Filter::Or(vec![
+                Filter::And(vec![
+                    Filter::Text("a".to_owned()),
+                    Filter::Text("b".to_owned())
+                ]),
+                Filter::Text("c".to_owned()),
+            ])
+        );
+
+        let query = Query::parse("a (b OR c)").unwrap();
+        assert_eq!(
+            query.filter,
+            Filter::And(vec![
+                Filter::Text("a".to_owned()),
+                Filter::Or(vec![
+                    Filter::Text("b".to_owned()),
+                    Filter::Text("c".to_owned())
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_fields_and_negation() {
+        let query = Query::parse("-tag:todo site:github.com is:unread https://x").unwrap();
+        assert_eq!(
+            query.filter,
+            Filter::And(vec![
+                Filter::Not(Box::new(Filter::Tag("todo".to_owned()))),
+                Filter::Site("github.com".to_owned()),
+                Filter::Read(false),
+                Filter::Text("https://x".to_owned()),
+            ])
+        );
+        assert_eq!(query.text_terms(), vec!["https://x".to_owned()]);
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(
+            Query::parse("tag:"),
+            Err(QueryError::EmptyValue("tag".to_owned()))
+        );
+        assert_eq!(
+            Query::parse("(a OR b"),
+            Err(QueryError::UnbalancedParentheses)
+        );
+        assert_eq!(Query::parse("a)"), Err(QueryError::UnbalancedParentheses));
+        assert_eq!(
+            Query::parse("a OR"),
+            Err(QueryError::DanglingOperator("OR".to_owned()))
+        );
+        assert_eq!(
+            Query::parse("a -"),
+            Err(QueryError::DanglingOperator("-".to_owned()))
+        );
+        assert_eq!(
+            Query::parse("opened:>3x"),
+            Err(QueryError::InvalidDuration(">3x".to_owned()))
+        );
+        assert_eq!(
+            Query::parse_lenient("(a").filter,
+            Filter::Text("(a".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_ages_out_of_range() {
+        for value in [
+            ">1000000y",
+            ">99999999999999d",
+            "<9223372036854775807h",
+            ">-5d",
+        ] {
+            assert_eq!(
+                Query::parse(&format!("opened:{}", value)),
+                Err(QueryError::InvalidDuration(value.to_owned()))
+            );
+        }
+        assert!(Query::parse("opened:>1000y").is_ok());
+    }
+
+    #[test]
+    fn compares_ages() {
+        let mut opened = link("Opened", "https://a.test");
+        opened.last_opened = Some(Utc::now() - Duration::days(10));
+        let never = link("Never", "https://b.test");
+
+        assert!(matches("opened:>7d", "t", &opened));
+        assert!(!matches("opened:>2w", "t", &opened));
+        assert!(matches("opened:<2w", "t", &opened));
+        assert!(!matches("opened:<1w", "t", &opened));
+        assert!(matches("opened:30d", "t", &never));
+        assert!(!matches("opened:<30d", "t", &never));
+
+        // * The cutoff is before the earliest date chrono has
+        let filter = Filter::Opened(Comparison::OlderThan, Duration::max_value());
+        let now = Utc::now();
+        let pages = PageMatches::new();
+        let topic = TopicModel::new("t".to_owned());
+        assert!(!filter.matches(&topic, &opened, now, &pages));
+        assert!(filter.matches(&topic, &never, now, &pages));
+        let filter = Filter::Opened(Comparison::NewerThan, Duration::max_value());
+        assert!(filter.matches(&topic, &opened, now, &pages));
+    }
+
+    #[test]
+    fn evaluates_filters() {
+        let mut rust = link("The Rust Book", "https://doc.rust-lang.org/book/");
+        rust.tags = vec!["Learn".to_owned()];
+
+        assert!(matches("topic:lang title:book", "Languages", &rust));
+        assert!(matches("site:rust-lang.org tag:learn", "t", &rust));
+        assert!(!matches("site:lang.org", "t", &rust));
+        assert!(matches("-tag:todo", "t", &rust));
+        assert!(matches("tag:todo OR url:book", "t", &rust));
+        assert!(!matches("-(tag:todo OR url:book)", "t", &rust));
+        assert!(matches(r#"title:"rust book""#, "t", &rust));
+        assert!(matches("is:unread", "t", &rust));
+        assert!(matches("rstbk", "t", &rust));
+    }
+}
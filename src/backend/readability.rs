@@ -0,0 +1,375 @@
+use super::page_meta::{attribute, attributes, decode_entities, find_ignore_case};
+
+/// Elements whose content is never part of the readable text.
+const SKIPPED: [&str; 14] = [
+    "script", "style", "noscript", "template", "svg", "iframe", "nav", "header", "footer", "aside",
+    "form", "button", "select", "head",
+];
+
+/// Elements that start or end a block of text.
+const BLOCKS: [&str; 26] = [
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "ul",
+    "ol",
+    "dl",
+    "dt",
+    "dd",
+    "blockquote",
+    "pre",
+    "table",
+    "tr",
+    "td",
+    "th",
+    "figcaption",
+    "br",
+    "hr",
+];
+
+/// Shorter paragraphs are kept only when they are headings, which may also be links.
+const MIN_BLOCK_WORDS: usize = 4;
+
+/// A block of a page as the reader shows it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArticleBlock {
+    /// Level 1 to 6 and text
+    Heading(u8, String),
+    Paragraph(String),
+    /// `number` is set on the first block of an item of an ordered list
+    ListItem {
+        number: Option<usize>,
+        text: String,
+    },
+    Quote(String),
+    /// Whitespace kept as in the page
+    Code(String),
+    /// `src` as written in the page, which may be relative or a `data:` URI
+    Image {
+        src: String,
+        alt: String,
+    },
+}
+
+impl ArticleBlock {
+    /// Text of the block, images have none.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            ArticleBlock::Heading(_, text)
+            | ArticleBlock::Paragraph(text)
+            | ArticleBlock::ListItem { text, .. }
+            | ArticleBlock::Quote(text)
+            | ArticleBlock::Code(text) => Some(text),
+            ArticleBlock::Image { .. } => None,
+        }
+    }
+}
+
+/// Readable text of a page as blocks such as paragraphs, headings and list items, see
+/// [`extract_article`].
+pub fn extract_blocks(html: &str) -> Vec<String> {
+    extract_article(html)
+        .iter()
+        .filter_map(|block| block.text().map(str::to_owned))
+        .collect()
+}
+
+/// Readable content of a page. Navigation, scripts, forms and blocks that are mostly links
+/// are left out, and only the `<article>` or `<main>` element is read when the page has one.
+pub fn extract_article(html: &str) -> Vec<ArticleBlock> {
+    let content = ["article", "main", "body"]
+        .iter()
+        .find_map(|name| element_content(html, name))
+        .unwrap_or(html);
+
+    let mut reader = BlockReader::default();
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        reader.text(&rest[..start]);
+        rest = &rest[start..];
+
+        // * Comments may hold `>`, skip to their own end
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+            continue;
+        }
+
+        let is_close = rest[1..].starts_with('/');
+        let name = rest[1 + is_close as usize..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+
+        // * Markup inside code is only highlighting
+        if reader.in_code && name != "pre" {
+            if name == "br" {
+                reader.text("\n");
+            }
+            rest = &rest[end..];
+            continue;
+        }
+
+        let is_self_closing = rest[..end].ends_with("/>");
+        if !is_close && !is_self_closing && SKIPPED.contains(&name.as_str()) {
+            rest = &rest[end..];
+            let close = find_ignore_case(rest, &format!("</{}", name))
+                .and_then(|close| rest[close..].find('>').map(|end| close + end + 1))
+                .unwrap_or(rest.len());
+            rest = &rest[close..];
+            continue;
+        }
+
+        if BLOCKS.contains(&name.as_str()) {
+            reader.flush();
+            reader.enter(&name, !is_close);
+        } else if name == "a" {
+            reader.in_link = !is_close;
+        } else if name == "img" && !is_close {
+            let attrs = attributes(rest[4.min(end)..end].trim_end_matches('>'));
+            reader.image(&attrs);
+        }
+
+        rest = &rest[end..];
+    }
+    reader.text(rest);
+    reader.flush();
+
+    reader.blocks
+}
+
+/// What is between the first `<name ...>` and the last `</name>`.
+fn element_content<'a>(html: &'a str, name: &str) -> Option<&'a str> {
+    let content = &html[find_open_tag(html, name)?..];
+    let content = &content[content.find('>')? + 1..];
+    let close = rfind_ignore_case(content, &format!("</{}", name)).unwrap_or(content.len());
+
+    Some(&content[..close])
+}
+
+/// Byte index of the first `<name>` or `<name ...>` tag, `<mainframe` is not `<main`.
+fn find_open_tag(html: &str, name: &str) -> Option<usize> {
+    let open = format!("<{}", name);
+    let mut offset = 0;
+    while let Some(start) = find_ignore_case(&html[offset..], &open) {
+        let idx = offset + start;
+        let next = html[idx + open.len()..].chars().next();
+        if next.is_some_and(|c| c == '>' || c == '/' || c.is_whitespace()) {
+            return Some(idx);
+        }
+        offset = idx + open.len();
+    }
+
+    None
+}
+
+fn rfind_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .rposition(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+#[derive(Debug, Default)]
+struct BlockReader {
+    blocks: Vec<ArticleBlock>,
+    current: String,
+    link_chars: usize,
+    in_link: bool,
+    in_code: bool,
+    heading: Option<u8>,
+    quotes: usize,
+    /// Open lists, with the last item number of the ordered ones
+    lists: Vec<Option<usize>>,
+    items: usize,
+    item_number: Option<usize>,
+}
+
+impl BlockReader {
+    fn text(&mut self, text: &str) {
+        let text = decode_entities(text);
+        if self.in_code {
+            self.current.push_str(&text);
+            return;
+        }
+
+        for word in text.split_whitespace() {
+            if !self.current.is_empty() {
+                self.current.push(' ');
+            }
+            self.current.push_str(word);
+            if self.in_link {
+                self.link_chars += word.chars().count() + 1;
+            }
+        }
+    }
+
+    /// Follows the block elements the next text is in, after the current block is flushed.
+    fn enter(&mut self, name: &str, is_open: bool) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" if is_open => {
+                self.heading = name[1..].parse().ok();
+            }
+            "pre" => self.in_code = is_open,
+            "blockquote" if is_open => self.quotes += 1,
+            "blockquote" => self.quotes = self.quotes.saturating_sub(1),
+            "ol" | "ul" if is_open => self.lists.push((name == "ol").then_some(0)),
+            "ol" | "ul" => {
+                self.lists.pop();
+            }
+            "li" if is_open => {
+                self.items += 1;
+                self.item_number = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        Some(*number)
+                    }
+                    _ => None,
+                };
+            }
+            "li" => self.items = self.items.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    fn image(&mut self, attrs: &[(String, String)]) {
+        // * Lazy-loaded images keep the real source aside, 1px ones are trackers
+        let src = attribute(attrs, "data-src").or_else(|| attribute(attrs, "src"));
+        let is_pixel = ["width", "height"]
+            .iter()
+            .any(|key| attribute(attrs, key).is_some_and(|size| size.trim() == "1"));
+        let Some(src) = src.filter(|src| !src.trim().is_empty() && !is_pixel) else {
+            return;
+        };
+
+        self.flush();
+        self.blocks.push(ArticleBlock::Image {
+            src: src.trim().to_owned(),
+            alt: attribute(attrs, "alt").unwrap_or_default(),
+        });
+    }
+
+    fn flush(&mut self) {
+        let block = std::mem::take(&mut self.current);
+        let link_chars = std::mem::take(&mut self.link_chars);
+        let heading = self.heading.take();
+
+        if self.in_code {
+            let code = block.trim_matches('\n').trim_end();
+            if !code.trim().is_empty() {
+                self.blocks.push(ArticleBlock::Code(code.to_owned()));
+            }
+            return;
+        }
+
+        let words = block.split_whitespace().count();
+        let is_link_list = link_chars * 2 > block.chars().count();
+        if words == 0 {
+            return;
+        }
+        let block = match heading {
+            Some(level) => ArticleBlock::Heading(level, block),
+            // * Short items are fine, menus are lists of links
+            None if self.items > 0 && !is_link_list => ArticleBlock::ListItem {
+                number: self.item_number.take(),
+                text: block,
+            },
+            None if is_link_list || words < MIN_BLOCK_WORDS => return,
+            None if self.quotes > 0 => ArticleBlock::Quote(block),
+            None => ArticleBlock::Paragraph(block),
+        };
+        self.blocks.push(block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_article() {
+        let html = r#"<html><head><title>Skipped</title></head><body>
+            <nav><p>Home and about and contact pages</p></nav>
+            <mainframe>Not the main element at all here</mainframe>
+            <article>
+                <h1>The <em>Title</em></h1>
+                <p>First paragraph with enough words &amp; an entity.</p>
+                <p>Too short.</p>
+                <!-- a comment with > inside -->
+                <script>var words = "not part of the text at all";</script>
+                <p><a href="/a">A paragraph that is only</a> <a href="/b">a list of links</a></p>
+                <blockquote><p>Quoted words from someone else.</p></blockquote>
+                <img src="/a.png" alt="A picture"><img src="/pixel.gif" width="1">
+            </article>
+            <footer><p>Copyright and all the other footer words</p></footer>
+        </body></html>"#;
+
+        assert_eq!(
+            extract_article(html),
+            vec![
+                ArticleBlock::Heading(1, "The Title".to_owned()),
+                ArticleBlock::Paragraph(
+                    "First paragraph with enough words & an entity.".to_owned()
+                ),
+                ArticleBlock::Quote("Quoted words from someone else.".to_owned()),
+                ArticleBlock::Image {
+                    src: "/a.png".to_owned(),
+                    alt: "A picture".to_owned()
+                },
+            ]
+        );
+        assert_eq!(
+            extract_blocks(html),
+            vec![
+                "The Title".to_owned(),
+                "First paragraph with enough words & an entity.".to_owned(),
+                "Quoted words from someone else.".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_lists_and_keeps_code() {
+        let html = "<main><ol><li>One</li><li>Two<ul><li>Nested</li></ul></li></ol>\
+            <ul><li><a href=\"/x\">Menu</a></li></ul>\
+            <pre><code>fn main() {\n    <span>println!</span>(\"&lt;hi&gt;\");\n}</code></pre></main>";
+
+        assert_eq!(
+            extract_article(html),
+            vec![
+                ArticleBlock::ListItem {
+                    number: Some(1),
+                    text: "One".to_owned()
+                },
+                ArticleBlock::ListItem {
+                    number: Some(2),
+                    text: "Two".to_owned()
+                },
+                ArticleBlock::ListItem {
+                    number: None,
+                    text: "Nested".to_owned()
+                },
+                ArticleBlock::Code("fn main() {\n    println!(\"<hi>\");\n}".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_pages_without_body() {
+        assert_eq!(
+            extract_blocks("Just some text without any markup"),
+            vec!["Just some text without any markup".to_owned()]
+        );
+        assert!(extract_blocks("").is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use std::{collections::HashMap, fs, path::PathBuf, sync::Arc};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use url::Url;
+
+use super::{
+    archive::{archived_page, fetch_page, link_id},
+    page_meta::FETCH_TIMEOUT,
+    readability::{extract_article, ArticleBlock},
+};
+use crate::utils::stash_dir;
+
+/// What the reader shows of a link.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Article {
+    /// Image sources are absolute URLs or `data:` URIs
+    pub blocks: Vec<ArticleBlock>,
+    /// Decoded `data:` images by block index
+    pub images: HashMap<usize, Arc<[u8]>>,
+    /// Read from the archived copy rather than the live page
+    pub is_archived: bool,
+}
+
+/// Readable content of `url`, from its archived copy when there is one so it reads offline.
+/// Blocking.
+pub fn load_article(url: &str) -> Result<Article, String> {
+    let archived = archived_page(url).and_then(|path| fs::read_to_string(path).ok());
+    let is_archived = archived.is_some();
+    let (html, base) = match archived {
+        // * Snapshots have absolute or inlined sources already
+        Some(html) => (html, Url::parse(url).map_err(|e| e.to_string())?),
+        None => {
+            let agent = ureq::AgentBuilder::new().timeout(FETCH_TIMEOUT).build();
+            fetch_page(&agent, url)?
+        }
+    };
+
+    let mut images = HashMap::new();
+    let blocks = extract_article(&html)
+        .into_iter()
+        .filter_map(|block| match block {
+            ArticleBlock::Image { src, alt } if !src.starts_with("data:") => {
+                let src = base.join(&src).ok()?;
+                matches!(src.scheme(), "http" | "https").then(|| ArticleBlock::Image {
+                    src: src.to_string(),
+                    alt,
+                })
+            }
+            block => Some(block),
+        })
+        .collect::<Vec<ArticleBlock>>();
+    for (idx, block) in blocks.iter().enumerate() {
+        if let ArticleBlock::Image { src, .. } = block {
+            if let Some(bytes) = decode_data_uri(src) {
+                images.insert(idx, bytes);
+            }
+        }
+    }
+
+    if blocks.is_empty() {
+        return Err("No readable text found on the page".to_owned());
+    }
+
+    Ok(Article {
+        blocks,
+        images,
+        is_archived,
+    })
+}
+
+/// Bytes of a base64 `data:` URI.
+fn decode_data_uri(uri: &str) -> Option<Arc<[u8]>> {
+    let (header, data) = uri.strip_prefix("data:")?.split_once(',')?;
+    if !header.ends_with(";base64") {
+        return None;
+    }
+
+    STANDARD.decode(data.trim()).ok().map(Arc::from)
+}
+
+fn positions_path() -> PathBuf {
+    stash_dir().join("reader_positions.json")
+}
+
+fn load_positions() -> HashMap<String, f32> {
+    fs::read_to_string(positions_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// How far down the reader was scrolled when `url` was last read.
+pub fn reading_position(url: &str) -> Option<f32> {
+    load_positions().get(&link_id(url)).copied()
+}
+
+/// Remembers how far down `url` was read, forgotten again at the top.
+pub fn save_reading_position(url: &str, offset: f32) {
+    let mut positions = load_positions();
+    if offset > 0. {
+        positions.insert(link_id(url), offset);
+    } else {
+        positions.remove(&link_id(url));
+    }
+
+    let data = serde_json::to_string(&positions).expect("Failed to serialize reading positions");
+    if let Err(e) = fs::write(positions_path(), data) {
+        eprintln!("Failed to save reading positions: {:?}", e);
+    }
+}
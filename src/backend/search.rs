@@ -0,0 +1,71 @@
+use super::{
+    models::{LinkModel, TopicModel},
+    query::PageMatches,
+    text_index::Snippet,
+};
+use crate::utils::fuzzy::fuzzy_match;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub topic: TopicModel,
+    pub link: LinkModel,
+    pub score: i64,
+
+    pub topic_indices: Vec<usize>,
+    pub title_indices: Vec<usize>,
+    pub url_indices: Vec<usize>,
+    /// Passage of the archived page the terms were found in
+    pub snippet: Option<Snippet>,
+}
+
+impl SearchHit {
+    /// Ranks a link by its best field for each term, titles weigh more than topics and URLs,
+    /// which weigh more than the text of the archived page.
+    pub fn new(
+        terms: &[String],
+        topic: &TopicModel,
+        link: &LinkModel,
+        pages: &PageMatches,
+    ) -> Self {
+        let mut hit = Self {
+            topic: topic.clone(),
+            link: link.clone(),
+            score: 0,
+            topic_indices: vec![],
+            title_indices: vec![],
+            url_indices: vec![],
+            snippet: None,
+        };
+
+        for term in terms {
+            let topic_match = fuzzy_match(term, &topic.name);
+            let title_match = fuzzy_match(term, &link.title);
+            let url_match = fuzzy_match(term, &link.url);
+            // * About half a good title match, pages saying it more often first
+            let page_score = pages
+                .get(term)
+                .and_then(|urls| urls.get(&link.url))
+                .map(|count| term.chars().count() as i64 * 8 + (*count).min(20) as i64);
+
+            hit.score += [
+                title_match.as_ref().map(|m| m.score * 3 / 2),
+                topic_match.as_ref().map(|m| m.score),
+                url_match.as_ref().map(|m| m.score),
+                page_score,
+            ]
+            .into_iter()
+            .flatten()
+            .max()
+            .unwrap_or_default();
+
+            hit.topic_indices
+                .extend(topic_match.map(|m| m.indices).unwrap_or_default());
+            hit.title_indices
+                .extend(title_match.map(|m| m.indices).unwrap_or_default());
+            hit.url_indices
+                .extend(url_match.map(|m| m.indices).unwrap_or_default());
+        }
+
+        hit
+    }
+}
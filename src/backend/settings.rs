@@ -0,0 +1,74 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{file_writer::write_later, url_rules::UrlRules};
+use crate::utils::{enums::AppTheme, stash_dir};
+
+/// User preferences restored on launch, stored in `settings.json` next to `bookmarks.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub theme: AppTheme,
+    pub always_on_top: bool,
+    /// Names of the expanded topics, so renames and reorders don't shift them
+    pub expanded_topics: Vec<String>,
+    pub window_size: Option<[f32; 2]>,
+    pub window_pos: Option<[f32; 2]>,
+    /// Days deleted items stay in the trash before they are purged
+    pub trash_retention_days: u32,
+    /// Show the fetched preview of each link under its title
+    pub show_link_previews: bool,
+    /// Hide the links already read from the topics
+    pub show_unread_only: bool,
+    /// Opening a link from the app marks it as read
+    pub mark_read_on_open: bool,
+    /// Tracking parameters stripped from links before they are saved
+    pub url_rules: UrlRules,
+    /// Background tasks, like link checks and page fetches, running at once
+    pub max_background_tasks: usize,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            theme: AppTheme::Dark,
+            always_on_top: false,
+            expanded_topics: vec![],
+            window_size: None,
+            window_pos: None,
+            trash_retention_days: 30,
+            show_link_previews: false,
+            show_unread_only: false,
+            mark_read_on_open: true,
+            url_rules: UrlRules::default(),
+            max_background_tasks: 8,
+        }
+    }
+}
+
+impl AppSettings {
+    pub fn load(is_debug: bool) -> Self {
+        match fs::read_to_string(Self::path(is_debug)) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                eprintln!("Failed to deserialize settings: {:?}", e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Saves in the background, pending saves are flushed on exit.
+    pub fn save(&self, is_debug: bool) {
+        let json = serde_json::to_string_pretty(self).expect("Failed to serialize settings");
+        write_later(Self::path(is_debug), json);
+    }
+
+    fn path(is_debug: bool) -> PathBuf {
+        stash_dir().join(if is_debug {
+            "settings_debug.json"
+        } else {
+            "settings.json"
+        })
+    }
+}
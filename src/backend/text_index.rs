@@ -0,0 +1,336 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::archive::{link_id, page_text};
+
+/// Longest snippet shown under a search result, in chars.
+const SNIPPET_CHARS: usize = 160;
+
+/// The part of a page around the words a search found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub text: String,
+    /// Char indices of `text` that matched
+    pub indices: Vec<usize>,
+}
+
+/// Inverted index of the words of archived pages, so links are found by what their page says.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextIndex {
+    #[serde(skip)]
+    path: PathBuf,
+
+    /// Word to the link ids whose page has it and how often
+    postings: HashMap<String, HashMap<String, u32>>,
+    /// URL of every indexed page by link id
+    pages: HashMap<String, String>,
+}
+
+impl TextIndex {
+    /// Reads the index saved at `path`, an unreadable one is rebuilt from the archive.
+    pub fn load(path: PathBuf) -> Self {
+        let index = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<Self>(&data).ok())
+            .unwrap_or_default();
+
+        Self { path, ..index }
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = fs::write(&self.path, self.to_json()) {
+            eprintln!("Failed to save text index: {:?}", e);
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Failed to serialize text index")
+    }
+
+    /// URLs of the indexed pages.
+    pub fn urls(&self) -> Vec<String> {
+        self.pages.values().cloned().collect()
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.pages.contains_key(&link_id(url))
+    }
+
+    /// Indexes the archived text of `url` again, `false` when it has none.
+    pub fn add(&mut self, url: &str) -> bool {
+        self.remove(url);
+        let Some(blocks) = page_text(url) else {
+            return false;
+        };
+        self.insert(url, &blocks);
+
+        true
+    }
+
+    /// Indexes `blocks` as the text of `url`.
+    fn insert(&mut self, url: &str, blocks: &[String]) {
+        let id = link_id(url);
+        for word in blocks.iter().flat_map(|block| words(block)) {
+            *self
+                .postings
+                .entry(word)
+                .or_default()
+                .entry(id.clone())
+                .or_default() += 1;
+        }
+        self.pages.insert(id, url.to_owned());
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        let id = link_id(url);
+        if self.pages.remove(&id).is_none() {
+            return;
+        }
+
+        self.postings.retain(|_, pages| {
+            pages.remove(&id);
+            !pages.is_empty()
+        });
+    }
+
+    /// URLs of the pages having every word of `text`, with how often those appear. A word
+    /// also finds the longer words it starts, so `archiv` finds `archived`.
+    pub fn search(&self, text: &str) -> HashMap<String, u32> {
+        let mut found: Option<HashMap<&String, u32>> = None;
+        for word in words(text) {
+            let mut pages: HashMap<&String, u32> = HashMap::new();
+            for (_, postings) in self.postings.iter().filter(|(w, _)| w.starts_with(&word)) {
+                for (id, count) in postings {
+                    *pages.entry(id).or_default() += count;
+                }
+            }
+
+            found = Some(match found {
+                Some(found) => found
+                    .into_iter()
+                    .filter_map(|(id, count)| Some((id, count + pages.get(id)?)))
+                    .collect(),
+                None => pages,
+            });
+        }
+
+        found
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(id, count)| Some((self.pages.get(id)?.clone(), count)))
+            .collect()
+    }
+
+    /// The first passage of the page at `url` with a word of `terms`, cut to about
+    /// [`SNIPPET_CHARS`] at word boundaries.
+    pub fn snippet(&self, url: &str, terms: &[String]) -> Option<Snippet> {
+        let wanted = terms
+            .iter()
+            .flat_map(|term| words(term))
+            .collect::<Vec<String>>();
+        if wanted.is_empty() || !self.contains(url) {
+            return None;
+        }
+
+        passage(&page_text(url)?, &wanted)
+    }
+}
+
+/// The first passage of `blocks` with a word starting with one of `wanted`.
+fn passage(blocks: &[String], wanted: &[String]) -> Option<Snippet> {
+    for block in blocks {
+        let chars = block.chars().collect::<Vec<char>>();
+        let spans = word_spans(&chars)
+            .into_iter()
+            .filter(|(start, end)| {
+                let word = chars[*start..*end]
+                    .iter()
+                    .collect::<String>()
+                    .to_lowercase();
+                wanted.iter().any(|w| word.starts_with(w.as_str()))
+            })
+            .collect::<Vec<(usize, usize)>>();
+        let Some(&(first, _)) = spans.first() else {
+            continue;
+        };
+
+        // * Some context before the first match, starting on a word
+        let start = match first.saturating_sub(SNIPPET_CHARS / 4) {
+            0 => 0,
+            start => (start..first)
+                .find(|idx| chars[idx - 1].is_whitespace())
+                .unwrap_or(first),
+        };
+        let end = (start + SNIPPET_CHARS).min(chars.len());
+        let end = if end < chars.len() {
+            (first + 1..end)
+                .rev()
+                .find(|idx| chars[*idx].is_whitespace())
+                .unwrap_or(end)
+        } else {
+            end
+        };
+
+        let offset = usize::from(start > 0);
+        let mut text = String::new();
+        if start > 0 {
+            text.push('…');
+        }
+        text.extend(&chars[start..end]);
+        if end < chars.len() {
+            text.push('…');
+        }
+        let indices = spans
+            .iter()
+            .filter(|(s, e)| *s >= start && *e <= end)
+            .flat_map(|(s, e)| (s - start + offset)..(e - start + offset))
+            .collect();
+
+        return Some(Snippet { text, indices });
+    }
+
+    None
+}
+
+/// Lowercase words of `text` as the index stores them, single letters left out.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 1)
+        .map(str::to_lowercase)
+}
+
+/// `(start, end)` char indices of the words in `chars`.
+fn word_spans(chars: &[char]) -> Vec<(usize, usize)> {
+    let mut spans = vec![];
+
+    let mut start = None;
+    for (idx, c) in chars.iter().enumerate() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(idx),
+            (false, Some(s)) => {
+                spans.push((s, idx));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, chars.len()));
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(text: &[&str]) -> Vec<String> {
+        text.iter().map(|block| block.to_string()).collect()
+    }
+
+    fn index() -> TextIndex {
+        let mut index = TextIndex::default();
+        index.insert(
+            "https://a.test/",
+            &blocks(&["Archived pages are searchable", "Pages, pages, PAGES!"]),
+        );
+        index.insert("https://b.test/", &blocks(&["An archive of a page"]));
+        index
+    }
+
+    fn found(index: &TextIndex, text: &str) -> Vec<(String, u32)> {
+        let mut found = index.search(text).into_iter().collect::<Vec<_>>();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn finds_pages_by_their_words() {
+        let index = index();
+
+        assert_eq!(
+            found(&index, "pages"),
+            vec![("https://a.test/".to_owned(), 4)]
+        );
+        // * Prefixes find longer words, every word has to be on the page
+        assert_eq!(
+            found(&index, "Archiv PAGE"),
+            vec![
+                ("https://a.test/".to_owned(), 5),
+                ("https://b.test/".to_owned(), 2)
+            ]
+        );
+        assert_eq!(found(&index, "searchable of"), vec![]);
+        // * Single letters are not indexed
+        assert_eq!(found(&index, "a"), vec![]);
+    }
+
+    #[test]
+    fn updates_after_edits_and_removals() {
+        let mut index = index();
+
+        index.remove("https://a.test/");
+        index.insert("https://a.test/", &blocks(&["Nothing else"]));
+        assert_eq!(found(&index, "searchable"), vec![]);
+        assert_eq!(
+            found(&index, "nothing"),
+            vec![("https://a.test/".to_owned(), 1)]
+        );
+
+        index.remove("https://b.test/");
+        assert!(!index.contains("https://b.test/"));
+        assert_eq!(found(&index, "archive"), vec![]);
+        assert_eq!(index.urls(), vec!["https://a.test/".to_owned()]);
+        // * Words only the removed page had are dropped
+        assert!(!index.postings.contains_key("archive"));
+    }
+
+    #[test]
+    fn keeps_its_words_when_saved() {
+        let index = index();
+        let loaded = serde_json::from_str::<TextIndex>(&index.to_json()).unwrap();
+
+        assert_eq!(loaded, index);
+    }
+
+    #[test]
+    fn snippets_the_first_passage_with_a_word() {
+        let wanted = vec!["fox".to_owned()];
+        let snippet = passage(
+            &blocks(&["No match here", "The quick brown Foxes jump"]),
+            &wanted,
+        )
+        .unwrap();
+
+        assert_eq!(snippet.text, "The quick brown Foxes jump");
+        assert_eq!(snippet.indices, (16..21).collect::<Vec<usize>>());
+        assert_eq!(passage(&blocks(&["No match here"]), &wanted), None);
+    }
+
+    #[test]
+    fn cuts_long_snippets_at_words() {
+        let block = format!("{}needle {}", "alpha ".repeat(20), "omega ".repeat(40));
+        let snippet = passage(&blocks(&[&block]), &["needle".to_owned()]).unwrap();
+
+        assert!(snippet.text.starts_with("…alpha "));
+        assert!(snippet.text.ends_with(" omega…"));
+        assert!(snippet.text.chars().count() <= SNIPPET_CHARS + 2);
+        let highlighted = snippet
+            .indices
+            .iter()
+            .map(|idx| snippet.text.chars().nth(*idx).unwrap())
+            .collect::<String>();
+        assert_eq!(highlighted, "needle");
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use url::{form_urlencoded, Url};
+
+/// Query parameters to strip only on one site and its subdomains.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainRule {
+    pub domain: String,
+    pub params: Vec<String>,
+}
+
+impl DomainRule {
+    pub fn new(domain: &str, params: &[&str]) -> Self {
+        Self {
+            domain: domain.to_owned(),
+            params: params.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    fn applies_to(&self, host: &str) -> bool {
+        host == self.domain || host.ends_with(&format!(".{}", self.domain))
+    }
+}
+
+/// Tracking parameters removed from URLs before they are saved. Names are case-insensitive
+/// and may end with `*` to match a prefix, like `utm_*`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UrlRules {
+    pub params: Vec<String>,
+    pub domains: Vec<DomainRule>,
+}
+
+impl Default for UrlRules {
+    fn default() -> Self {
+        Self {
+            params: [
+                "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid",
+                "mc_cid", "mc_eid", "igshid", "_hsenc", "_hsmi", "mkt_tok", "ref_src", "ref_url",
+            ]
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+            domains: vec![
+                DomainRule::new("youtube.com", &["si", "feature", "pp"]),
+                DomainRule::new("youtu.be", &["si", "feature"]),
+                DomainRule::new(
+                    "amazon.com",
+                    &["tag", "psc", "ref", "ref_", "pd_rd_*", "pf_rd_*"],
+                ),
+                DomainRule::new("producthunt.com", &["ref"]),
+                DomainRule::new("twitter.com", &["s", "t"]),
+                DomainRule::new("x.com", &["s", "t"]),
+                DomainRule::new("instagram.com", &["igsh"]),
+                DomainRule::new("reddit.com", &["share_id"]),
+                DomainRule::new("linkedin.com", &["trackingid", "lipi"]),
+            ],
+        }
+    }
+}
+
+impl UrlRules {
+    /// Trims the URL and drops the tracking parameters of web URLs, leaving the rest untouched.
+    pub fn canonicalize(&self, url: &str) -> String {
+        let url = url.trim();
+        let Ok(mut parsed) = Url::parse(url) else {
+            return url.to_owned();
+        };
+        if !matches!(parsed.scheme(), "http" | "https") || parsed.query().is_none() {
+            return url.to_owned();
+        }
+
+        let host = parsed.host_str().unwrap_or_default().to_lowercase();
+        let domain_params = self
+            .domains
+            .iter()
+            .filter(|rule| rule.applies_to(&host))
+            .flat_map(|rule| rule.params.iter())
+            .collect::<Vec<&String>>();
+        let is_tracking = |name: &str| {
+            self.params
+                .iter()
+                .chain(domain_params.iter().copied())
+                .any(|pattern| matches_param(pattern, name))
+        };
+
+        // * Only the tracking segments go, the rest keeps its exact spelling
+        let query = parsed.query().unwrap_or_default();
+        let segments = query.split('&').collect::<Vec<&str>>();
+        let kept = segments
+            .iter()
+            .filter(|segment| {
+                let name = form_urlencoded::parse(segment.as_bytes())
+                    .next()
+                    .map(|(name, _)| name.into_owned())
+                    .unwrap_or_default();
+                !is_tracking(&name)
+            })
+            .copied()
+            .collect::<Vec<&str>>();
+        if kept.len() == segments.len() {
+            return url.to_owned();
+        }
+
+        if kept.is_empty() {
+            parsed.set_query(None);
+        } else {
+            parsed.set_query(Some(&kept.join("&")));
+        }
+
+        parsed.to_string()
+    }
+
+    /// One rule per line, `param` for every site or `domain: param, param` for one site.
+    pub fn to_text(&self) -> String {
+        self.params
+            .iter()
+            .cloned()
+            .chain(
+                self.domains
+                    .iter()
+                    .map(|rule| format!("{}: {}", rule.domain, rule.params.join(", "))),
+            )
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Reads rules written by [`UrlRules::to_text`], blank lines are skipped.
+    pub fn from_text(text: &str) -> Self {
+        let mut rules = Self {
+            params: vec![],
+            domains: vec![],
+        };
+
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            match line.split_once(':') {
+                Some((domain, params)) => rules.domains.push(DomainRule {
+                    domain: domain.trim().to_lowercase(),
+                    params: params
+                        .split(',')
+                        .map(|p| p.trim().to_owned())
+                        .filter(|p| !p.is_empty())
+                        .collect(),
+                }),
+                None => rules.params.push(line.to_owned()),
+            }
+        }
+
+        rules
+    }
+}
+
+fn matches_param(pattern: &str, name: &str) -> bool {
+    let name = name.to_lowercase();
+    let pattern = pattern.to_lowercase();
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_tracking_params() {
+        let rules = UrlRules::default();
+        assert_eq!(
+            rules.canonicalize(" https://a.test/p?utm_source=x&id=1&UTM_Medium=y#top "),
+            "https://a.test/p?id=1#top"
+        );
+        assert_eq!(
+            rules.canonicalize("https://a.test/p?fbclid=1"),
+            "https://a.test/p"
+        );
+        assert_eq!(
+            rules.canonicalize("https://www.youtube.com/watch?v=abc&si=xyz"),
+            "https://www.youtube.com/watch?v=abc"
+        );
+        // * Only YouTube's `si` is tracking
+        assert_eq!(
+            rules.canonicalize("https://a.test/?si=1"),
+            "https://a.test/?si=1"
+        );
+        assert_eq!(
+            rules.canonicalize("mailto:a@b.test?utm_source=x"),
+            "mailto:a@b.test?utm_source=x"
+        );
+    }
+
+    #[test]
+    fn keeps_ref_outside_its_domains() {
+        let rules = UrlRules::default();
+        assert_eq!(
+            rules.canonicalize("https://github.com/o/r/blob/main/f.rs?ref=v1.0"),
+            "https://github.com/o/r/blob/main/f.rs?ref=v1.0"
+        );
+        assert_eq!(
+            rules.canonicalize("https://www.amazon.com/dp/B0?ref=sr_1&th=1"),
+            "https://www.amazon.com/dp/B0?th=1"
+        );
+    }
+
+    #[test]
+    fn keeps_the_rest_of_the_query_as_written() {
+        let rules = UrlRules::default();
+        assert_eq!(
+            rules.canonicalize("https://a.test/?flag&utm_source=x&q=a%20b+c&e=%7e"),
+            "https://a.test/?flag&q=a%20b+c&e=%7e"
+        );
+        assert_eq!(
+            rules.canonicalize("https://a.test/?utm%5Fsource=x&q=1"),
+            "https://a.test/?q=1"
+        );
+        assert_eq!(
+            rules.canonicalize("https://a.test/?flag&q=%7e"),
+            "https://a.test/?flag&q=%7e"
+        );
+    }
+
+    #[test]
+    fn round_trips_text() {
+        let rules = UrlRules::default();
+        assert_eq!(UrlRules::from_text(&rules.to_text()), rules);
+
+        let rules = UrlRules::from_text("utm_*\n\n Example.com: a, b* ,\n");
+        assert_eq!(rules.params, vec!["utm_*".to_owned()]);
+        assert_eq!(
+            rules.domains,
+            vec![DomainRule::new("example.com", &["a", "b*"])]
+        );
+    }
+}
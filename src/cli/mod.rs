@@ -0,0 +1,265 @@
+use std::io::{self, Read};
+
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::{env::Shells, ArgValueCandidates, CompletionCandidate};
+use directories::UserDirs;
+
+use crate::{
+    backend::{
+        bookmark_manager::{read_topic_names, BookmarkManager},
+        models::{LinkModel, TopicModel},
+        settings::AppSettings,
+    },
+    utils::{enums::BookmarkItem, extract_urls},
+};
+
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+#[derive(Debug, Parser)]
+#[command(name = "stash", version, about, long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Add links to a topic
+    Add {
+        /// Topic to add the links to, it is created if missing
+        #[arg(add = ArgValueCandidates::new(topic_candidates))]
+        topic: Option<String>,
+
+        /// Topic to add the links to, every argument is then a URL
+        #[arg(
+            short = 't',
+            long = "topic",
+            value_name = "TOPIC",
+            add = ArgValueCandidates::new(topic_candidates)
+        )]
+        topic_flag: Option<String>,
+
+        /// URLs to add, use `-` to read them from stdin
+        urls: Vec<String>,
+    },
+
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Print the roff man page
+    Man,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub fn name(&self) -> &str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+}
+
+fn topic_candidates() -> Vec<CompletionCandidate> {
+    // * Completing nothing beats a panic where there are no user directories
+    let Some(dirs) = UserDirs::new() else {
+        return vec![];
+    };
+    let Some(documents) = dirs.document_dir() else {
+        return vec![];
+    };
+
+    read_topic_names(&documents.join("stash"), cfg!(debug_assertions))
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// The topic and URLs of `stash add`, given as `TOPIC URLS...` or as `--topic TOPIC URLS...`.
+fn add_arguments(
+    topic: Option<String>,
+    topic_flag: Option<String>,
+    urls: Vec<String>,
+) -> Result<(String, Vec<String>), clap::Error> {
+    let (topic, urls) = match (topic_flag, topic) {
+        // * With the flag, the first positional is a URL too
+        (Some(flag), topic) => (flag, topic.into_iter().chain(urls).collect()),
+        (None, Some(topic)) => (topic, urls),
+        (None, None) => {
+            return Err(Cli::command().error(
+                ErrorKind::MissingRequiredArgument,
+                "a topic is required, as the first argument or with --topic",
+            ))
+        }
+    };
+    if urls.is_empty() {
+        return Err(Cli::command().error(
+            ErrorKind::MissingRequiredArgument,
+            "at least one URL, or `-` for stdin, is required",
+        ));
+    }
+
+    Ok((topic, urls))
+}
+
+pub fn run(command: Commands) -> io::Result<()> {
+    match command {
+        Commands::Add {
+            topic,
+            topic_flag,
+            urls,
+        } => {
+            let (topic, urls) = add_arguments(topic, topic_flag, urls).unwrap_or_else(|e| e.exit());
+            add_links(topic, urls)?
+        }
+        Commands::Completions { shell } => {
+            // * Registration scripts call back into `COMPLETE=<shell> stash` so topics stay up to date
+            let shells = Shells::builtins();
+            let completer = shells.completer(shell.name()).expect("Unsupported shell");
+            let name = Cli::command().get_name().to_string();
+            completer.write_registration(COMPLETE_VAR, &name, &name, &name, &mut io::stdout())?;
+        }
+        Commands::Man => {
+            clap_mangen::Man::new(Cli::command()).render(&mut io::stdout())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn add_links(topic_name: String, args: Vec<String>) -> io::Result<()> {
+    let url_rules = AppSettings::load(cfg!(debug_assertions)).url_rules;
+
+    let mut urls = vec![];
+    for arg in args {
+        if arg == "-" {
+            // * Stdin can be any text, only the URLs in it are kept
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            urls.extend(extract_urls(&text));
+        } else {
+            urls.push(arg);
+        }
+    }
+
+    let mut bookmark_manager = BookmarkManager::new(cfg!(debug_assertions));
+    let topic = BookmarkItem::Topic(TopicModel::new(topic_name.clone()));
+    let links = urls
+        .iter()
+        .map(|url| {
+            let url = url_rules.canonicalize(url);
+            BookmarkItem::Link(LinkModel::new(url.clone(), url, None))
+        })
+        .collect::<Vec<BookmarkItem>>();
+
+    let total = links.len();
+    let skipped = bookmark_manager.add_links(topic, links).len();
+    let added = total - skipped;
+
+    println!(
+        "Added {} {} to \"{}\", skipped {} {}",
+        added,
+        if added == 1 { "link" } else { "links" },
+        topic_name,
+        skipped,
+        if skipped == 1 {
+            "duplicate"
+        } else {
+            "duplicates"
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap_complete::engine::complete;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<(String, Vec<String>), clap::Error> {
+        match Cli::try_parse_from(args)?.command {
+            Some(Commands::Add {
+                topic,
+                topic_flag,
+                urls,
+            }) => add_arguments(topic, topic_flag, urls),
+            command => panic!("Parsed {:?}", command),
+        }
+    }
+
+    #[test]
+    fn topic_comes_first() {
+        assert_eq!(
+            parse(&["stash", "add", "Rust", "https://a.test", "-"]).unwrap(),
+            (
+                "Rust".to_owned(),
+                vec!["https://a.test".to_owned(), "-".to_owned()]
+            )
+        );
+
+        assert!(parse(&["stash", "add", "Rust"]).is_err());
+        assert!(parse(&["stash", "add"]).is_err());
+    }
+
+    #[test]
+    fn takes_the_topic_as_a_flag() {
+        assert_eq!(
+            parse(&["stash", "add", "--topic", "Research", "-"]).unwrap(),
+            ("Research".to_owned(), vec!["-".to_owned()])
+        );
+        assert_eq!(
+            parse(&[
+                "stash",
+                "add",
+                "-t",
+                "Research",
+                "https://a.test",
+                "https://b.test"
+            ])
+            .unwrap(),
+            (
+                "Research".to_owned(),
+                vec!["https://a.test".to_owned(), "https://b.test".to_owned()]
+            )
+        );
+
+        assert!(parse(&["stash", "add", "--topic", "Research"]).is_err());
+    }
+
+    #[test]
+    fn completes_topics_after_add() {
+        let values = |args: &[&str]| {
+            complete(
+                &mut Cli::command(),
+                args.iter().map(Into::into).collect(),
+                args.len() - 1,
+                None,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|candidate| candidate.get_value().to_string_lossy().into_owned())
+            .collect::<Vec<String>>()
+        };
+        let topics = topic_candidates()
+            .into_iter()
+            .map(|candidate| candidate.get_value().to_string_lossy().into_owned())
+            .collect::<Vec<String>>();
+
+        // * Only reads the bookmarks of this machine, whatever topics they have
+        let first = values(&["stash", "add", ""]);
+        assert!(topics.iter().all(|topic| first.contains(topic)));
+        assert_eq!(values(&["stash", "add", "--topic", ""]), topics);
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(non_snake_case)]
 pub mod app;
 pub mod backend;
+pub mod cli;
 pub mod ui;
 pub mod utils;
@@ -1,8 +1,13 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
 use tokio::runtime::Runtime;
 
-use stash::utils::{run_first_error_app::run_first_error_app, run_main_app::run_main_app};
+use stash::{
+    cli::{self, Cli, COMPLETE_VAR},
+    utils::{run_first_error_app::run_first_error_app, run_main_app::run_main_app},
+};
 
 fn check_env() -> Result<(), String> {
     #[cfg(any(target_os = "windows", target_os = "linux"))]
@@ -17,6 +22,19 @@ fn check_env() -> Result<(), String> {
 }
 
 fn main() -> Result<(), eframe::Error> {
+    CompleteEnv::with_factory(Cli::command)
+        .var(COMPLETE_VAR)
+        .complete();
+
+    if let Some(command) = Cli::parse().command {
+        if let Err(e) = cli::run(command) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
     let rt = Runtime::new().expect("Unable to create Runtime");
     let _enter = rt.enter();
 
@@ -0,0 +1,225 @@
+use std::cmp::Reverse;
+
+use crossbeam::channel::Sender;
+use egui::{
+    scroll_area::ScrollBarVisibility, Align, Align2, Frame, Key, KeyboardShortcut, Modifiers,
+    RichText, Rounding, ScrollArea, TextEdit, TextStyle, Window,
+};
+use egui_phosphor::regular;
+
+use crate::utils::{enums::AppMessage, fuzzy::fuzzy_match};
+
+use super::components::highlighted_text;
+
+pub const TOGGLE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::K);
+/// Rows shown at most, the palette has a few commands per link.
+const MAX_RESULTS: usize = 50;
+
+/// `(command index, matched char indices)`
+type Row = (usize, Vec<usize>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteCommand {
+    pub label: String,
+    pub msg: AppMessage,
+}
+
+impl PaletteCommand {
+    pub fn new(label: impl Into<String>, msg: AppMessage) -> Self {
+        Self {
+            label: label.into(),
+            msg,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct CommandPalette {
+    is_open: bool,
+    query: String,
+    selected: usize,
+    commands: Vec<PaletteCommand>,
+    /// [`Self::filtered`] and the number of matches, until the query changes
+    results: Option<(Vec<Row>, usize)>,
+}
+
+impl CommandPalette {
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    /// Opens the palette with a fresh snapshot of the available commands.
+    pub fn open(&mut self, commands: Vec<PaletteCommand>) {
+        self.is_open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.commands = commands;
+        self.results = None;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.query.clear();
+        self.commands.clear();
+        self.results = None;
+    }
+
+    /// The best [`MAX_RESULTS`] commands matching the query, and how many matched in all.
+    fn filtered(&self) -> (Vec<Row>, usize) {
+        let mut matches = self
+            .commands
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, command)| {
+                fuzzy_match(&self.query, &command.label).map(|m| (idx, m.score, m.indices))
+            })
+            .collect::<Vec<(usize, i64, Vec<usize>)>>();
+        if !self.query.trim().is_empty() {
+            matches.sort_by_key(|m| Reverse(m.1));
+        }
+
+        let total = matches.len();
+        let matches = matches
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(idx, _, indices)| (idx, indices))
+            .collect();
+
+        (matches, total)
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context, tx: &Sender<AppMessage>) {
+        if !self.is_open {
+            return;
+        }
+
+        if self.results.is_none() {
+            self.results = Some(self.filtered());
+        }
+        let (filtered, total) = self.results.clone().unwrap_or_default();
+
+        // * Keyboard handling before the text edit sees the keys
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        if escape {
+            self.close();
+            return;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if down && self.selected + 1 < filtered.len() {
+            self.selected += 1;
+        }
+        self.selected = self.selected.min(filtered.len().saturating_sub(1));
+
+        let mut to_run = None;
+        if enter {
+            to_run = filtered.get(self.selected).map(|(idx, _)| *idx);
+        }
+
+        let width = (ctx.screen_rect().width() - 40.).max(200.);
+        Window::new("command_palette")
+            .title_bar(false)
+            .resizable(false)
+            .collapsible(false)
+            .anchor(Align2::CENTER_TOP, [0., 40.])
+            .fixed_size([width, 0.])
+            .frame(Frame::popup(&ctx.style()).rounding(Rounding::same(9.)))
+            .show(ctx, |ui| {
+                let query_res = ui.add(
+                    TextEdit::singleline(&mut self.query)
+                        .hint_text(format!("{} Type a command", regular::COMMAND))
+                        .desired_width(f32::INFINITY),
+                );
+                query_res.request_focus();
+                if query_res.changed() {
+                    self.selected = 0;
+                    self.results = None;
+                }
+
+                ui.add_space(5.);
+
+                ScrollArea::vertical()
+                    .max_height(200.)
+                    .auto_shrink([false, true])
+                    .scroll_bar_visibility(ScrollBarVisibility::VisibleWhenNeeded)
+                    .show(ui, |ui| {
+                        if filtered.is_empty() {
+                            ui.label("No matching commands");
+                        }
+
+                        for (row, (idx, indices)) in filtered.iter().enumerate() {
+                            let is_selected = row == self.selected;
+                            let job = highlighted_text(
+                                ui,
+                                &self.commands[*idx].label,
+                                indices,
+                                TextStyle::Body.resolve(ui.style()),
+                            );
+
+                            let res = ui.selectable_label(is_selected, job);
+                            if is_selected && (up || down) {
+                                res.scroll_to_me(Some(Align::Center));
+                            }
+                            if res.clicked() {
+                                to_run = Some(*idx);
+                            }
+                        }
+
+                        if total > filtered.len() {
+                            ui.label(
+                                RichText::new(format!(
+                                    "{} more, keep typing to narrow down",
+                                    total - filtered.len()
+                                ))
+                                .small()
+                                .weak(),
+                            );
+                        }
+                    });
+            });
+
+        if let Some(idx) = to_run {
+            let msg = self.commands[idx].msg.clone();
+            self.close();
+            if let Err(e) = tx.send(msg) {
+                eprintln!("Error: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_the_results() {
+        let mut palette = CommandPalette::default();
+        let mut commands = (0..200)
+            .map(|idx| PaletteCommand::new(format!("Open link {}", idx), AppMessage::Undo))
+            .collect::<Vec<PaletteCommand>>();
+        commands.push(PaletteCommand::new("Undo", AppMessage::Undo));
+        palette.open(commands);
+
+        let (results, total) = palette.filtered();
+        assert_eq!((results.len(), total), (MAX_RESULTS, 201));
+
+        palette.query = "undo".to_owned();
+        let (results, total) = palette.filtered();
+        assert_eq!(total, 1);
+        assert_eq!(results, vec![(200, vec![0, 1, 2, 3])]);
+
+        palette.query = "link 19".to_owned();
+        // * Scattered matches like "Open link 109" come after
+        let (results, _) = palette.filtered();
+        assert_eq!(results[0].0, 19);
+    }
+}
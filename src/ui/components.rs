@@ -1,6 +1,13 @@
-use egui::{vec2, Button, Rounding};
+use egui::{
+    ecolor::Hsva, text::LayoutJob, vec2, Align2, Button, Color32, CursorIcon, FontId, Image, Label,
+    RichText, Rounding, Sense, Stroke, TextFormat,
+};
+use egui_phosphor::regular;
 
-use crate::utils::calc_btn_size_from_text;
+use crate::{
+    backend::{cache::cached_preview_uri, favicons::SiteIcon, models::LinkPreview},
+    utils::{calc_btn_size_from_text, enums::TreeFocus},
+};
 
 pub fn custom_button(ui: &mut egui::Ui, text: &str, width: Option<f32>, on_press: impl FnOnce()) {
     let min_size = match width {
@@ -35,3 +42,132 @@ pub fn custom_tile(ui: &mut egui::Ui, text: &str, on_press: impl FnOnce()) {
         on_press();
     }
 }
+
+/// Lays out `text` with the chars at `indices` drawn on the selection color.
+pub fn highlighted_text(
+    ui: &egui::Ui,
+    text: &str,
+    indices: &[usize],
+    font_id: FontId,
+) -> LayoutJob {
+    let normal = TextFormat {
+        font_id: font_id.clone(),
+        color: ui.visuals().text_color(),
+        ..Default::default()
+    };
+    let highlight = TextFormat {
+        font_id,
+        color: ui.visuals().strong_text_color(),
+        background: ui.visuals().selection.bg_fill,
+        ..Default::default()
+    };
+
+    let mut job = LayoutJob::default();
+    let mut run = String::new();
+    let mut is_run_highlighted = false;
+    for (idx, c) in text.chars().enumerate() {
+        let is_highlighted = indices.contains(&idx);
+        if is_highlighted != is_run_highlighted && !run.is_empty() {
+            let format = if is_run_highlighted {
+                &highlight
+            } else {
+                &normal
+            };
+            job.append(&run, 0., format.clone());
+            run.clear();
+        }
+        is_run_highlighted = is_highlighted;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        let format = if is_run_highlighted {
+            highlight
+        } else {
+            normal
+        };
+        job.append(&run, 0., format);
+    }
+
+    job
+}
+
+/// Outline of a group frame, drawn with the selection stroke when it has the keyboard focus.
+pub fn focus_stroke(ui: &egui::Ui, is_focused: bool) -> Stroke {
+    if is_focused {
+        ui.visuals().selection.stroke
+    } else {
+        ui.visuals().widgets.noninteractive.bg_stroke
+    }
+}
+
+/// Background of a selected row, transparent otherwise.
+pub fn selection_fill(ui: &egui::Ui, is_selected: bool) -> Color32 {
+    if is_selected {
+        ui.visuals().selection.bg_fill.gamma_multiply(0.4)
+    } else {
+        Color32::TRANSPARENT
+    }
+}
+
+/// Grip icon that starts dragging `item` for reordering.
+pub fn drag_handle(ui: &mut egui::Ui, item: TreeFocus) {
+    let handle = ui
+        .add(Label::new(RichText::new(regular::DOTS_SIX_VERTICAL).size(14.)).sense(Sense::drag()))
+        .on_hover_cursor(CursorIcon::Grab);
+    handle.dnd_set_drag_payload(item);
+    if handle.dragged() {
+        ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
+    }
+}
+
+/// Image, site name and description of a link, the image only once it is cached.
+pub fn link_preview_card(ui: &mut egui::Ui, preview: &LinkPreview) {
+    const MAX_DESCRIPTION_CHARS: usize = 200;
+
+    ui.horizontal(|ui| {
+        if let Some(uri) = preview.image.as_deref().and_then(cached_preview_uri) {
+            ui.add(
+                Image::new(uri)
+                    .max_size(vec2(96., 64.))
+                    .rounding(Rounding::same(5.)),
+            );
+        }
+
+        ui.vertical(|ui| {
+            if let Some(site_name) = &preview.site_name {
+                ui.label(RichText::new(site_name).small().strong());
+            }
+            if let Some(description) = &preview.description {
+                let mut description = description.clone();
+                if let Some((idx, _)) = description.char_indices().nth(MAX_DESCRIPTION_CHARS) {
+                    description.truncate(idx);
+                    description.push('…');
+                }
+                ui.label(RichText::new(description).small());
+            }
+        });
+    });
+}
+
+/// Favicon of a link, or its site's first letter on a color picked from that letter.
+pub fn site_icon(ui: &mut egui::Ui, icon: &SiteIcon) {
+    let size = vec2(16., 16.);
+    match icon {
+        SiteIcon::Cached(uri) => {
+            ui.add(Image::new(uri.clone()).fit_to_exact_size(size));
+        }
+        SiteIcon::Letter(letter) => {
+            let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+            let hue = (*letter as u32 % 12) as f32 / 12.;
+            ui.painter()
+                .rect_filled(rect, Rounding::same(4.), Hsva::new(hue, 0.5, 0.6, 1.));
+            ui.painter().text(
+                rect.center(),
+                Align2::CENTER_CENTER,
+                letter,
+                FontId::proportional(11.),
+                Color32::WHITE,
+            );
+        }
+    }
+}
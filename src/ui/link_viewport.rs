@@ -1,25 +1,51 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
-use crossbeam::channel::Sender;
-use egui::{CentralPanel, Frame, Grid, Key, Margin, RichText, Rounding, ViewportCommand};
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use egui::{CentralPanel, Frame, Grid, Key, Margin, RichText, Rounding, TextEdit, ViewportCommand};
+use egui_phosphor::regular;
+use url::Url;
+
+use crate::{
+    backend::{
+        cache::cache_preview_image,
+        models::{LinkModel, LinkPreview},
+        page_meta::PageMeta,
+    },
+    utils::enums::AppMessage,
+};
 
-use crate::{backend::models::LinkModel, utils::enums::AppMessage};
+use super::components::{custom_button, link_preview_card};
 
-use super::components::custom_button;
+/// Pause after the last keystroke in the URL field before its page is fetched.
+const FETCH_DELAY: Duration = Duration::from_millis(600);
 
-#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(Default, Debug, Clone)]
 pub struct LinkViewport {
     topic_name: String,
 
-    old_title: String,
-    old_url: String,
+    old_link: LinkModel,
 
     new_title: String,
     new_url: String,
-    preview: Option<String>,
+    new_tags: String,
+    new_notes: String,
+    preview: Option<LinkPreview>,
+
+    /// Every saved link as `(topic name, link)`, to warn about duplicates
+    existing_links: Vec<(String, LinkModel)>,
+
+    url_changed_at: Option<Instant>,
+    fetched_url: String,
+    /// Title filled in from the page, replaced by the next fetch unless the user edits it
+    fetched_title: String,
+    fetch_rx: Option<Receiver<Result<PageMeta, String>>>,
+    fetch_error: Option<String>,
 
     is_editing: bool,
 }
@@ -29,12 +55,8 @@ impl LinkViewport {
         self.topic_name = header;
     }
 
-    pub fn set_old_title(&mut self, title: String) {
-        self.old_title = title;
-    }
-
-    pub fn set_old_url(&mut self, url: String) {
-        self.old_url = url;
+    pub fn set_old_link(&mut self, link: LinkModel) {
+        self.old_link = link;
     }
 
     pub fn set_new_title(&mut self, title: String) {
@@ -45,11 +67,106 @@ impl LinkViewport {
         self.new_url = url;
     }
 
+    pub fn set_new_tags(&mut self, tags: Vec<String>) {
+        self.new_tags = tags.join(", ");
+    }
+
+    pub fn set_preview(&mut self, preview: Option<LinkPreview>) {
+        self.preview = preview;
+    }
+
+    pub fn set_new_notes(&mut self, notes: String) {
+        self.new_notes = notes;
+    }
+
+    pub fn set_existing_links(&mut self, links: Vec<(String, LinkModel)>) {
+        self.existing_links = links;
+    }
+
+    /// Saved links with the same URL as the one being entered, other than the one being edited.
+    fn duplicates(&self) -> Vec<&(String, LinkModel)> {
+        if self.new_url.trim().is_empty() {
+            return vec![];
+        }
+
+        self.existing_links
+            .iter()
+            .filter(|(name, link)| {
+                link.same_url(&self.new_url)
+                    && !(self.is_editing && name == &self.topic_name && link == &self.old_link)
+            })
+            .collect()
+    }
+
     pub fn set_is_editing(&mut self, is_editing: bool) {
         self.is_editing = is_editing;
     }
 
+    /// Picks up a finished page fetch and starts one once the URL stopped changing.
+    fn poll_page_fetch(&mut self, ctx: &egui::Context) {
+        if let Some(rx) = &self.fetch_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.fetch_rx = None;
+                match result {
+                    Ok(meta) => {
+                        let can_replace =
+                            self.new_title.is_empty() || self.new_title == self.fetched_title;
+                        if let (Some(title), true) = (meta.title.clone(), can_replace) {
+                            self.new_title = title.clone();
+                            self.fetched_title = title;
+                        }
+                        self.preview = meta.preview();
+                    }
+                    Err(e) => self.fetch_error = Some(e),
+                }
+            }
+        }
+
+        if let Some(changed_at) = self.url_changed_at {
+            let elapsed = changed_at.elapsed();
+            if elapsed < FETCH_DELAY {
+                ctx.request_repaint_after(FETCH_DELAY - elapsed);
+            } else {
+                self.url_changed_at = None;
+                self.start_page_fetch(ctx);
+            }
+        }
+    }
+
+    fn start_page_fetch(&mut self, ctx: &egui::Context) {
+        let url = self.new_url.trim().to_owned();
+        let is_web_url = Url::parse(&url).is_ok_and(|u| matches!(u.scheme(), "http" | "https"));
+        if !is_web_url || url == self.fetched_url {
+            return;
+        }
+
+        self.fetched_url = url.clone();
+        self.fetch_error = None;
+
+        // * A newer fetch replaces the receiver, so stale results are dropped
+        let (tx, rx) = unbounded();
+        self.fetch_rx = Some(rx);
+
+        let ctx = ctx.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = PageMeta::fetch(&url);
+            let image = result.as_ref().ok().and_then(|meta| meta.preview()?.image);
+            let _ = tx.send(result);
+            ctx.request_repaint();
+
+            // * The card shows the image once it is on disk
+            if let Some(image) = image {
+                match cache_preview_image(&image) {
+                    Ok(_) => ctx.request_repaint(),
+                    Err(e) => eprintln!("Failed to cache preview image {}: {}", image, e),
+                }
+            }
+        });
+    }
+
     pub fn ui(&mut self, ctx: &egui::Context, is_open: &Arc<AtomicBool>, tx: &Sender<AppMessage>) {
+        self.poll_page_fetch(ctx);
+
         CentralPanel::default().show(ctx, |ui| {
             ui.add_space(9.);
 
@@ -72,7 +189,12 @@ impl LinkViewport {
                 .show(ui, |ui| {
 
                     ui.label("Title:");
-                    ui.text_edit_singleline(&mut self.new_title);
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.new_title);
+                        if self.fetch_rx.is_some() {
+                            ui.spinner().on_hover_text("Fetching the page title");
+                        }
+                    });
                     if ui.input(|i| i.key_pressed(Key::Enter)) {
                         self.send_link(ctx, tx);
                     }
@@ -80,7 +202,27 @@ impl LinkViewport {
                     ui.end_row();
 
                     ui.label("URL:");
-                    ui.text_edit_singleline(&mut self.new_url);
+                    if ui.text_edit_singleline(&mut self.new_url).changed() {
+                        self.url_changed_at = Some(Instant::now());
+                    }
+                    if ui.input(|i| i.key_pressed(Key::Enter)) {
+                        self.send_link(ctx, tx);
+                    }
+
+                    ui.end_row();
+
+                    ui.label("Tags:");
+                    ui.add(
+                        TextEdit::singleline(&mut self.new_tags).hint_text("Comma separated"),
+                    );
+                    if ui.input(|i| i.key_pressed(Key::Enter)) {
+                        self.send_link(ctx, tx);
+                    }
+
+                    ui.end_row();
+
+                    ui.label("Notes:");
+                    ui.add(TextEdit::singleline(&mut self.new_notes).hint_text("Optional"));
                     if ui.input(|i| i.key_pressed(Key::Enter)) {
                         self.send_link(ctx, tx);
                     }
@@ -88,11 +230,58 @@ impl LinkViewport {
                     ui.end_row();
 
                     ui.label("Preview:");
-                    ui.label("Not implemented yet.");
+                    let mut refresh = false;
+                    ui.vertical(|ui| {
+                        match &self.preview {
+                            Some(preview) => link_preview_card(ui, preview),
+                            None if self.fetch_rx.is_some() => {
+                                ui.label(RichText::new("Fetching...").weak());
+                            }
+                            None => {
+                                ui.label(RichText::new("No preview").weak());
+                            }
+                        }
+                        refresh = ui
+                            .small_button(format!("{} Refresh", regular::ARROW_CLOCKWISE))
+                            .clicked();
+                    });
+                    if refresh {
+                        self.fetched_url.clear();
+                        self.start_page_fetch(ctx);
+                    }
 
                     ui.end_row();
                 });
 
+            if let Some(e) = &self.fetch_error {
+                ui.add_space(5.);
+                ui.vertical_centered(|ui| {
+                    ui.label(RichText::new(format!("Couldn't fetch the page: {}", e)).small().weak());
+                });
+            }
+
+            let duplicates = self.duplicates();
+            if let Some((name, link)) = duplicates.first() {
+                ui.add_space(5.);
+                ui.vertical_centered(|ui| {
+                    let text = if name == &self.topic_name {
+                        format!(
+                            "{} Already saved here as \"{}\", it will be ignored",
+                            regular::WARNING,
+                            link.title
+                        )
+                    } else {
+                        format!(
+                            "{} Already saved in \"{}\" as \"{}\"",
+                            regular::WARNING,
+                            name,
+                            link.title
+                        )
+                    };
+                    ui.label(RichText::new(text).color(ui.visuals().warn_fg_color));
+                });
+            }
+
             ui.add_space(9.);
 
             ui.horizontal(|ui| {
@@ -130,7 +319,7 @@ impl LinkViewport {
                             "Press 'Enter' or click on button after filling the fields to add link.\n\n",
                         ));
                         ui.label(RichText::new(
-                            "If the new link already exists with is the topic (both title and url), it will be ignored.",
+                            "If the topic already has a link to the same page, the new one will be ignored. URLs are compared without the scheme, 'www.', trailing slashes, fragments and query order.",
                         ));
                     });
                 });
@@ -145,17 +334,37 @@ impl LinkViewport {
 
     fn send_link(&mut self, ctx: &egui::Context, tx: &Sender<AppMessage>) {
         if !self.new_title.is_empty() && !self.new_url.is_empty() {
-            let link = LinkModel::new(
-                self.new_title.clone(),
-                self.new_url.clone(),
-                self.preview.clone(),
-            );
+            let tags = self
+                .new_tags
+                .split(',')
+                .map(|tag| tag.trim().to_owned())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            let link = LinkModel {
+                title: self.new_title.clone(),
+                url: self.new_url.clone(),
+                preview: self.preview.clone().map(Box::new),
+                tags,
+                notes: self.new_notes.trim().to_owned(),
+                // * A new URL has not been checked or archived yet
+                health: self
+                    .old_link
+                    .health
+                    .clone()
+                    .filter(|_| self.new_url == self.old_link.url),
+                archived_at: self
+                    .old_link
+                    .archived_at
+                    .filter(|_| self.new_url == self.old_link.url),
+                resolved_url: self
+                    .old_link
+                    .resolved_url
+                    .clone()
+                    .filter(|_| self.new_url == self.old_link.url),
+                ..self.old_link.clone()
+            };
             let msg = if self.is_editing {
-                AppMessage::EditLink(
-                    self.topic_name.clone(),
-                    LinkModel::new(self.old_title.clone(), self.old_url.clone(), None),
-                    link.clone(),
-                )
+                AppMessage::EditLink(self.topic_name.clone(), self.old_link.clone(), link)
             } else {
                 AppMessage::AddLink(self.topic_name.clone(), link)
             };
@@ -174,12 +383,19 @@ impl LinkViewport {
     fn exit_viewport(&mut self, ctx: &egui::Context, should_exit: bool) {
         self.new_title.clear();
         self.new_url.clear();
+        self.new_tags.clear();
+        self.new_notes.clear();
         self.preview = None;
+        self.existing_links.clear();
+        self.url_changed_at = None;
+        self.fetched_url.clear();
+        self.fetched_title.clear();
+        self.fetch_rx = None;
+        self.fetch_error = None;
 
         if self.is_editing || should_exit {
             self.topic_name.clear();
-            self.old_title.clear();
-            self.old_url.clear();
+            self.old_link = LinkModel::default();
             self.is_editing = false;
             ctx.send_viewport_cmd(ViewportCommand::Close);
         }
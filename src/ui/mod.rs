@@ -1,4 +1,7 @@
 pub mod about;
+pub mod command_palette;
 pub mod link_viewport;
+pub mod reader_viewport;
+pub mod toast;
 pub mod topic_viewport;
 pub mod components;
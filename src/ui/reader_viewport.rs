@@ -0,0 +1,318 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use egui::{
+    vec2, Align, CentralPanel, Frame, Image, Label, Layout, Margin, RichText, Rounding, ScrollArea,
+    TopBottomPanel, Vec2,
+};
+use egui_phosphor::regular;
+
+use crate::{
+    backend::{
+        archive::link_id,
+        models::LinkModel,
+        readability::ArticleBlock,
+        reader::{load_article, reading_position, save_reading_position, Article},
+    },
+    utils::{enums::AppMessage, open_urls},
+};
+
+use super::components::custom_button;
+
+/// Pause after scrolling stops before the position is saved.
+const SAVE_DELAY: Duration = Duration::from_millis(1000);
+/// How long the saved position is scrolled back to while images above it still load.
+const RESTORE_TIME: Duration = Duration::from_secs(3);
+/// Longer lines are hard to follow.
+const MAX_TEXT_WIDTH: f32 = 680.;
+
+#[derive(Default, Debug, Clone)]
+pub struct ReaderViewport {
+    topic_name: String,
+    link: LinkModel,
+
+    article: Option<Result<Article, String>>,
+    load_rx: Option<Receiver<Result<Article, String>>>,
+
+    /// Saved position to scroll back to and when that started
+    restore: Option<(f32, Instant)>,
+    offset: f32,
+    scrolled_at: Option<Instant>,
+}
+
+impl ReaderViewport {
+    /// Shows `link`, read from where it was left.
+    pub fn set_link(&mut self, ctx: &egui::Context, topic_name: String, link: LinkModel) {
+        self.save_position();
+        self.forget_images(ctx);
+
+        self.restore = reading_position(&link.url).map(|offset| (offset, Instant::now()));
+        self.offset = 0.;
+        self.topic_name = topic_name;
+        self.link = link;
+        self.article = None;
+
+        // * A newer load replaces the receiver, so stale results are dropped
+        let (tx, rx) = unbounded();
+        self.load_rx = Some(rx);
+
+        let url = self.link.url.clone();
+        let ctx = ctx.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(load_article(&url));
+            ctx.request_repaint();
+        });
+    }
+
+    fn save_position(&mut self) {
+        if self.scrolled_at.take().is_some() {
+            save_reading_position(&self.link.url, self.offset);
+        }
+    }
+
+    /// URI the decoded image of block `idx` is loaded under.
+    fn image_uri(&self, idx: usize, src: &str) -> String {
+        // * The SVG loader only takes URIs ending in `.svg`
+        let extension = if src.starts_with("data:image/svg") {
+            ".svg"
+        } else {
+            ""
+        };
+        format!(
+            "bytes://reader/{}/{}{}",
+            link_id(&self.link.url),
+            idx,
+            extension
+        )
+    }
+
+    /// Drops the decoded images of the current article from the loaders.
+    fn forget_images(&self, ctx: &egui::Context) {
+        if let Some(Ok(article)) = &self.article {
+            for (idx, block) in article.blocks.iter().enumerate() {
+                if let (ArticleBlock::Image { src, .. }, true) =
+                    (block, article.images.contains_key(&idx))
+                {
+                    ctx.forget_image(&self.image_uri(idx, src));
+                }
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context, is_open: &Arc<AtomicBool>, tx: &Sender<AppMessage>) {
+        if let Some(rx) = &self.load_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.load_rx = None;
+                self.article = Some(result);
+            }
+        }
+
+        TopBottomPanel::top("reader_top_panel").show(ctx, |ui| {
+            ui.add_space(5.);
+            ui.horizontal(|ui| {
+                let read_label = if self.link.is_read() {
+                    "Mark as unread"
+                } else {
+                    "Mark as read"
+                };
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    custom_button(ui, read_label, None, || {
+                        let read = !self.link.is_read();
+                        tx.send(AppMessage::MarkRead(
+                            vec![(self.topic_name.clone(), self.link.url.clone())],
+                            read,
+                        ))
+                        .expect("Unable to send");
+                        self.link.read_at = read.then(Utc::now);
+                    });
+                    custom_button(ui, "Open in browser", None, || {
+                        open_urls(std::slice::from_ref(&self.link.url));
+                        tx.send(AppMessage::MarkOpened(vec![self.link.url.clone()]))
+                            .expect("Unable to send");
+                    });
+
+                    ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+                        ui.add(
+                            Label::new(RichText::new(&self.link.title).strong().size(16.))
+                                .truncate(true),
+                        )
+                        .on_hover_text(&self.link.url);
+                    });
+                });
+            });
+            ui.add_space(5.);
+        });
+
+        let restore = self.restore;
+        let offset = CentralPanel::default()
+            .show(ctx, |ui| match &self.article {
+                None => {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(20.);
+                        ui.spinner();
+                        ui.label(RichText::new("Loading the page...").weak());
+                    });
+                    None
+                }
+                Some(Err(e)) => {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(20.);
+                        ui.label(format!(
+                            "{} Couldn't read the page: {}",
+                            regular::WARNING,
+                            e
+                        ));
+                    });
+                    None
+                }
+                Some(Ok(article)) => {
+                    let mut scroll_area = ScrollArea::vertical().auto_shrink([false, false]);
+                    if let Some((offset, _)) = restore {
+                        scroll_area = scroll_area.vertical_scroll_offset(offset);
+                    }
+
+                    let output = scroll_area.show(ui, |ui| {
+                        let width = (ui.available_width() - 18.).min(MAX_TEXT_WIDTH);
+                        let margin = (ui.available_width() - width) / 2.;
+                        Frame::none()
+                            .inner_margin(Margin::symmetric(margin, 9.))
+                            .show(ui, |ui| {
+                                ui.set_max_width(width);
+                                if !article.is_archived {
+                                    ui.label(
+                                        RichText::new(
+                                            "Read from the live page, archive it to read offline",
+                                        )
+                                        .small()
+                                        .weak(),
+                                    );
+                                    ui.add_space(5.);
+                                }
+                                for (idx, block) in article.blocks.iter().enumerate() {
+                                    self.show_block(ui, article, idx, block, width);
+                                }
+                            });
+                    });
+                    Some(output.state.offset.y)
+                }
+            })
+            .inner;
+
+        // * Until the saved position is reached, the user scrolls or it takes too long
+        if let (Some((target, started)), Some(offset)) = (self.restore, offset) {
+            let user_scrolled = ctx.input(|i| i.raw_scroll_delta != Vec2::ZERO);
+            if offset >= target - 1. || user_scrolled || started.elapsed() > RESTORE_TIME {
+                self.restore = None;
+            } else {
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+        }
+        if let (None, Some(offset)) = (self.restore, offset) {
+            if (offset - self.offset).abs() > 0.5 {
+                self.offset = offset;
+                self.scrolled_at = Some(Instant::now());
+            }
+        }
+
+        if let Some(scrolled_at) = self.scrolled_at {
+            let elapsed = scrolled_at.elapsed();
+            if elapsed < SAVE_DELAY {
+                ctx.request_repaint_after(SAVE_DELAY - elapsed);
+            } else {
+                self.save_position();
+            }
+        }
+
+        // * Close viewport on close button
+        if ctx.input(|i| i.viewport().close_requested()) {
+            self.save_position();
+            self.forget_images(ctx);
+            self.article = None;
+            // Tell parent to close us.
+            is_open.store(false, Ordering::Relaxed);
+        }
+    }
+
+    fn show_block(
+        &self,
+        ui: &mut egui::Ui,
+        article: &Article,
+        idx: usize,
+        block: &ArticleBlock,
+        width: f32,
+    ) {
+        match block {
+            ArticleBlock::Heading(level, text) => {
+                ui.add_space(9.);
+                let size = 26. - 2. * *level as f32;
+                ui.add(Label::new(RichText::new(text).strong().size(size)).wrap(true));
+                ui.add_space(3.);
+            }
+            ArticleBlock::Paragraph(text) => {
+                ui.add(Label::new(RichText::new(text).size(15.)).wrap(true));
+                ui.add_space(7.);
+            }
+            ArticleBlock::ListItem { number, text } => {
+                let marker = match number {
+                    Some(number) => format!("{}.", number),
+                    None => "•".to_owned(),
+                };
+                ui.horizontal_top(|ui| {
+                    ui.add_space(9.);
+                    ui.label(RichText::new(marker).size(15.));
+                    ui.add(Label::new(RichText::new(text).size(15.)).wrap(true));
+                });
+                ui.add_space(3.);
+            }
+            ArticleBlock::Quote(text) => {
+                Frame::none()
+                    .inner_margin(Margin {
+                        left: 18.,
+                        ..Default::default()
+                    })
+                    .show(ui, |ui| {
+                        ui.add(Label::new(RichText::new(text).size(15.).italics()).wrap(true));
+                    });
+                ui.add_space(7.);
+            }
+            ArticleBlock::Code(code) => {
+                Frame::group(ui.style())
+                    .fill(ui.visuals().code_bg_color)
+                    .rounding(Rounding::same(5.))
+                    .show(ui, |ui| {
+                        ui.set_width(width - 12.);
+                        ScrollArea::horizontal()
+                            .id_source(("reader_code", idx))
+                            .show(ui, |ui| {
+                                ui.add(Label::new(RichText::new(code).monospace()).wrap(false));
+                            });
+                    });
+                ui.add_space(7.);
+            }
+            ArticleBlock::Image { src, alt } => {
+                let image = match article.images.get(&idx) {
+                    Some(bytes) => Image::from_bytes(self.image_uri(idx, src), bytes.clone()),
+                    None if src.starts_with("data:") => return,
+                    None => Image::new(src.clone()),
+                };
+                let response = ui.add(
+                    image
+                        .max_size(vec2(width, 480.))
+                        .rounding(Rounding::same(5.))
+                        .show_loading_spinner(true),
+                );
+                if !alt.is_empty() {
+                    response.on_hover_text(alt);
+                }
+                ui.add_space(7.);
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::Sender;
+use egui::{Align2, Area, Frame, Id, Order, Rounding};
+
+use crate::utils::enums::AppMessage;
+
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+/// A short-lived notice at the bottom of the window, with an optional action button.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+    text: String,
+    action: Option<(String, AppMessage)>,
+    shown_at: Instant,
+}
+
+impl Toast {
+    pub fn new(text: String, action: Option<(String, AppMessage)>) -> Self {
+        Self {
+            text,
+            action,
+            shown_at: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.shown_at.elapsed() >= TOAST_DURATION
+    }
+
+    /// Returns `false` once the toast should be dropped, either expired or acted on.
+    pub fn ui(&self, ctx: &egui::Context, tx: &Sender<AppMessage>) -> bool {
+        if self.is_expired() {
+            return false;
+        }
+
+        let mut is_visible = true;
+        Area::new(Id::new("toast"))
+            .order(Order::Foreground)
+            .anchor(Align2::CENTER_BOTTOM, [0., -15.])
+            .show(ctx, |ui| {
+                Frame::popup(ui.style())
+                    .rounding(Rounding::same(9.))
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(&self.text);
+
+                            if let Some((label, msg)) = &self.action {
+                                ui.separator();
+                                if ui.button(label).clicked() {
+                                    if let Err(e) = tx.send(msg.clone()) {
+                                        eprintln!("Error: {}", e);
+                                    }
+                                    is_visible = false;
+                                }
+                            }
+                        });
+                    });
+            });
+
+        ctx.request_repaint_after(TOAST_DURATION.saturating_sub(self.shown_at.elapsed()));
+
+        is_visible
+    }
+}
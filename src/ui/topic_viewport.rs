@@ -6,7 +6,10 @@ use std::sync::{
 use crossbeam::channel::Sender;
 use egui::{CentralPanel, Frame, Grid, Key, Margin, RichText, Rounding, ViewportCommand};
 
-use crate::{backend::models::TopicModel, utils::enums::AppMessage};
+use crate::{
+    backend::models::{SmartTopicModel, TopicModel},
+    utils::enums::AppMessage,
+};
 
 use super::components::custom_button;
 
@@ -14,6 +17,7 @@ use super::components::custom_button;
 pub struct TopicViewport {
     old_name: String,
     new_name: String,
+    query: Option<String>,
 
     is_editing: bool,
 }
@@ -27,6 +31,10 @@ impl TopicViewport {
         self.new_name = name;
     }
 
+    pub fn set_query(&mut self, query: Option<String>) {
+        self.query = query;
+    }
+
     pub fn set_editing(&mut self, is_editing: bool) {
         self.is_editing = is_editing;
     }
@@ -37,11 +45,15 @@ impl TopicViewport {
 
             ui.vertical_centered(|ui| {
                 ui.label(
-                    RichText::new("Add Topic")
-                        .strong()
-                        .size(24.)
-                        .heading()
-                        .extra_letter_spacing(1.),
+                    RichText::new(if self.query.is_some() {
+                        "Add Smart Topic"
+                    } else {
+                        "Add Topic"
+                    })
+                    .strong()
+                    .size(24.)
+                    .heading()
+                    .extra_letter_spacing(1.),
                 );
             });
 
@@ -60,6 +72,13 @@ impl TopicViewport {
                     }
 
                     ui.end_row();
+
+                    if let Some(query) = &mut self.query {
+                        ui.label("Query:");
+                        ui.text_edit_singleline(query);
+
+                        ui.end_row();
+                    }
                 });
 
             ui.add_space(9.);
@@ -114,7 +133,12 @@ impl TopicViewport {
     fn send_topic(&mut self, ctx: &egui::Context, tx: &Sender<AppMessage>) {
         if !self.new_name.is_empty() {
             let new_topic = TopicModel::new(self.new_name.clone());
-            let msg = if self.is_editing {
+            let msg = if let Some(query) = &self.query {
+                AppMessage::AddSmartTopic(SmartTopicModel::new(
+                    self.new_name.clone(),
+                    query.clone(),
+                ))
+            } else if self.is_editing {
                 let old_topic = TopicModel::new(self.old_name.clone());
                 AppMessage::EditTopic(old_topic, new_topic)
             } else {
@@ -135,8 +159,9 @@ impl TopicViewport {
     fn clear_exit_viewport(&mut self, ctx: &egui::Context, should_exit: bool) {
         self.new_name.clear();
 
-        if self.is_editing || should_exit {
+        if self.is_editing || self.query.is_some() || should_exit {
             self.is_editing = false;
+            self.query = None;
             ctx.send_viewport_cmd(ViewportCommand::Close);
         }
     }
@@ -2,12 +2,80 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
-use crate::backend::models::{LinkModel, TopicModel};
+#[cfg(target_os = "linux")]
+use crate::backend::dbus::DbusRequest;
+use crate::backend::{
+    jobs::{JobId, JobOutput},
+    models::{LinkHealth, LinkModel, SmartTopicModel, TopicModel, TrashEntry},
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppPage {
     Main,
     Settings,
+    Trash,
+    Duplicates,
+    CleanLinks,
+    LinkReport,
+}
+
+/// Sections of the link report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkReportTab {
+    Broken,
+    Redirected,
+    Ok,
+}
+
+impl LinkReportTab {
+    pub fn of(health: &LinkHealth) -> Self {
+        if health.is_broken() {
+            LinkReportTab::Broken
+        } else if health.is_redirected() {
+            LinkReportTab::Redirected
+        } else {
+            LinkReportTab::Ok
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            LinkReportTab::Broken => "Broken",
+            LinkReportTab::Redirected => "Redirected",
+            LinkReportTab::Ok => "OK",
+        }
+    }
+
+    pub fn actions(&self) -> &'static [LinkReportAction] {
+        match self {
+            LinkReportTab::Broken => &[LinkReportAction::MoveToBroken, LinkReportAction::Delete],
+            LinkReportTab::Redirected => &[LinkReportAction::Update],
+            LinkReportTab::Ok => &[],
+        }
+    }
+}
+
+/// What the buttons of the link report do to their links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkReportAction {
+    /// Points redirected links to where they lead
+    Update,
+    MoveToBroken,
+    Delete,
+}
+
+impl LinkReportAction {
+    /// Label of the button of a link, or of every link of the tab when `all`.
+    pub fn label(&self, all: bool) -> &str {
+        match (self, all) {
+            (LinkReportAction::Update, false) => "Update",
+            (LinkReportAction::Update, true) => "Update all",
+            (LinkReportAction::MoveToBroken, false) => "Move",
+            (LinkReportAction::MoveToBroken, true) => "Move all to Broken",
+            (LinkReportAction::Delete, false) => "Delete",
+            (LinkReportAction::Delete, true) => "Delete all",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -16,20 +84,153 @@ pub enum AppMessage {
     AddTopic(TopicModel),
     EditTopic(TopicModel, TopicModel),
     RemoveTopic(TopicModel),
+    ReorderTopic(usize, usize),
+    RestoreTopic(usize, TopicModel, Vec<LinkModel>),
 
     // Link
     AddLink(String, LinkModel),
     EditLink(String, LinkModel, LinkModel),
+    EditLinks(Vec<(String, LinkModel, LinkModel)>),
     RemoveLink(String, LinkModel),
+    ReorderLink(String, usize, usize),
+    MoveLinks(String, Vec<LinkModel>, String),
+    /// Moves a link to another topic at an index, as dropping it on a row does
+    MoveLinkTo(String, LinkModel, String, usize),
+    CopyLinks(Vec<LinkModel>, String),
+    RemoveLinks(Vec<(String, LinkModel)>),
+    TagLinks(Vec<(String, LinkModel)>, String),
+    RestoreLinks(Vec<(String, usize, LinkModel)>),
+    MergeDuplicates(Vec<Vec<(String, LinkModel)>>),
+    MarkOpened(Vec<String>),
+    /// Links as `(topic name, url)` and whether they are now read
+    MarkRead(Vec<(String, String)>, bool),
+    /// Topics read from an exported file, merged into the bookmarks
+    ImportTopics(Vec<(String, Vec<LinkModel>)>),
+
+    // Smart topic
+    AddSmartTopic(SmartTopicModel),
+    RemoveSmartTopic(SmartTopicModel),
+    RestoreSmartTopic(usize, SmartTopicModel),
+
+    // Trash
+    RestoreFromTrash(TrashEntry),
+    /// Undoes a restore, with whether the restore created the topic
+    ReturnToTrash(TrashEntry, bool),
+    DeleteFromTrash(TrashEntry),
+    EmptyTrash,
+
+    // History
+    Undo,
+    Redo,
 
     // UI
-    ToggleCollapsed(usize),
+    SetExpanded(usize, bool),
+    OpenAddTopic,
+    OpenEditTopic(String),
+    OpenAddLink(String),
+    OpenEditLink(String, LinkModel),
+    OpenReader(String, LinkModel),
+    /// In the topic, or in any topic when `None`
+    OpenNextUnread(Option<String>),
+    ToggleUnreadOnly,
+    OpenLinks(Vec<String>),
+    SetTheme(AppTheme),
+    SetPage(AppPage),
 
     // Misc
     ToggleAlwaysOnTop,
+    SetVisible(bool),
+    BackupBookmarks,
+    RefreshIcons,
+    CheckLinks,
+    /// Follows the redirects of the links to find where they end up
+    ResolveLinks(Vec<(String, LinkModel)>),
+    ArchiveLinks(Vec<(String, LinkModel)>),
+    /// Fills in the titles and previews the links are missing
+    FetchMetadata(Vec<(String, LinkModel)>),
+    ImportBookmarks(PathBuf),
+    ExportBookmarks,
+    ExportLinks(Vec<(String, LinkModel)>),
+
+    // Jobs
+    JobOutput(JobId, JobOutput),
+    CancelJob(JobId),
+
+    // D-Bus
+    #[cfg(target_os = "linux")]
+    Dbus(DbusRequest),
+}
+
+impl AppMessage {
+    pub fn is_bookmark_mutation(&self) -> bool {
+        matches!(
+            self,
+            AppMessage::AddTopic(_)
+                | AppMessage::EditTopic(_, _)
+                | AppMessage::RemoveTopic(_)
+                | AppMessage::ReorderTopic(_, _)
+                | AppMessage::RestoreTopic(_, _, _)
+                | AppMessage::AddLink(_, _)
+                | AppMessage::EditLink(_, _, _)
+                | AppMessage::EditLinks(_)
+                | AppMessage::RemoveLink(_, _)
+                | AppMessage::ReorderLink(_, _, _)
+                | AppMessage::MoveLinks(_, _, _)
+                | AppMessage::MoveLinkTo(_, _, _, _)
+                | AppMessage::CopyLinks(_, _)
+                | AppMessage::RemoveLinks(_)
+                | AppMessage::TagLinks(_, _)
+                | AppMessage::RestoreLinks(_)
+                | AppMessage::MergeDuplicates(_)
+                | AppMessage::MarkRead(_, _)
+                | AppMessage::ImportTopics(_)
+                | AppMessage::RestoreFromTrash(_)
+                | AppMessage::ReturnToTrash(_, _)
+                | AppMessage::Undo
+                | AppMessage::Redo
+        )
+    }
+
+    /// Whether the message may change links or smart topics, which search results and the
+    /// pages listing links have to be built again for. Wider than
+    /// [`Self::is_bookmark_mutation`], as it counts what background jobs record on links.
+    pub fn changes_bookmarks(&self) -> bool {
+        match self {
+            AppMessage::JobOutput(_, output) => output.changes_bookmarks(),
+            AppMessage::MarkOpened(_)
+            | AppMessage::AddSmartTopic(_)
+            | AppMessage::RemoveSmartTopic(_)
+            | AppMessage::RestoreSmartTopic(_, _) => true,
+            msg => msg.is_bookmark_mutation(),
+        }
+    }
+
+    /// Removals are the mutations worth offering an undo for right away.
+    pub fn is_removal(&self) -> bool {
+        matches!(
+            self,
+            AppMessage::RemoveTopic(_)
+                | AppMessage::RemoveLink(_, _)
+                | AppMessage::RemoveLinks(_)
+                | AppMessage::RemoveSmartTopic(_)
+        )
+    }
+}
+
+/// A row of the topic/link tree by topic and link index, for the keyboard cursor and drag-and-drop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeFocus {
+    Topic(usize),
+    Link(usize, usize),
 }
 
-impl AppMessage {}
+impl TreeFocus {
+    pub fn topic_idx(&self) -> usize {
+        match self {
+            TreeFocus::Topic(idx) | TreeFocus::Link(idx, _) => *idx,
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BookmarkItem {
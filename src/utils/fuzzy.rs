@@ -0,0 +1,187 @@
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Char indices of `text` that matched the pattern
+    pub indices: Vec<usize>,
+}
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 12;
+const BONUS_WORD_START: i64 = 10;
+const BONUS_FIRST_CHAR: i64 = 8;
+const PENALTY_GAP: i64 = 2;
+
+/// Case-insensitive subsequence match of `pattern` in `text`.
+///
+/// Every occurrence of the first pattern char starts a window that is then tightened
+/// from its end, and the best scoring window wins. So `"rust"` prefers the word in
+/// `"trust rust"` and `"rs bk"` prefers `"Rust book"` over scattered matches.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<char>>();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch::default());
+    }
+
+    // * Folded like the pattern, a char may lowercase to several, each pointing back at it
+    let mut chars = vec![];
+    let mut lower = vec![];
+    let mut origins = vec![];
+    for (idx, c) in text.chars().enumerate() {
+        for folded in c.to_lowercase() {
+            chars.push(c);
+            lower.push(folded);
+            origins.push(idx);
+        }
+    }
+
+    let mut best: Option<FuzzyMatch> = None;
+    for start in (0..lower.len()).filter(|&idx| lower[idx] == pattern[0]) {
+        // * No later start can match if this one doesn't
+        let Some(indices) = match_window(&pattern, &lower, start) else {
+            break;
+        };
+        let score = score(&chars, &indices);
+
+        let is_better = match &best {
+            Some(best) => score > best.score,
+            None => true,
+        };
+        if is_better {
+            let mut indices = indices
+                .into_iter()
+                .map(|idx| origins[idx])
+                .collect::<Vec<usize>>();
+            indices.dedup();
+            best = Some(FuzzyMatch { score, indices });
+        }
+    }
+
+    best
+}
+
+fn match_window(pattern: &[char], lower: &[char], start: usize) -> Option<Vec<usize>> {
+    // * Forward pass: end of the first window containing the pattern
+    let mut p_idx = 0;
+    let mut end = None;
+    for (idx, c) in lower.iter().enumerate().skip(start) {
+        if *c == pattern[p_idx] {
+            p_idx += 1;
+            if p_idx == pattern.len() {
+                end = Some(idx);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    // * Backward pass: tighten the window from its end
+    let mut indices = Vec::with_capacity(pattern.len());
+    let mut p_idx = pattern.len();
+    for idx in (start..=end).rev() {
+        if lower[idx] == pattern[p_idx - 1] {
+            indices.push(idx);
+            p_idx -= 1;
+            if p_idx == 0 {
+                break;
+            }
+        }
+    }
+    indices.reverse();
+
+    Some(indices)
+}
+
+fn score(chars: &[char], indices: &[usize]) -> i64 {
+    let mut score = 0;
+    for (i, &idx) in indices.iter().enumerate() {
+        score += SCORE_MATCH;
+
+        if idx == 0 {
+            score += BONUS_FIRST_CHAR;
+        }
+        if idx == 0 || !chars[idx - 1].is_alphanumeric() {
+            score += BONUS_WORD_START;
+        } else if chars[idx].is_uppercase() && chars[idx - 1].is_lowercase() {
+            score += BONUS_WORD_START / 2;
+        }
+
+        if i > 0 {
+            let gap = (idx - indices[i - 1] - 1) as i64;
+            if gap == 0 {
+                score += BONUS_CONSECUTIVE;
+            } else {
+                score -= PENALTY_GAP * gap.min(10);
+            }
+        }
+    }
+
+    // * Shorter texts rank higher for the same match
+    score - (chars.len() as i64 / 16).min(8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indices(pattern: &str, text: &str) -> Option<Vec<usize>> {
+        fuzzy_match(pattern, text).map(|m| m.indices)
+    }
+
+    fn score(pattern: &str, text: &str) -> i64 {
+        fuzzy_match(pattern, text).unwrap().score
+    }
+
+    #[test]
+    fn matches_subsequences() {
+        assert_eq!(indices("rbk", "Rust book"), Some(vec![0, 5, 8]));
+        assert_eq!(indices("rs bk", "Rust book"), Some(vec![0, 2, 5, 8]));
+        assert_eq!(indices("kb", "Rust book"), None);
+        assert_eq!(indices("rustt", "Rust"), None);
+        assert_eq!(fuzzy_match("  ", "anything"), Some(FuzzyMatch::default()));
+        assert_eq!(indices("a", ""), None);
+    }
+
+    #[test]
+    fn prefers_the_best_window() {
+        // * The word beats the same letters inside "trust"
+        assert_eq!(indices("rust", "trust rust"), Some(vec![6, 7, 8, 9]));
+        // * The window is tightened from its end
+        assert_eq!(indices("ab", "a_a_b"), Some(vec![2, 4]));
+
+        assert!(score("rs bk", "Rust book") > score("rs bk", "Reads some bookmarks"));
+        assert!(score("rust", "rust") > score("rust", "rust and other things"));
+        assert!(score("book", "the book") > score("book", "the_bo_ok"));
+        assert!(score("gh", "GitHub") > score("gh", "ought"));
+    }
+
+    #[test]
+    fn folds_case_and_unicode() {
+        assert_eq!(indices("RUST", "rust"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(indices("rust", "RUST"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(indices("émile", "ÉMILE"), Some(vec![0, 1, 2, 3, 4]));
+
+        // * `İ` lowercases to two chars, on either side
+        assert_eq!(indices("İstanbul", "İstanbul"), Some((0..8).collect()));
+        assert_eq!(indices("istanbul", "İstanbul"), Some((0..8).collect()));
+        assert_eq!(indices("bul", "İstanbul"), Some(vec![5, 6, 7]));
+    }
+
+    #[test]
+    fn highlights_chars_not_bytes() {
+        // * Indices count chars, so they line up with `text.chars()`
+        let text = "naïve café";
+        let matched = indices("CAFÉ", text).unwrap();
+        assert_eq!(matched, vec![6, 7, 8, 9]);
+        assert_eq!(
+            matched
+                .iter()
+                .map(|idx| text.chars().nth(*idx).unwrap())
+                .collect::<String>(),
+            "café"
+        );
+    }
+}
@@ -1,20 +1,147 @@
-use std::{fs, process::Command};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
+use chrono::{DateTime, Utc};
 use directories::UserDirs;
+use indexmap::IndexMap;
+use url::Url;
 
 #[cfg(target_os = "windows")]
 use {std::os::windows::process::CommandExt, winapi::um::winbase};
 
 use self::enums::OpenLocationType;
+use crate::backend::{file_writer::flush_writes, models::LinkModel};
 
 pub mod enums;
+pub mod fuzzy;
 pub mod run_first_error_app;
 pub mod run_main_app;
 
+/// `Documents/stash`, where bookmarks, settings and backups live. Created if missing.
+pub fn stash_dir() -> PathBuf {
+    let dirs = UserDirs::new().expect("Failed to get user directories");
+    let path = dirs
+        .document_dir()
+        .expect("Failed to get documents directory")
+        .join("stash");
+    if !path.exists() {
+        fs::create_dir_all(&path).expect("Failed to create stash directory");
+    }
+
+    path
+}
+
+/// Rough age such as `just now`, `5 minutes ago` or `3 days ago`.
+pub fn format_age(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let age = now - then;
+    let (amount, unit) = if age.num_days() > 0 {
+        (age.num_days(), "day")
+    } else if age.num_hours() > 0 {
+        (age.num_hours(), "hour")
+    } else if age.num_minutes() > 0 {
+        (age.num_minutes(), "minute")
+    } else {
+        return "just now".to_owned();
+    };
+
+    format!(
+        "{} {}{} ago",
+        amount,
+        unit,
+        if amount == 1 { "" } else { "s" }
+    )
+}
+
 pub fn calc_btn_size_from_text(text: &str) -> f32 {
     text.len() as f32 * 10.0
 }
 
+/// Finds every `http(s)://` URL in `text`, whether it is one per line or embedded in prose.
+pub fn extract_urls(text: &str) -> Vec<String> {
+    let mut urls = vec![];
+
+    let mut rest = text;
+    while let Some(start) = rest.find("http") {
+        let candidate = &rest[start..];
+        if !candidate.starts_with("http://") && !candidate.starts_with("https://") {
+            rest = &candidate[4..];
+            continue;
+        }
+
+        // * Stop at whitespace, quotes and brackets, but keep balanced parentheses
+        let mut depth = 0;
+        let mut end = candidate.len();
+        for (idx, c) in candidate.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth > 0 => depth -= 1,
+                ')' | '"' | '\'' | '<' | '>' | '[' | ']' | '{' | '}' | '`' | '|' => {
+                    end = idx;
+                    break;
+                }
+                c if c.is_whitespace() => {
+                    end = idx;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        let url = candidate[..end].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        if Url::parse(url).is_ok_and(|u| u.has_host()) {
+            urls.push(url.to_string());
+        }
+
+        rest = &candidate[end..];
+    }
+
+    urls
+}
+
+/// Key under which two URLs count as the same link: the scheme of web URLs, a leading `www.`,
+/// trailing slashes and the fragment are ignored, and query parameters are sorted.
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.trim_end_matches('/').to_lowercase();
+    };
+    parsed.set_fragment(None);
+
+    let Some(host) = parsed.host_str() else {
+        return parsed.to_string();
+    };
+    let host = host.trim_start_matches("www.");
+
+    let mut normalized = match parsed.scheme() {
+        "http" | "https" => String::new(),
+        scheme => format!("{}://", scheme),
+    };
+    normalized.push_str(host);
+    if let Some(port) = parsed.port() {
+        normalized.push_str(&format!(":{}", port));
+    }
+    normalized.push_str(parsed.path().trim_end_matches('/'));
+
+    let mut pairs = parsed
+        .query_pairs()
+        .into_owned()
+        .collect::<Vec<(String, String)>>();
+    if !pairs.is_empty() {
+        pairs.sort();
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(pairs)
+            .finish();
+        normalized.push('?');
+        normalized.push_str(&query);
+    }
+
+    normalized
+}
+
 pub fn open_urls(urls: &[String]) {
     for url in urls {
         if let Err(e) = webbrowser::open(url) {
@@ -23,6 +150,28 @@ pub fn open_urls(urls: &[String]) {
     }
 }
 
+/// Opens the file in the app the system uses for its type, like an archived page in the browser.
+pub fn open_file(path: &Path) {
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        let mut cmd = Command::new("explorer");
+        cmd.creation_flags(winbase::CREATE_NO_WINDOW);
+        cmd
+    };
+    #[cfg(target_os = "linux")]
+    let mut cmd = Command::new("xdg-open");
+    #[cfg(target_os = "macos")]
+    let mut cmd = Command::new("open");
+
+    cmd.arg(path);
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        if let Err(e) = cmd.status() {
+            eprintln!("Failed to open file: {:?} with error: {:?}", path, e);
+        }
+    });
+}
+
 pub fn backup_bookmarks() {
     let dirs = UserDirs::new().expect("Failed to get user directories");
     let document_dir = dirs
@@ -31,6 +180,8 @@ pub fn backup_bookmarks() {
         .join("stash");
 
     let bookmarks_file = document_dir.join("bookmarks.json");
+    // * The app saves in the background, the backup must have the latest edits
+    flush_writes();
 
     if !bookmarks_file.exists() {
         println!("Bookmarks file does not exist, skipping backup");
@@ -50,6 +201,45 @@ pub fn backup_bookmarks() {
     open_file_location(OpenLocationType::Custom(backup_file));
 }
 
+/// Groups `(topic name, link)` pairs by topic, keeping the order topics first appear in.
+pub fn group_by_topic(links: &[(String, LinkModel)]) -> Vec<(String, Vec<LinkModel>)> {
+    let mut groups: IndexMap<String, Vec<LinkModel>> = IndexMap::new();
+    for (topic_name, link) in links {
+        groups
+            .entry(topic_name.clone())
+            .or_default()
+            .push(link.clone());
+    }
+
+    groups.into_iter().collect()
+}
+
+/// The links in the `idx_name` layout of bookmarks.json.
+pub fn links_to_json(links: &[(String, LinkModel)]) -> String {
+    let data = group_by_topic(links)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (topic_name, links))| (format!("{}_{}", idx, topic_name), links))
+        .collect::<HashMap<String, Vec<LinkModel>>>();
+
+    serde_json::to_string(&data).expect("Failed to serialize links")
+}
+
+/// Writes `data` to `exports/<name>_<date>.json`. Blocking.
+pub fn write_export(name: &str, data: &str) -> Result<PathBuf, String> {
+    let export_dir = stash_dir().join("exports");
+    fs::create_dir_all(&export_dir).map_err(|e| e.to_string())?;
+
+    let export_file = export_dir.join(format!(
+        "{}_{}.json",
+        name,
+        chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+    ));
+    fs::write(&export_file, data).map_err(|e| e.to_string())?;
+
+    Ok(export_file)
+}
+
 pub fn open_file_location(location: OpenLocationType) {
     let dirs = UserDirs::new().expect("Failed to get user directories");
     let document_dir = dirs
@@ -114,3 +304,35 @@ pub fn open_file_location(location: OpenLocationType) {
             .expect("Failed to open file location");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_urls() {
+        let same = |a: &str, b: &str| normalize_url(a) == normalize_url(b);
+
+        assert!(same("https://a.test/docs/", "https://a.test/docs"));
+        assert!(same("https://a.test/", "https://a.test"));
+        assert!(same("http://a.test/x", "https://a.test/x"));
+        assert!(same("https://A.Test/x", "https://a.test/x"));
+        assert!(same("https://www.a.test/x", "https://a.test/x"));
+        assert!(same("https://a.test/x#part", "https://a.test/x"));
+        assert!(same("https://a.test/?b=2&a=1", "https://a.test?a=1&b=2"));
+        assert!(same(" https://a.test/x ", "https://a.test/x"));
+
+        // * Paths, ports, queries and other schemes still tell links apart
+        assert!(!same("https://a.test/X", "https://a.test/x"));
+        assert!(!same("https://a.test:8080/", "https://a.test/"));
+        assert!(!same("https://a.test/?a=1", "https://a.test/?a=2"));
+        assert!(!same("ftp://a.test/x", "https://a.test/x"));
+        assert!(!same("https://sub.a.test/", "https://a.test/"));
+
+        assert_eq!(
+            normalize_url("https://www.a.test/x/?b=2&a=1#top"),
+            "a.test/x?a=1&b=2"
+        );
+        assert_eq!(normalize_url("Not a URL/"), "not a url");
+    }
+}
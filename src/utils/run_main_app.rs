@@ -1,32 +1,43 @@
 use eframe::{icon_data::from_png_bytes, Theme};
-use egui::{ViewportBuilder, X11WindowType};
+use egui::{ViewportBuilder, WindowLevel, X11WindowType};
 
-use crate::app::StashApp;
+use crate::{app::StashApp, backend::settings::AppSettings};
 
 pub fn run_main_app() -> Result<(), eframe::Error> {
+    let settings = AppSettings::load(cfg!(debug_assertions));
+
     let min_size = [350.0, 500.0];
+    let mut viewport = ViewportBuilder::default()
+        .with_inner_size(settings.window_size.unwrap_or(min_size))
+        .with_min_inner_size(min_size)
+        .with_decorations(true)
+        .with_transparent(false)
+        .with_close_button(true)
+        .with_maximize_button(true)
+        .with_minimize_button(true)
+        .with_drag_and_drop(false)
+        .with_active(true)
+        .with_resizable(true)
+        .with_taskbar(true)
+        .with_visible(true)
+        .with_icon(
+            from_png_bytes(include_bytes!("../../assets/stash.png")).expect("Failed to load icon"),
+        )
+        .with_app_id("io.github.aymanfarsi.stash")
+        .with_window_type(X11WindowType::Normal)
+        .with_window_level(if settings.always_on_top {
+            WindowLevel::AlwaysOnTop
+        } else {
+            WindowLevel::Normal
+        });
+    if let Some(pos) = settings.window_pos {
+        viewport = viewport.with_position(pos);
+    }
+
     let options = eframe::NativeOptions {
-        viewport: ViewportBuilder::default()
-            .with_inner_size(min_size)
-            .with_min_inner_size(min_size)
-            .with_decorations(true)
-            .with_transparent(false)
-            .with_close_button(true)
-            .with_maximize_button(true)
-            .with_minimize_button(true)
-            .with_drag_and_drop(false)
-            .with_active(true)
-            .with_resizable(true)
-            .with_taskbar(true)
-            .with_visible(true)
-            .with_icon(
-                from_png_bytes(include_bytes!("../../assets/stash.png"))
-                    .expect("Failed to load icon"),
-            )
-            .with_app_id("io.github.aymanfarsi.stash")
-            .with_window_type(X11WindowType::Normal),
+        viewport,
         default_theme: Theme::Dark,
-        centered: true,
+        centered: settings.window_pos.is_none(),
         vsync: true,
         ..Default::default()
     };
@@ -35,7 +46,7 @@ pub fn run_main_app() -> Result<(), eframe::Error> {
         "Stash",
         options,
         Box::new(move |_cc| {
-            let app = StashApp::new();
+            let app = StashApp::new(settings);
 
             Box::new(app)
         }),